@@ -0,0 +1,130 @@
+// Providers fail with a `Status`, matching the signature of tonic interceptors.
+#![allow(clippy::result_large_err)]
+
+use std::sync::{Arc, RwLock};
+use tonic::{
+    metadata::{errors::InvalidMetadataValue, AsciiMetadataKey, AsciiMetadataValue, MetadataMap},
+    service::Interceptor,
+    Request, Status,
+};
+
+/// Adds credentials or other metadata to every outgoing request.
+///
+/// Providers are called on each request, so implementations can hand out
+/// credentials that change over time without rebuilding the client.
+pub trait AuthProvider: Send + Sync {
+    fn apply(&self, metadata: &mut MetadataMap) -> Result<(), Status>;
+}
+
+impl<F> AuthProvider for F
+where
+    F: Fn(&mut MetadataMap) -> Result<(), Status> + Send + Sync,
+{
+    fn apply(&self, metadata: &mut MetadataMap) -> Result<(), Status> {
+        self(metadata)
+    }
+}
+
+/// Fixed metadata entries, e.g. a static `x-token` or `authorization` header.
+#[derive(Debug, Clone, Default)]
+pub struct StaticMetadata {
+    entries: Vec<(AsciiMetadataKey, AsciiMetadataValue)>,
+}
+
+impl StaticMetadata {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn insert(mut self, key: AsciiMetadataKey, value: AsciiMetadataValue) -> Self {
+        self.entries.push((key, value));
+        self
+    }
+
+    pub fn bearer(self, token: &str) -> Result<Self, InvalidMetadataValue> {
+        let value = format!("Bearer {token}").parse()?;
+
+        Ok(self.insert(AsciiMetadataKey::from_static("authorization"), value))
+    }
+}
+
+impl AuthProvider for StaticMetadata {
+    fn apply(&self, metadata: &mut MetadataMap) -> Result<(), Status> {
+        for (key, value) in self.entries.iter() {
+            metadata.insert(key.clone(), value.clone());
+        }
+
+        Ok(())
+    }
+}
+
+/// A single metadata entry whose value can be replaced at runtime.
+///
+/// Clones share the value, so one clone can be handed to the client while
+/// another is kept by the task that refreshes the credential.
+#[derive(Debug, Clone)]
+pub struct RotatingMetadata {
+    key: AsciiMetadataKey,
+    value: Arc<RwLock<Option<AsciiMetadataValue>>>,
+}
+
+impl RotatingMetadata {
+    pub fn new(key: AsciiMetadataKey) -> Self {
+        Self {
+            key,
+            value: Arc::default(),
+        }
+    }
+
+    /// An `authorization` entry, to be filled with [`RotatingMetadata::set_bearer`].
+    pub fn authorization() -> Self {
+        Self::new(AsciiMetadataKey::from_static("authorization"))
+    }
+
+    pub fn set(&self, value: &str) -> Result<(), InvalidMetadataValue> {
+        *self.value.write().unwrap() = Some(value.parse()?);
+
+        Ok(())
+    }
+
+    pub fn set_bearer(&self, token: &str) -> Result<(), InvalidMetadataValue> {
+        self.set(&format!("Bearer {token}"))
+    }
+
+    /// Stops sending the entry until a new value is set.
+    pub fn clear(&self) {
+        *self.value.write().unwrap() = None;
+    }
+}
+
+impl AuthProvider for RotatingMetadata {
+    fn apply(&self, metadata: &mut MetadataMap) -> Result<(), Status> {
+        if let Some(value) = self.value.read().unwrap().clone() {
+            metadata.insert(self.key.clone(), value);
+        }
+
+        Ok(())
+    }
+}
+
+/// Interceptor that runs a list of [`AuthProvider`]s in order on every request.
+#[derive(Clone, Default)]
+pub struct AuthInterceptor {
+    providers: Vec<Arc<dyn AuthProvider>>,
+}
+
+impl AuthInterceptor {
+    pub fn new(providers: Vec<Arc<dyn AuthProvider>>) -> Self {
+        Self { providers }
+    }
+}
+
+impl Interceptor for AuthInterceptor {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        for provider in self.providers.iter() {
+            provider.apply(request.metadata_mut())?;
+        }
+
+        Ok(request)
+    }
+}
@@ -0,0 +1,69 @@
+use prost::Message;
+use std::{marker::PhantomData, sync::Arc};
+use tonic::{
+    codec::{Codec, DecodeBuf, Decoder},
+    Status,
+};
+
+/// tonic's prost codec, except that decode failures keep the
+/// [`prost::DecodeError`] as the source of the returned status.
+///
+/// Generated clients use it through `tonic_build`'s `codec_path`, so decode
+/// failures can be told apart from other `Internal` statuses, see
+/// [`is_decode_error`].
+#[derive(Debug)]
+pub struct ProstCodec<T, U> {
+    inner: tonic::codec::ProstCodec<T, U>,
+}
+
+impl<T, U> Default for ProstCodec<T, U> {
+    fn default() -> Self {
+        Self {
+            inner: tonic::codec::ProstCodec::default(),
+        }
+    }
+}
+
+impl<T, U> Codec for ProstCodec<T, U>
+where
+    T: Message + Send + 'static,
+    U: Message + Default + Send + 'static,
+{
+    type Encode = T;
+    type Decode = U;
+    type Encoder = <tonic::codec::ProstCodec<T, U> as Codec>::Encoder;
+    type Decoder = ProstDecoder<U>;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        self.inner.encoder()
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        ProstDecoder(PhantomData)
+    }
+}
+
+#[derive(Debug)]
+pub struct ProstDecoder<U>(PhantomData<U>);
+
+impl<U: Message + Default> Decoder for ProstDecoder<U> {
+    type Item = U;
+    type Error = Status;
+
+    fn decode(&mut self, buf: &mut DecodeBuf<'_>) -> Result<Option<Self::Item>, Self::Error> {
+        match U::decode(buf) {
+            Ok(item) => Ok(Some(item)),
+            Err(error) => {
+                let mut status = Status::internal(error.to_string());
+                status.set_source(Arc::new(error));
+                Err(status)
+            }
+        }
+    }
+}
+
+/// Whether `status` was returned by [`ProstDecoder`] for a message that
+/// failed to decode.
+pub fn is_decode_error(status: &Status) -> bool {
+    std::error::Error::source(status).is_some_and(|source| source.is::<prost::DecodeError>())
+}
@@ -0,0 +1,65 @@
+use tonic::{codegen::http::uri::InvalidUri, metadata::MetadataMap, Code, Status};
+
+use crate::codec::is_decode_error;
+
+/// Errors of the gRPC clients built on this crate.
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    #[error("invalid endpoint: {0}")]
+    InvalidEndpoint(#[from] InvalidUri),
+    #[error("tls configuration failed: {0}")]
+    Tls(#[source] tonic::transport::Error),
+    #[error("failed to connect: {0}")]
+    Transport(#[source] tonic::transport::Error),
+    #[error("authentication rejected: {}", .0.message())]
+    Unauthenticated(Box<Status>),
+    /// A received message failed to decode, see [`crate::codec::ProstCodec`].
+    #[error("failed to decode message: {}", .0.message())]
+    Decode(Box<Status>),
+    #[error("request failed with {}: {}", .0.code(), .0.message())]
+    Status(Box<Status>),
+    /// The server ended a stream that was expected to stay open.
+    #[error("stream closed by server")]
+    StreamClosed,
+    /// The request side of a bidirectional stream is closed.
+    #[error("subscription is closed")]
+    SubscriptionClosed,
+    /// A multi-endpoint subscription was given no endpoints.
+    #[error("no endpoints given")]
+    NoEndpoints,
+    #[cfg(feature = "recording")]
+    #[error("failed to open recording: {0}")]
+    Recording(#[source] std::io::Error),
+}
+
+impl From<Status> for ClientError {
+    fn from(status: Status) -> Self {
+        match status.code() {
+            Code::Unauthenticated | Code::PermissionDenied => {
+                Self::Unauthenticated(Box::new(status))
+            }
+            Code::Internal if is_decode_error(&status) => Self::Decode(Box::new(status)),
+            _ => Self::Status(Box::new(status)),
+        }
+    }
+}
+
+impl ClientError {
+    /// The gRPC status returned by the server, if the error came from one.
+    pub fn status(&self) -> Option<&Status> {
+        match self {
+            Self::Unauthenticated(status) | Self::Decode(status) | Self::Status(status) => {
+                Some(status)
+            }
+            _ => None,
+        }
+    }
+
+    pub fn code(&self) -> Option<Code> {
+        self.status().map(|status| status.code())
+    }
+
+    pub fn metadata(&self) -> Option<&MetadataMap> {
+        self.status().map(|status| status.metadata())
+    }
+}
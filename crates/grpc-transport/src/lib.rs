@@ -0,0 +1,137 @@
+use std::time::Duration;
+use tonic::transport::Endpoint;
+
+pub mod auth;
+pub mod codec;
+pub mod error;
+#[cfg(feature = "tracing")]
+pub mod logging;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "recording")]
+pub mod recording;
+pub mod tls;
+
+/// HTTP/2 and TCP settings applied to the channel of a gRPC client.
+///
+/// Every unset field keeps tonic's default.
+#[derive(Debug, Clone, Default)]
+pub struct TransportConfig {
+    pub http2_keep_alive_interval: Option<Duration>,
+    pub keep_alive_timeout: Option<Duration>,
+    pub keep_alive_while_idle: Option<bool>,
+    pub tcp_keepalive: Option<Duration>,
+    pub tcp_nodelay: Option<bool>,
+    pub connect_timeout: Option<Duration>,
+    pub request_timeout: Option<Duration>,
+    pub initial_stream_window_size: Option<u32>,
+    pub initial_connection_window_size: Option<u32>,
+    pub http2_adaptive_window: Option<bool>,
+}
+
+impl TransportConfig {
+    pub fn builder() -> TransportConfigBuilder {
+        TransportConfigBuilder::default()
+    }
+
+    pub fn apply(&self, mut endpoint: Endpoint) -> Endpoint {
+        if let Some(interval) = self.http2_keep_alive_interval {
+            endpoint = endpoint.http2_keep_alive_interval(interval);
+        }
+        if let Some(timeout) = self.keep_alive_timeout {
+            endpoint = endpoint.keep_alive_timeout(timeout);
+        }
+        if let Some(enabled) = self.keep_alive_while_idle {
+            endpoint = endpoint.keep_alive_while_idle(enabled);
+        }
+        if let Some(keepalive) = self.tcp_keepalive {
+            endpoint = endpoint.tcp_keepalive(Some(keepalive));
+        }
+        if let Some(enabled) = self.tcp_nodelay {
+            endpoint = endpoint.tcp_nodelay(enabled);
+        }
+        if let Some(timeout) = self.connect_timeout {
+            endpoint = endpoint.connect_timeout(timeout);
+        }
+        if let Some(timeout) = self.request_timeout {
+            endpoint = endpoint.timeout(timeout);
+        }
+        if let Some(size) = self.initial_stream_window_size {
+            endpoint = endpoint.initial_stream_window_size(size);
+        }
+        if let Some(size) = self.initial_connection_window_size {
+            endpoint = endpoint.initial_connection_window_size(size);
+        }
+        if let Some(enabled) = self.http2_adaptive_window {
+            endpoint = endpoint.http2_adaptive_window(enabled);
+        }
+
+        endpoint
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TransportConfigBuilder {
+    config: TransportConfig,
+}
+
+impl TransportConfigBuilder {
+    /// Interval of HTTP/2 keepalive pings on the connection.
+    pub fn http2_keep_alive_interval(mut self, interval: Duration) -> Self {
+        self.config.http2_keep_alive_interval = Some(interval);
+        self
+    }
+
+    /// Time to wait for an HTTP/2 keepalive ack before closing the connection.
+    pub fn keep_alive_timeout(mut self, timeout: Duration) -> Self {
+        self.config.keep_alive_timeout = Some(timeout);
+        self
+    }
+
+    /// Send HTTP/2 keepalive pings even when there are no open streams.
+    pub fn keep_alive_while_idle(mut self, enabled: bool) -> Self {
+        self.config.keep_alive_while_idle = Some(enabled);
+        self
+    }
+
+    pub fn tcp_keepalive(mut self, keepalive: Duration) -> Self {
+        self.config.tcp_keepalive = Some(keepalive);
+        self
+    }
+
+    pub fn tcp_nodelay(mut self, enabled: bool) -> Self {
+        self.config.tcp_nodelay = Some(enabled);
+        self
+    }
+
+    pub fn connect_timeout(mut self, timeout: Duration) -> Self {
+        self.config.connect_timeout = Some(timeout);
+        self
+    }
+
+    /// Deadline for each request. Also applies to streaming calls, so leave it
+    /// unset for long lived subscriptions.
+    pub fn request_timeout(mut self, timeout: Duration) -> Self {
+        self.config.request_timeout = Some(timeout);
+        self
+    }
+
+    pub fn initial_stream_window_size(mut self, size: u32) -> Self {
+        self.config.initial_stream_window_size = Some(size);
+        self
+    }
+
+    pub fn initial_connection_window_size(mut self, size: u32) -> Self {
+        self.config.initial_connection_window_size = Some(size);
+        self
+    }
+
+    pub fn http2_adaptive_window(mut self, enabled: bool) -> Self {
+        self.config.http2_adaptive_window = Some(enabled);
+        self
+    }
+
+    pub fn build(self) -> TransportConfig {
+        self.config
+    }
+}
@@ -0,0 +1,288 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    sync::{Arc, OnceLock, RwLock},
+};
+use tracing::{
+    field::{Field, Visit},
+    span, Event, Level, Subscriber,
+};
+use tracing_subscriber::{
+    filter::{LevelFilter, Targets},
+    layer::{Context, SubscriberExt},
+    registry::LookupSpan,
+    reload,
+    util::{SubscriberInitExt, TryInitError},
+    Layer, Registry,
+};
+
+pub use tracing;
+pub use tracing_subscriber;
+
+/// A `tracing` event flattened for loggers that know nothing about spans,
+/// e.g. a JS callback.
+#[derive(Debug, Clone)]
+pub struct LogRecord {
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+    /// Fields of the event and of every span it happened in, the innermost
+    /// value wins when names collide.
+    pub fields: BTreeMap<String, String>,
+    /// Names of the spans the event happened in, outermost first.
+    pub spans: Vec<String>,
+}
+
+/// A layer passing every event it sees to a callback as a [`LogRecord`].
+pub struct CallbackLayer<F> {
+    callback: F,
+}
+
+impl<F> CallbackLayer<F>
+where
+    F: Fn(LogRecord) + Send + Sync + 'static,
+{
+    pub fn new(callback: F) -> Self {
+        Self { callback }
+    }
+}
+
+/// Fields recorded on a span so far, kept in the span's extensions.
+#[derive(Default)]
+struct SpanFields(BTreeMap<String, String>);
+
+struct FieldVisitor<'a>(&'a mut BTreeMap<String, String>);
+
+impl Visit for FieldVisitor<'_> {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.0.insert(field.name().to_string(), value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        self.0
+            .insert(field.name().to_string(), format!("{value:?}"));
+    }
+}
+
+impl<S, F> Layer<S> for CallbackLayer<F>
+where
+    S: Subscriber + for<'a> LookupSpan<'a>,
+    F: Fn(LogRecord) + Send + Sync + 'static,
+{
+    fn on_new_span(&self, attrs: &span::Attributes<'_>, id: &span::Id, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut fields = SpanFields::default();
+        attrs.record(&mut FieldVisitor(&mut fields.0));
+        span.extensions_mut().insert(fields);
+    }
+
+    fn on_record(&self, id: &span::Id, values: &span::Record<'_>, ctx: Context<'_, S>) {
+        let Some(span) = ctx.span(id) else {
+            return;
+        };
+        let mut extensions = span.extensions_mut();
+        if let Some(fields) = extensions.get_mut::<SpanFields>() {
+            values.record(&mut FieldVisitor(&mut fields.0));
+        }
+    }
+
+    fn on_event(&self, event: &Event<'_>, ctx: Context<'_, S>) {
+        let mut fields = BTreeMap::new();
+        event.record(&mut FieldVisitor(&mut fields));
+        let message = fields.remove("message").unwrap_or_default();
+
+        let mut spans = Vec::new();
+        // Walks from the innermost span outwards.
+        for span in ctx.event_scope(event).into_iter().flatten() {
+            spans.push(span.name().to_string());
+            if let Some(span_fields) = span.extensions().get::<SpanFields>() {
+                for (name, value) in span_fields.0.iter() {
+                    fields.entry(name.clone()).or_insert_with(|| value.clone());
+                }
+            }
+        }
+        spans.reverse();
+
+        let metadata = event.metadata();
+        (self.callback)(LogRecord {
+            level: *metadata.level(),
+            target: metadata.target().to_string(),
+            message,
+            fields,
+            spans,
+        });
+    }
+}
+
+type GlobalCallback = Arc<dyn Fn(LogRecord) + Send + Sync>;
+
+static GLOBAL_CALLBACK: RwLock<Option<GlobalCallback>> = RwLock::new(None);
+static GLOBAL_FILTER: OnceLock<reload::Handle<Targets, Registry>> = OnceLock::new();
+
+/// Crates of this workspace, the targets [`set_global_callback`] passes on.
+pub const WORKSPACE_TARGETS: &[&str] = &[
+    "grpc_transport",
+    "yellowstone_geyser_client",
+    "yellowstone_geyser_client_ts",
+    "shredstream_proxy_client",
+    "shredstream_proxy_client_ts",
+    "solana_entry_decoder",
+];
+
+/// Filter enabling [`WORKSPACE_TARGETS`] at or above `level`.
+pub fn workspace_targets(level: LevelFilter) -> Targets {
+    Targets::new().with_targets(WORKSPACE_TARGETS.iter().map(|target| (*target, level)))
+}
+
+/// Routes the events of the crates in [`WORKSPACE_TARGETS`] at or above
+/// `level` to `callback`, replacing the callback and filter set by an earlier
+/// call.
+///
+/// The first call installs a global subscriber and fails if the application
+/// has installed one already. Applications with their own subscriber should
+/// add a [`CallbackLayer`] to it instead.
+pub fn set_global_callback(
+    callback: impl Fn(LogRecord) + Send + Sync + 'static,
+    level: LevelFilter,
+) -> Result<(), TryInitError> {
+    set_global_callback_with_targets(callback, workspace_targets(level))
+}
+
+/// Like [`set_global_callback`], with the events passed on chosen by
+/// `targets`, e.g. to include `tonic` and `h2`.
+pub fn set_global_callback_with_targets(
+    callback: impl Fn(LogRecord) + Send + Sync + 'static,
+    targets: Targets,
+) -> Result<(), TryInitError> {
+    *GLOBAL_CALLBACK.write().unwrap() = Some(Arc::new(callback));
+
+    if let Some(handle) = GLOBAL_FILTER.get() {
+        // Only fails once the subscriber is gone, which a global one never is.
+        let _ = handle.reload(targets);
+        return Ok(());
+    }
+
+    let (filter, handle) = reload::Layer::new(targets);
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(CallbackLayer::new(|record| {
+            // Cloned out of the lock so the callback may replace itself.
+            let callback = GLOBAL_CALLBACK.read().unwrap().clone();
+            if let Some(callback) = callback {
+                callback(record);
+            }
+        }))
+        .try_init()?;
+    let _ = GLOBAL_FILTER.set(handle);
+
+    Ok(())
+}
+
+/// Stops passing events to the callback set with [`set_global_callback`].
+pub fn clear_global_callback() {
+    *GLOBAL_CALLBACK.write().unwrap() = None;
+}
+
+pub use crate::napi_logger;
+
+/// Defines the `LogRecord` object and the `setLogger` and `clearLogger`
+/// functions of a napi package.
+///
+/// The package needs `napi` as a dependency and `#[macro_use] extern crate
+/// napi_derive`. Events are passed on as by [`set_global_callback`], unless
+/// JS names the targets itself.
+#[macro_export]
+macro_rules! napi_logger {
+    () => {
+        use napi::{
+            threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode},
+            Env,
+        };
+
+        /// A log event of the client, e.g. a stream failing or a reconnect attempt.
+        #[napi(object)]
+        pub struct LogRecord {
+            /// One of `trace`, `debug`, `info`, `warn` and `error`.
+            pub level: String,
+            pub target: String,
+            pub message: String,
+            /// Fields of the event and its spans, e.g. `endpoint`, `filters` and `slot`.
+            pub fields: std::collections::HashMap<String, String>,
+            /// Spans the event happened in, outermost first.
+            pub spans: Vec<String>,
+        }
+
+        impl From<$crate::logging::LogRecord> for LogRecord {
+            fn from(record: $crate::logging::LogRecord) -> Self {
+                Self {
+                    level: record.level.as_str().to_ascii_lowercase(),
+                    target: record.target,
+                    message: record.message,
+                    fields: record.fields.into_iter().collect(),
+                    spans: record.spans,
+                }
+            }
+        }
+
+        /// Passes the client's log events at or above `level` to `callback`,
+        /// replacing the logger set before. `level` defaults to `info`.
+        ///
+        /// Only events of the client's own crates are passed on, unless
+        /// `targets` lists the targets to log instead, e.g. `["h2", "tonic"]`.
+        #[napi]
+        pub fn set_logger(
+            env: Env,
+            mut callback: ThreadsafeFunction<LogRecord>,
+            level: Option<String>,
+            targets: Option<Vec<String>>,
+        ) -> napi::Result<()> {
+            use $crate::logging::tracing_subscriber::filter::{LevelFilter, Targets};
+
+            let level = level
+                .as_deref()
+                .unwrap_or("info")
+                .parse::<LevelFilter>()
+                .map_err(|e| napi::Error::from_reason(e.to_string()))?;
+            let targets = match targets {
+                Some(targets) => {
+                    Targets::new().with_targets(targets.into_iter().map(|target| (target, level)))
+                }
+                None => $crate::logging::workspace_targets(level),
+            };
+            // The logger must not keep the process alive on its own.
+            callback.unref(&env)?;
+
+            $crate::logging::set_global_callback_with_targets(
+                move |record| {
+                    callback.call(Ok(record.into()), ThreadsafeFunctionCallMode::NonBlocking);
+                },
+                targets,
+            )
+            .map_err(|e| napi::Error::from_reason(e.to_string()))
+        }
+
+        /// Stops passing log events to the logger set with `setLogger`.
+        #[napi]
+        pub fn clear_logger() {
+            $crate::logging::clear_global_callback();
+        }
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn enables_only_workspace_targets() {
+        let targets = workspace_targets(LevelFilter::INFO);
+
+        assert!(targets.would_enable("yellowstone_geyser_client::reconnect", &Level::INFO));
+        assert!(targets.would_enable("shredstream_proxy_client", &Level::WARN));
+        assert!(!targets.would_enable("yellowstone_geyser_client", &Level::DEBUG));
+        assert!(!targets.would_enable("h2::codec", &Level::INFO));
+        assert!(!targets.would_enable("tonic::transport", &Level::ERROR));
+    }
+}
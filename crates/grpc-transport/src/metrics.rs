@@ -0,0 +1,91 @@
+use prometheus::{Encoder, Registry, TextEncoder};
+use std::{io, net::SocketAddr, time::Duration};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream, ToSocketAddrs},
+    task::JoinHandle,
+};
+use tonic::Code;
+
+pub use prometheus;
+
+/// Renders every metric of `registry` in the Prometheus text format.
+pub fn encode(registry: &Registry) -> String {
+    let mut buffer = Vec::new();
+    // Encoding into a `Vec` only fails on metrics with invalid names, which
+    // `register` rejects already.
+    let _ = TextEncoder::new().encode(&registry.gather(), &mut buffer);
+
+    String::from_utf8(buffer).unwrap_or_default()
+}
+
+/// Label value for a gRPC status code, e.g. `Unavailable`.
+pub fn code_label(code: Code) -> String {
+    format!("{code:?}")
+}
+
+/// Serves the metrics of a registry over HTTP until dropped.
+///
+/// Every request is answered with the current metrics regardless of its path,
+/// so the server is meant to be bound to a local or otherwise private address.
+pub struct MetricsServer {
+    local_addr: SocketAddr,
+    handle: JoinHandle<()>,
+}
+
+impl MetricsServer {
+    /// Binds `addr` and starts serving on the current tokio runtime.
+    pub async fn bind(addr: impl ToSocketAddrs, registry: Registry) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr).await?;
+        let local_addr = listener.local_addr()?;
+        let handle = tokio::spawn(async move {
+            loop {
+                let Ok((stream, _)) = listener.accept().await else {
+                    // E.g. out of file descriptors, give connections time to close.
+                    tokio::time::sleep(Duration::from_millis(100)).await;
+                    continue;
+                };
+                let registry = registry.clone();
+                tokio::spawn(async move {
+                    let _ = respond(stream, &registry).await;
+                });
+            }
+        });
+
+        Ok(Self { local_addr, handle })
+    }
+
+    /// The bound address, useful when binding port 0.
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+}
+
+impl Drop for MetricsServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+async fn respond(mut stream: TcpStream, registry: &Registry) -> io::Result<()> {
+    // The request itself is irrelevant, read until the end of its headers.
+    let mut request = Vec::new();
+    let mut buffer = [0; 1024];
+    while !request.windows(4).any(|window| window == b"\r\n\r\n") {
+        let read = stream.read(&mut buffer).await?;
+        if read == 0 || request.len() > 16 * 1024 {
+            return Ok(());
+        }
+        request.extend_from_slice(&buffer[..read]);
+    }
+
+    let body = encode(registry);
+    let header = format!(
+        "HTTP/1.1 200 OK\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+        prometheus::TEXT_FORMAT,
+        body.len()
+    );
+    stream.write_all(header.as_bytes()).await?;
+    stream.write_all(body.as_bytes()).await?;
+    stream.shutdown().await
+}
@@ -0,0 +1,470 @@
+use futures::Stream;
+use prost::Message;
+use std::{
+    fs::File,
+    future::Future,
+    io::{self, BufRead, BufReader, BufWriter, Read, Write},
+    marker::PhantomData,
+    path::Path,
+    pin::Pin,
+    task::{ready, Context, Poll},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use tokio::time::{sleep_until, Instant, Sleep};
+use tonic::Status;
+
+const MAGIC: &[u8; 8] = b"SSTRMREC";
+const VERSION: u8 = 1;
+const COMPRESSION_NONE: u8 = 0;
+const COMPRESSION_ZSTD: u8 = 1;
+/// Larger length prefixes are taken for corruption rather than allocated.
+const MAX_MESSAGE_SIZE: u64 = 1 << 30;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Compression {
+    #[default]
+    None,
+    /// zstd at the given level, `0` picks zstd's default.
+    Zstd(i32),
+}
+
+/// A message read from a recording.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Recorded<M> {
+    pub message: M,
+    pub received_at: SystemTime,
+}
+
+enum Writer {
+    Plain(BufWriter<Box<dyn Write + Send>>),
+    Zstd(zstd::Encoder<'static, BufWriter<Box<dyn Write + Send>>>),
+}
+
+impl Writer {
+    fn get_mut(&mut self) -> &mut dyn Write {
+        match self {
+            Self::Plain(writer) => writer,
+            Self::Zstd(writer) => writer,
+        }
+    }
+
+    fn finish(self) -> io::Result<()> {
+        match self {
+            Self::Plain(mut writer) => writer.flush(),
+            Self::Zstd(writer) => writer.finish()?.flush(),
+        }
+    }
+}
+
+/// Writes messages together with their receive time to a recording.
+///
+/// A recording starts with an 8 byte magic, a version and a compression byte.
+/// The rest, zstd compressed if enabled, is a sequence of records: the receive
+/// time as little endian `u64` microseconds since the Unix epoch, followed by
+/// the length-delimited protobuf message.
+///
+/// Writes are buffered. Dropping the recorder finishes the recording like
+/// [`Self::finish`] does, but ignores errors.
+pub struct Recorder<M> {
+    writer: Option<Writer>,
+    _message: PhantomData<fn(&M)>,
+}
+
+impl<M: Message> Recorder<M> {
+    pub fn create(path: impl AsRef<Path>, compression: Compression) -> io::Result<Self> {
+        Self::new(File::create(path)?, compression)
+    }
+
+    pub fn new(writer: impl Write + Send + 'static, compression: Compression) -> io::Result<Self> {
+        let mut writer = BufWriter::new(Box::new(writer) as Box<dyn Write + Send>);
+        let compression_byte = match compression {
+            Compression::None => COMPRESSION_NONE,
+            Compression::Zstd(_) => COMPRESSION_ZSTD,
+        };
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION, compression_byte])?;
+
+        let writer = match compression {
+            Compression::None => Writer::Plain(writer),
+            Compression::Zstd(level) => Writer::Zstd(zstd::Encoder::new(writer, level)?),
+        };
+
+        Ok(Self {
+            writer: Some(writer),
+            _message: PhantomData,
+        })
+    }
+
+    pub fn record(&mut self, message: &M, received_at: SystemTime) -> io::Result<()> {
+        let Some(writer) = self.writer.as_mut() else {
+            return Err(io::Error::other("recording is finished"));
+        };
+
+        let micros = received_at
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_micros();
+        let mut buffer = Vec::with_capacity(8 + 10 + message.encoded_len());
+        buffer.extend_from_slice(&u64::try_from(micros).unwrap_or(u64::MAX).to_le_bytes());
+        message
+            .encode_length_delimited(&mut buffer)
+            .map_err(io::Error::other)?;
+
+        writer.get_mut().write_all(&buffer)
+    }
+
+    /// Writes buffered records through to the underlying writer.
+    pub fn flush(&mut self) -> io::Result<()> {
+        match self.writer.as_mut() {
+            Some(writer) => writer.get_mut().flush(),
+            None => Ok(()),
+        }
+    }
+
+    /// Flushes the recording and ends the zstd frame if compressed.
+    pub fn finish(mut self) -> io::Result<()> {
+        self.writer.take().map_or(Ok(()), Writer::finish)
+    }
+}
+
+impl<M> Drop for Recorder<M> {
+    fn drop(&mut self) {
+        if let Some(writer) = self.writer.take() {
+            let _ = writer.finish();
+        }
+    }
+}
+
+/// Reads the records of a recording written by a [`Recorder`], in order.
+///
+/// Reading stops after the first error, later reads return `None`.
+pub struct RecordReader<M> {
+    reader: Box<dyn BufRead + Send>,
+    failed: bool,
+    _message: PhantomData<fn() -> M>,
+}
+
+impl<M: Message + Default> RecordReader<M> {
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        Self::new(File::open(path)?)
+    }
+
+    /// Reads the header of the recording, failing if it is not one.
+    pub fn new(reader: impl Read + Send + 'static) -> io::Result<Self> {
+        let mut reader = BufReader::new(reader);
+        let mut header = [0; 10];
+        reader.read_exact(&mut header)?;
+        if header[..8] != MAGIC[..] {
+            return Err(invalid_data("not a stream recording"));
+        }
+        if header[8] != VERSION {
+            return Err(invalid_data(format!(
+                "unsupported recording version {}",
+                header[8]
+            )));
+        }
+
+        let reader: Box<dyn BufRead + Send> = match header[9] {
+            COMPRESSION_NONE => Box::new(reader),
+            COMPRESSION_ZSTD => Box::new(BufReader::new(zstd::Decoder::with_buffer(reader)?)),
+            other => return Err(invalid_data(format!("unknown compression {other}"))),
+        };
+
+        Ok(Self {
+            reader,
+            failed: false,
+            _message: PhantomData,
+        })
+    }
+
+    /// Returns the next record, `None` at the end of the recording.
+    ///
+    /// A recording cut off in the middle of a record, e.g. because the
+    /// recorder was killed, fails with `UnexpectedEof`.
+    pub fn read(&mut self) -> io::Result<Option<Recorded<M>>> {
+        if self.failed {
+            return Ok(None);
+        }
+
+        let record = self.read_record();
+        self.failed = record.is_err();
+        record
+    }
+
+    fn read_record(&mut self) -> io::Result<Option<Recorded<M>>> {
+        if self.reader.fill_buf()?.is_empty() {
+            return Ok(None);
+        }
+
+        let mut micros = [0; 8];
+        self.reader.read_exact(&mut micros)?;
+        let len = read_varint(&mut self.reader)?;
+        if len > MAX_MESSAGE_SIZE {
+            return Err(invalid_data(format!("message of {len} bytes is too large")));
+        }
+        let mut data = vec![0; len as usize];
+        self.reader.read_exact(&mut data)?;
+
+        Ok(Some(Recorded {
+            message: M::decode(data.as_slice()).map_err(invalid_data)?,
+            received_at: UNIX_EPOCH + Duration::from_micros(u64::from_le_bytes(micros)),
+        }))
+    }
+}
+
+impl<M: Message + Default> Iterator for RecordReader<M> {
+    type Item = io::Result<Recorded<M>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.read().transpose()
+    }
+}
+
+fn read_varint(reader: &mut impl Read) -> io::Result<u64> {
+    let mut value = 0;
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0; 1];
+        reader.read_exact(&mut byte)?;
+        value |= u64::from(byte[0] & 0x7f) << shift;
+        if byte[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    Err(invalid_data("invalid length prefix"))
+}
+
+fn invalid_data(error: impl Into<Box<dyn std::error::Error + Send + Sync>>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, error)
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ReplaySpeed {
+    /// Keeps the gaps between messages as recorded.
+    #[default]
+    Original,
+    /// Divides the gaps between messages by the factor, e.g. `2.0` replays
+    /// twice as fast. Factors that are not positive replay as fast as possible.
+    Accelerated(f64),
+    /// Delivers messages as fast as they are read.
+    Unlimited,
+}
+
+/// Replays a recording as a stream, paced by the receive times of its records.
+///
+/// Records are read synchronously while polling, which is fine for local
+/// files but blocks the runtime thread on slow storage. The stream ends after
+/// the first read error.
+pub struct Replay<M> {
+    reader: Option<RecordReader<M>>,
+    speed: ReplaySpeed,
+    /// Start of the replay and receive time of the first record.
+    origin: Option<(Instant, SystemTime)>,
+    pending: Option<(Recorded<M>, Pin<Box<Sleep>>)>,
+}
+
+impl<M: Message + Default> Replay<M> {
+    pub fn open(path: impl AsRef<Path>, speed: ReplaySpeed) -> io::Result<Self> {
+        Ok(Self::new(RecordReader::open(path)?, speed))
+    }
+
+    pub fn new(reader: RecordReader<M>, speed: ReplaySpeed) -> Self {
+        Self {
+            reader: Some(reader),
+            speed,
+            origin: None,
+            pending: None,
+        }
+    }
+
+    /// When the record received at `received_at` is due, `None` if right away.
+    fn due(&mut self, received_at: SystemTime) -> Option<Instant> {
+        let factor = match self.speed {
+            ReplaySpeed::Original => 1.0,
+            ReplaySpeed::Accelerated(factor) => factor,
+            ReplaySpeed::Unlimited => return None,
+        };
+        let (started, first) = *self
+            .origin
+            .get_or_insert_with(|| (Instant::now(), received_at));
+
+        let offset = received_at.duration_since(first).unwrap_or_default();
+        let delay = Duration::try_from_secs_f64(offset.as_secs_f64() / factor).ok()?;
+        started.checked_add(delay)
+    }
+}
+
+impl<M: Message + Default + Unpin> Stream for Replay<M> {
+    type Item = io::Result<Recorded<M>>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        if let Some((_, sleep)) = this.pending.as_mut() {
+            ready!(sleep.as_mut().poll(cx));
+            return Poll::Ready(this.pending.take().map(|(record, _)| Ok(record)));
+        }
+
+        let Some(reader) = this.reader.as_mut() else {
+            return Poll::Ready(None);
+        };
+        let record = match reader.read() {
+            Ok(Some(record)) => record,
+            Ok(None) => {
+                this.reader = None;
+                return Poll::Ready(None);
+            }
+            Err(error) => {
+                this.reader = None;
+                return Poll::Ready(Some(Err(error)));
+            }
+        };
+
+        match this.due(record.received_at) {
+            Some(due) if due > Instant::now() => {
+                let mut sleep = Box::pin(sleep_until(due));
+                // Registers the waker, the sleep cannot be ready yet.
+                let _ = sleep.as_mut().poll(cx);
+                this.pending = Some((record, sleep));
+                Poll::Pending
+            }
+            _ => Poll::Ready(Some(Ok(record))),
+        }
+    }
+}
+
+/// Passes a gRPC stream through while recording every message it yields,
+/// received at the time it is yielded.
+///
+/// Recording never interrupts the stream. When a message cannot be recorded
+/// it is still yielded, recording stops, and the error is kept for
+/// [`Self::error`] and returned by [`Self::finish`].
+pub struct RecordingStream<S, M> {
+    stream: S,
+    recorder: Recorder<M>,
+    error: Option<io::Error>,
+}
+
+impl<S, M: Message> RecordingStream<S, M> {
+    pub fn new(stream: S, recorder: Recorder<M>) -> Self {
+        Self {
+            stream,
+            recorder,
+            error: None,
+        }
+    }
+
+    pub fn recorder_mut(&mut self) -> &mut Recorder<M> {
+        &mut self.recorder
+    }
+
+    /// The error that stopped recording, if any.
+    pub fn error(&self) -> Option<&io::Error> {
+        self.error.as_ref()
+    }
+
+    /// Stops recording and finishes the recording, see [`Recorder::finish`].
+    ///
+    /// Fails with the error that stopped recording earlier, if any.
+    pub fn finish(self) -> io::Result<S> {
+        if let Some(error) = self.error {
+            return Err(error);
+        }
+        self.recorder.finish()?;
+        Ok(self.stream)
+    }
+}
+
+impl<S, M> Stream for RecordingStream<S, M>
+where
+    S: Stream<Item = Result<M, Status>> + Unpin,
+    M: Message,
+{
+    type Item = Result<M, Status>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+        let message = ready!(Pin::new(&mut this.stream).poll_next(cx));
+
+        if let (Some(Ok(message)), None) = (message.as_ref(), this.error.as_ref()) {
+            if let Err(error) = this.recorder.record(message, SystemTime::now()) {
+                #[cfg(feature = "tracing")]
+                tracing::warn!(%error, "failed to record message, recording stopped");
+                this.error = Some(error);
+            }
+        }
+
+        Poll::Ready(message)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{executor::block_on, stream, StreamExt};
+
+    /// Accepts nothing, so a recorder fails once its buffer is flushed.
+    struct FailingWriter;
+
+    impl Write for FailingWriter {
+        fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+            Err(io::Error::other("disk full"))
+        }
+
+        fn flush(&mut self) -> io::Result<()> {
+            Err(io::Error::other("disk full"))
+        }
+    }
+
+    #[test]
+    fn round_trips_records() {
+        for compression in [Compression::None, Compression::Zstd(0)] {
+            let path = std::env::temp_dir().join(format!(
+                "grpc-transport-round-trip-{}-{compression:?}",
+                std::process::id()
+            ));
+            let received_at = UNIX_EPOCH + Duration::from_micros(1_700_000_000_123_456);
+
+            let mut recorder = Recorder::create(&path, compression).unwrap();
+            recorder.record(&"first".to_string(), received_at).unwrap();
+            recorder.record(&String::new(), received_at).unwrap();
+            recorder.finish().unwrap();
+
+            let records = RecordReader::<String>::open(&path)
+                .unwrap()
+                .collect::<io::Result<Vec<_>>>()
+                .unwrap();
+            std::fs::remove_file(&path).unwrap();
+            assert_eq!(
+                records,
+                [
+                    Recorded {
+                        message: "first".to_string(),
+                        received_at,
+                    },
+                    Recorded {
+                        message: String::new(),
+                        received_at,
+                    },
+                ]
+            );
+        }
+    }
+
+    #[test]
+    fn keeps_streaming_when_recording_fails() {
+        let recorder = Recorder::new(FailingWriter, Compression::None).unwrap();
+        // Larger than the write buffer, so the first record reaches the writer.
+        let messages = vec![vec![1u8; 16 * 1024], vec![2u8; 8]];
+        let mut stream =
+            RecordingStream::new(stream::iter(messages.clone().into_iter().map(Ok)), recorder);
+
+        let yielded = block_on(stream.by_ref().map(Result::unwrap).collect::<Vec<_>>());
+        assert_eq!(yielded, messages);
+        assert_eq!(
+            stream.error().map(ToString::to_string).as_deref(),
+            Some("disk full")
+        );
+        assert!(stream.finish().is_err());
+    }
+}
@@ -0,0 +1,93 @@
+use tonic::transport::{Certificate, ClientTlsConfig, Endpoint, Identity};
+
+/// TLS settings of a gRPC client channel.
+///
+/// The default trusts the native and webpki root stores and presents no
+/// client certificate.
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    /// Connect over plaintext HTTP/2 (h2c), the endpoint must use `http://`.
+    pub plaintext: bool,
+    /// Trust the platform and webpki root certificates.
+    pub public_roots: bool,
+    /// PEM encoded CA bundle trusted in addition to, or instead of, the public roots.
+    pub ca_certificate: Option<Vec<u8>>,
+    /// PEM encoded certificate and key presented for mutual TLS.
+    pub identity: Option<ClientIdentity>,
+    /// Overrides the domain name used for SNI and certificate verification.
+    pub domain_name: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ClientIdentity {
+    pub certificate: Vec<u8>,
+    pub key: Vec<u8>,
+}
+
+impl Default for TlsConfig {
+    fn default() -> Self {
+        Self {
+            plaintext: false,
+            public_roots: true,
+            ca_certificate: None,
+            identity: None,
+            domain_name: None,
+        }
+    }
+}
+
+impl TlsConfig {
+    pub fn plaintext() -> Self {
+        Self {
+            plaintext: true,
+            ..Default::default()
+        }
+    }
+
+    /// Trusts only the given PEM encoded CA bundle.
+    pub fn with_ca_certificate(mut self, pem: impl Into<Vec<u8>>) -> Self {
+        self.ca_certificate = Some(pem.into());
+        self.public_roots = false;
+        self
+    }
+
+    pub fn with_identity(
+        mut self,
+        certificate: impl Into<Vec<u8>>,
+        key: impl Into<Vec<u8>>,
+    ) -> Self {
+        self.identity = Some(ClientIdentity {
+            certificate: certificate.into(),
+            key: key.into(),
+        });
+        self
+    }
+
+    pub fn with_domain_name(mut self, domain_name: impl Into<String>) -> Self {
+        self.domain_name = Some(domain_name.into());
+        self
+    }
+
+    pub fn apply(&self, endpoint: Endpoint) -> Result<Endpoint, tonic::transport::Error> {
+        if self.plaintext {
+            return Ok(endpoint);
+        }
+
+        let mut tls_config = ClientTlsConfig::new();
+        if self.public_roots {
+            tls_config = tls_config.with_native_roots().with_webpki_roots();
+        }
+        if let Some(pem) = self.ca_certificate.as_ref() {
+            tls_config = tls_config.ca_certificate(Certificate::from_pem(pem));
+        }
+        if let Some(identity) = self.identity.as_ref() {
+            tls_config =
+                tls_config.identity(Identity::from_pem(&identity.certificate, &identity.key));
+        }
+        if let Some(domain_name) = self.domain_name.as_ref() {
+            tls_config = tls_config.domain_name(domain_name);
+        }
+
+        endpoint.tls_config(tls_config)
+    }
+}
@@ -1,3 +1,6 @@
 fn main() {
-    tonic_build::compile_protos("proto/shredstream.proto").unwrap();
+    tonic_build::configure()
+        .codec_path("grpc_transport::codec::ProstCodec")
+        .compile_protos(&["proto/shredstream.proto"], &["proto"])
+        .unwrap();
 }
@@ -0,0 +1 @@
+pub use grpc_transport::error::ClientError as ShredstreamClientError;
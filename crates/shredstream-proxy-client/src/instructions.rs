@@ -0,0 +1,22 @@
+//! Instruction decoding for decoded entries with [`solana_instruction_decoder`].
+
+use solana_entry::entry::Entry;
+
+pub use solana_instruction_decoder::{
+    decode_transaction, DecodedInstruction, InstructionDecodeError, ParsedInstruction,
+    TransactionInstruction,
+};
+
+/// Decodes the instructions of every transaction in `entry`, one list per
+/// transaction.
+///
+/// Entries carry no transaction meta, so accounts loaded from address lookup
+/// tables fail with [`InstructionDecodeError::AccountIndexOutOfRange`] and
+/// there are no inner instructions.
+pub fn decode_entry_instructions(entry: &Entry) -> Vec<Vec<TransactionInstruction>> {
+    entry
+        .transactions
+        .iter()
+        .map(|transaction| decode_transaction(&transaction.message, None, None))
+        .collect()
+}
@@ -1,44 +1,135 @@
+use crate::error::ShredstreamClientError;
 use crate::proto::{Entry, SubscribeEntriesRequest};
-use std::error::Error;
+use futures::Stream;
+use grpc_transport::auth::{AuthInterceptor, AuthProvider};
+use std::{
+    pin::Pin,
+    sync::Arc,
+    task::{ready, Context, Poll},
+};
 use tonic::{
     codec::CompressionEncoding,
-    transport::{Channel, ClientTlsConfig},
-    Streaming,
+    service::interceptor::InterceptedService,
+    transport::{Channel, Endpoint},
+    Status, Streaming,
 };
 
+pub mod error;
+#[cfg(feature = "instructions")]
+pub mod instructions;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "mock")]
+pub mod mock;
 pub mod proto;
+#[cfg(feature = "recording")]
+pub mod recording;
+
+#[cfg(feature = "tracing")]
+pub use grpc_transport::logging;
+pub use grpc_transport::{auth, tls::TlsConfig, TransportConfig, TransportConfigBuilder};
 
 #[derive(Default)]
 pub struct ShredstreamClientConfig {
+    /// Providers run on every request, e.g. to add an auth token.
+    pub auth: Vec<Arc<dyn AuthProvider>>,
     pub send_compressed: Option<CompressionEncoding>,
     pub accept_compressed: Option<CompressionEncoding>,
     pub max_decoding_message_size: Option<usize>,
     pub max_encoding_message_size: Option<usize>,
+    pub transport: TransportConfig,
+    pub tls: TlsConfig,
+    /// Instruments the streams opened by the client.
+    pub metrics: Option<MetricsHandle>,
+}
+
+/// Metrics for [`ShredstreamClientConfig::metrics`], created from a
+/// [`crate::metrics::ShredstreamMetrics`] with the `metrics` feature.
+///
+/// The field exists either way so configs can be built the same with and
+/// without the feature. Without it no handle can be created.
+#[derive(Debug, Clone)]
+pub struct MetricsHandle {
+    #[cfg(feature = "metrics")]
+    metrics: crate::metrics::ShredstreamMetrics,
+    #[cfg(not(feature = "metrics"))]
+    _never: std::convert::Infallible,
+}
+
+#[cfg(feature = "metrics")]
+impl From<crate::metrics::ShredstreamMetrics> for MetricsHandle {
+    fn from(metrics: crate::metrics::ShredstreamMetrics) -> Self {
+        Self { metrics }
+    }
 }
 
 #[derive(Clone)]
 pub struct ShredstreamClient {
-    client: crate::proto::shredstream::shredstream_proxy_client::ShredstreamProxyClient<Channel>,
+    client: crate::proto::shredstream::shredstream_proxy_client::ShredstreamProxyClient<
+        InterceptedService<Channel, AuthInterceptor>,
+    >,
+    endpoint: Arc<str>,
+    #[cfg(feature = "metrics")]
+    metrics: Option<crate::metrics::ShredstreamMetrics>,
 }
 
 impl ShredstreamClient {
+    /// Creates a client whose channel connects on first use.
     pub fn new(
         endpoint_url: impl AsRef<str>,
         config: Option<ShredstreamClientConfig>,
-    ) -> Result<Self, Box<dyn Error>> {
+    ) -> Result<Self, ShredstreamClientError> {
         let config = config.unwrap_or_default();
+        let channel = Self::endpoint(&endpoint_url, &config)?.connect_lazy();
 
-        let tls_config = ClientTlsConfig::new()
-            .with_native_roots()
-            .with_webpki_roots();
-        let channel = Channel::from_shared(endpoint_url.as_ref().to_string())
-            .unwrap()
-            .tls_config(tls_config)?
-            .connect_lazy();
+        Ok(Self::with_channel(channel, endpoint_url, config))
+    }
 
+    /// Creates a client and connects right away, failing if the endpoint is unreachable.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "shredstream_connect",
+            skip_all,
+            fields(endpoint = endpoint_url.as_ref()),
+            err
+        )
+    )]
+    pub async fn connect(
+        endpoint_url: impl AsRef<str>,
+        config: Option<ShredstreamClientConfig>,
+    ) -> Result<Self, ShredstreamClientError> {
+        let config = config.unwrap_or_default();
+        let channel = Self::endpoint(&endpoint_url, &config)?
+            .connect()
+            .await
+            .map_err(ShredstreamClientError::Transport)?;
+
+        Ok(Self::with_channel(channel, endpoint_url, config))
+    }
+
+    fn endpoint(
+        endpoint_url: &impl AsRef<str>,
+        config: &ShredstreamClientConfig,
+    ) -> Result<Endpoint, ShredstreamClientError> {
+        let endpoint = Channel::from_shared(endpoint_url.as_ref().to_string())?;
+        let endpoint = config
+            .tls
+            .apply(endpoint)
+            .map_err(ShredstreamClientError::Tls)?;
+
+        Ok(config.transport.apply(endpoint))
+    }
+
+    fn with_channel(
+        channel: Channel,
+        endpoint_url: impl AsRef<str>,
+        config: ShredstreamClientConfig,
+    ) -> Self {
         let mut client =
-            crate::proto::shredstream::shredstream_proxy_client::ShredstreamProxyClient::new(
+            crate::proto::shredstream::shredstream_proxy_client::ShredstreamProxyClient::with_interceptor(
                 channel,
+                AuthInterceptor::new(config.auth),
             );
 
         if let Some(encoding) = config.send_compressed {
@@ -54,19 +145,123 @@ impl ShredstreamClient {
             client = client.max_encoding_message_size(limit);
         }
 
-        Ok(Self { client })
+        Self {
+            client,
+            endpoint: endpoint_url.as_ref().into(),
+            #[cfg(feature = "metrics")]
+            metrics: config.metrics.map(|handle| handle.metrics),
+        }
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "shredstream_subscribe",
+            skip_all,
+            fields(endpoint = %self.endpoint, filters = ?filter_names(&request)),
+            err
+        )
+    )]
     pub async fn subscribe_entries(
         &mut self,
         request: SubscribeEntriesRequest,
-    ) -> Result<Streaming<Entry>, Box<dyn Error>> {
-        let response = self
-            .client
-            .subscribe_entries(request)
-            .await
-            .map_err(|e| Box::new(e))?;
+    ) -> Result<EntryStream, ShredstreamClientError> {
+        #[cfg(feature = "tracing")]
+        let span = tracing::info_span!(
+            "shredstream_stream",
+            endpoint = %self.endpoint,
+            filters = ?filter_names(&request),
+        );
+        let response = self.client.subscribe_entries(request).await;
+
+        #[cfg(feature = "metrics")]
+        if let (Some(metrics), Err(status)) = (self.metrics.as_ref(), &response) {
+            metrics.observe_error(status.code());
+        }
 
-        Ok(response.into_inner())
+        Ok(EntryStream {
+            stream: response?.into_inner(),
+            #[cfg(feature = "metrics")]
+            metrics: self.metrics.clone(),
+            #[cfg(feature = "tracing")]
+            span,
+        })
     }
+
+    /// The URL the client was created with.
+    pub fn endpoint_url(&self) -> &str {
+        &self.endpoint
+    }
+
+    #[cfg(feature = "metrics")]
+    pub fn metrics(&self) -> Option<&crate::metrics::ShredstreamMetrics> {
+        self.metrics.as_ref()
+    }
+}
+
+/// A `SubscribeEntries` stream, instrumented when the client has metrics.
+pub struct EntryStream {
+    stream: Streaming<Entry>,
+    #[cfg(feature = "metrics")]
+    metrics: Option<crate::metrics::ShredstreamMetrics>,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+impl EntryStream {
+    /// Returns the next entry, `None` once the server ends the stream.
+    pub async fn message(&mut self) -> Result<Option<Entry>, ShredstreamClientError> {
+        let message = futures::future::poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await;
+
+        Ok(message.transpose()?)
+    }
+
+    pub fn into_inner(self) -> Streaming<Entry> {
+        self.stream
+    }
+}
+
+impl Stream for EntryStream {
+    type Item = Result<Entry, Status>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let message = ready!(Pin::new(&mut self.stream).poll_next(cx));
+
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = self.metrics.as_ref() {
+            match &message {
+                Some(Ok(entry)) => metrics.observe_entry(entry),
+                Some(Err(status)) => metrics.observe_error(status.code()),
+                None => {}
+            }
+        }
+
+        #[cfg(feature = "tracing")]
+        match &message {
+            Some(Ok(_)) => {}
+            Some(Err(status)) => tracing::warn!(
+                parent: &self.span,
+                code = ?status.code(),
+                error = status.message(),
+                "stream failed"
+            ),
+            None => tracing::info!(parent: &self.span, "stream closed by server"),
+        }
+
+        Poll::Ready(message)
+    }
+}
+
+#[cfg(feature = "tracing")]
+fn filter_names(request: &SubscribeEntriesRequest) -> Vec<&str> {
+    let mut names = request
+        .accounts
+        .keys()
+        .chain(request.transactions.keys())
+        .chain(request.slots.keys())
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    names.sort_unstable();
+    names.dedup();
+    names
 }
@@ -0,0 +1,71 @@
+use std::collections::HashMap;
+
+use grpc_transport::metrics::{
+    code_label,
+    prometheus::{IntCounter, IntCounterVec, Opts, Registry, Result},
+};
+use prost::Message;
+use tonic::Code;
+
+use crate::proto::Entry;
+
+pub use grpc_transport::metrics::{encode, prometheus, MetricsServer};
+
+/// Prometheus metrics of `SubscribeEntries` streams, clones share the same metrics.
+#[derive(Debug, Clone)]
+pub struct ShredstreamMetrics {
+    entries: IntCounter,
+    entry_bytes: IntCounter,
+    stream_errors: IntCounterVec,
+}
+
+impl ShredstreamMetrics {
+    /// Creates the metrics and registers them with `registry`.
+    pub fn register(registry: &Registry) -> Result<Self> {
+        Self::register_with_labels(registry, HashMap::new())
+    }
+
+    /// Like [`Self::register`], with `labels` set on every metric, e.g. to tell
+    /// several endpoints apart in one registry.
+    pub fn register_with_labels(
+        registry: &Registry,
+        labels: HashMap<String, String>,
+    ) -> Result<Self> {
+        let opts = |name: &str, help: &str| Opts::new(name, help).const_labels(labels.clone());
+
+        let metrics = Self {
+            entries: IntCounter::with_opts(opts(
+                "shredstream_entries_total",
+                "Entry messages received.",
+            ))?,
+            entry_bytes: IntCounter::with_opts(opts(
+                "shredstream_entry_bytes_total",
+                "Encoded size of the entry messages received.",
+            ))?,
+            stream_errors: IntCounterVec::new(
+                opts(
+                    "shredstream_stream_errors_total",
+                    "Failures to open or read a stream, by gRPC code.",
+                ),
+                &["code"],
+            )?,
+        };
+
+        registry.register(Box::new(metrics.entries.clone()))?;
+        registry.register(Box::new(metrics.entry_bytes.clone()))?;
+        registry.register(Box::new(metrics.stream_errors.clone()))?;
+
+        Ok(metrics)
+    }
+
+    pub(crate) fn observe_entry(&self, entry: &Entry) {
+        self.entries.inc();
+        self.entry_bytes.inc_by(entry.encoded_len() as u64);
+    }
+
+    pub(crate) fn observe_error(&self, code: Code) {
+        self.stream_errors
+            .with_label_values(&[code_label(code).as_str()])
+            .inc();
+    }
+}
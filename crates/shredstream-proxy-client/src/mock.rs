@@ -0,0 +1,440 @@
+use futures::{
+    channel::{mpsc, oneshot},
+    Stream, StreamExt,
+};
+use http_body::Frame;
+use solana_hash::Hash;
+use std::{
+    collections::{BTreeMap, BTreeSet, VecDeque},
+    convert::Infallible,
+    io,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+use tokio::task::JoinHandle;
+use tonic::{
+    body::Body,
+    codegen::{http, BoxFuture, Bytes, Service, StdError},
+    server::NamedService,
+    transport::{server::TcpIncoming, Server},
+    Code, Request, Response, Status,
+};
+
+use crate::proto::{
+    shredstream::shredstream_proxy_server::{
+        ShredstreamProxy, ShredstreamProxyServer, SERVICE_NAME,
+    },
+    Entry, SubscribeEntriesRequest,
+};
+
+pub use solana_entry::entry::Entry as SolanaEntry;
+
+/// One step of a scripted `SubscribeEntries` stream.
+#[derive(Debug, Clone)]
+pub enum MockEvent {
+    /// Sends the entries bincode-encoded in one `Entry` message.
+    Entries {
+        slot: u64,
+        entries: Vec<SolanaEntry>,
+    },
+    /// Sends an `Entry` message whose payload is not a bincode `Vec<Entry>`.
+    Corrupt {
+        slot: u64,
+        payload: Vec<u8>,
+    },
+    Delay(Duration),
+    /// Ends the stream with this status.
+    Status(Code, String),
+    /// Ends the stream with an `OK` status.
+    End,
+    /// Resets the HTTP/2 stream, so the client sees no status at all.
+    ///
+    /// Entries still buffered on the server are dropped with the stream,
+    /// precede it with a delay to get them delivered first.
+    Reset,
+}
+
+impl MockEvent {
+    /// `count` tick entries for `slot`, with hashes derived from the slot.
+    pub fn ticks(slot: u64, count: usize) -> Self {
+        let entries = (0..count)
+            .map(|index| {
+                let mut hash = [0; 32];
+                hash[..8].copy_from_slice(&slot.to_le_bytes());
+                hash[8..16].copy_from_slice(&(index as u64).to_le_bytes());
+                // `Entry::new_tick` is only built for solana-entry's own tests.
+                SolanaEntry {
+                    num_hashes: 1,
+                    hash: Hash::new_from_array(hash),
+                    transactions: Vec::new(),
+                }
+            })
+            .collect();
+
+        Self::Entries { slot, entries }
+    }
+}
+
+/// Scripts for a run of consecutive slots with faults mixed in.
+///
+/// Every slot becomes one message of tick entries unless it is skipped,
+/// duplicated or corrupted. Disconnects split the run into one script per
+/// connection, each following one resuming at the next slot the way a live
+/// proxy would.
+#[derive(Debug, Clone)]
+pub struct MockSlots {
+    start_slot: u64,
+    count: u64,
+    entries_per_slot: usize,
+    interval: Option<Duration>,
+    skipped: BTreeSet<u64>,
+    duplicated: BTreeSet<u64>,
+    corrupted: BTreeSet<u64>,
+    disconnects: BTreeMap<u64, (Code, String)>,
+}
+
+impl MockSlots {
+    pub fn new(start_slot: u64, count: u64) -> Self {
+        Self {
+            start_slot,
+            count,
+            entries_per_slot: 1,
+            interval: None,
+            skipped: BTreeSet::new(),
+            duplicated: BTreeSet::new(),
+            corrupted: BTreeSet::new(),
+            disconnects: BTreeMap::new(),
+        }
+    }
+
+    pub fn entries_per_slot(mut self, entries_per_slot: usize) -> Self {
+        self.entries_per_slot = entries_per_slot;
+        self
+    }
+
+    /// Waits this long before every slot.
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = Some(interval);
+        self
+    }
+
+    /// Leaves a gap where `slot` would be.
+    pub fn skip(mut self, slot: u64) -> Self {
+        self.skipped.insert(slot);
+        self
+    }
+
+    /// Sends `slot` twice in a row.
+    pub fn duplicate(mut self, slot: u64) -> Self {
+        self.duplicated.insert(slot);
+        self
+    }
+
+    /// Sends `slot` with a payload that fails to decode.
+    pub fn corrupt(mut self, slot: u64) -> Self {
+        self.corrupted.insert(slot);
+        self
+    }
+
+    /// Ends the stream with `code` right after `slot`.
+    pub fn disconnect_after(mut self, slot: u64, code: Code, message: impl Into<String>) -> Self {
+        self.disconnects.insert(slot, (code, message.into()));
+        self
+    }
+
+    /// One script per connection, the last one ending with the run.
+    pub fn build(self) -> Vec<Vec<MockEvent>> {
+        let mut scripts = Vec::new();
+        let mut events = Vec::new();
+        for slot in self.start_slot..self.start_slot.saturating_add(self.count) {
+            if let Some(interval) = self.interval {
+                events.push(MockEvent::Delay(interval));
+            }
+
+            if self.corrupted.contains(&slot) {
+                events.push(MockEvent::Corrupt {
+                    slot,
+                    payload: vec![0xff; 16],
+                });
+            } else if !self.skipped.contains(&slot) {
+                let event = MockEvent::ticks(slot, self.entries_per_slot);
+                if self.duplicated.contains(&slot) {
+                    events.push(event.clone());
+                }
+                events.push(event);
+            }
+
+            if let Some((code, message)) = self.disconnects.get(&slot) {
+                events.push(MockEvent::Status(*code, message.clone()));
+                scripts.push(std::mem::take(&mut events));
+            }
+        }
+        events.push(MockEvent::End);
+        scripts.push(events);
+
+        scripts
+    }
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    subscriptions: VecDeque<Vec<MockEvent>>,
+    failures: VecDeque<Status>,
+    requests: Vec<SubscribeEntriesRequest>,
+}
+
+/// An in-process ShredstreamProxy server playing scripted entry streams.
+///
+/// Every `SubscribeEntries` call plays the next script queued with
+/// [`Self::push_subscription`], and stays open without entries once no
+/// script is left. Clones share the same state.
+///
+/// Resets need the server started with [`Self::serve`]. Mounted as a plain
+/// [`ShredstreamProxyServer`], a reset ends the stream normally.
+#[derive(Debug, Clone, Default)]
+pub struct MockShredstream {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockShredstream {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues the events of a future `SubscribeEntries` stream.
+    pub fn push_subscription(&self, events: impl IntoIterator<Item = MockEvent>) {
+        let mut state = self.state.lock().unwrap();
+        state.subscriptions.push_back(events.into_iter().collect());
+    }
+
+    /// Queues the scripts of a [`MockSlots`] run.
+    pub fn push_slots(&self, slots: MockSlots) {
+        for events in slots.build() {
+            self.push_subscription(events);
+        }
+    }
+
+    /// Fails the next `SubscribeEntries` call with `status` instead of streaming.
+    pub fn fail_next(&self, status: Status) {
+        self.state.lock().unwrap().failures.push_back(status);
+    }
+
+    /// Requests of every `SubscribeEntries` call so far, failed ones included.
+    pub fn requests(&self) -> Vec<SubscribeEntriesRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Serves the mock on a free local port until the server is dropped.
+    pub async fn serve(self) -> io::Result<MockServer> {
+        let incoming = TcpIncoming::bind(SocketAddr::from(([127, 0, 0, 1], 0)))?;
+        let local_addr = incoming.local_addr()?;
+        let (shutdown, signal) = oneshot::channel::<()>();
+
+        let service = MockService {
+            server: ShredstreamProxyServer::new(self),
+        };
+        let handle = tokio::spawn(async move {
+            let _ = Server::builder()
+                .add_service(service)
+                .serve_with_incoming_shutdown(incoming, async {
+                    let _ = signal.await;
+                })
+                .await;
+        });
+
+        Ok(MockServer {
+            local_addr,
+            shutdown: Some(shutdown),
+            handle,
+        })
+    }
+}
+
+/// A running [`MockShredstream`], shut down on drop.
+pub struct MockServer {
+    local_addr: SocketAddr,
+    shutdown: Option<oneshot::Sender<()>>,
+    handle: JoinHandle<()>,
+}
+
+impl MockServer {
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// URL to pass to [`crate::ShredstreamClient::new`].
+    pub fn endpoint(&self) -> String {
+        format!("http://{}", self.local_addr)
+    }
+
+    /// Stops accepting connections and waits for open streams to end.
+    pub async fn shutdown(mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        let _ = (&mut self.handle).await;
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+enum MockFrame {
+    Entry(Entry),
+    Reset,
+}
+
+async fn play(events: Vec<MockEvent>, sender: mpsc::UnboundedSender<Result<MockFrame, Status>>) {
+    for event in events {
+        let frame = match event {
+            MockEvent::Entries { slot, entries } => match bincode::serialize(&entries) {
+                Ok(entries) => Ok(MockFrame::Entry(Entry { slot, entries })),
+                Err(error) => Err(Status::internal(error.to_string())),
+            },
+            MockEvent::Corrupt { slot, payload } => Ok(MockFrame::Entry(Entry {
+                slot,
+                entries: payload,
+            })),
+            MockEvent::Delay(delay) => {
+                tokio::time::sleep(delay).await;
+                continue;
+            }
+            MockEvent::Status(code, message) => Err(Status::new(code, message)),
+            MockEvent::Reset => Ok(MockFrame::Reset),
+            MockEvent::End => break,
+        };
+
+        let last = !matches!(frame, Ok(MockFrame::Entry(_)));
+        if sender.unbounded_send(frame).is_err() || last {
+            break;
+        }
+    }
+    sender.close_channel();
+}
+
+/// Set by [`MockService`] on every request, for [`ResetBody`].
+#[derive(Clone)]
+struct ResetFlag(Arc<AtomicBool>);
+
+struct FrameStream {
+    receiver: mpsc::UnboundedReceiver<Result<MockFrame, Status>>,
+    reset: Option<ResetFlag>,
+}
+
+impl Stream for FrameStream {
+    type Item = Result<Entry, Status>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match ready!(self.receiver.poll_next_unpin(cx)) {
+            Some(Ok(MockFrame::Entry(entry))) => Poll::Ready(Some(Ok(entry))),
+            Some(Ok(MockFrame::Reset)) => {
+                if let Some(ResetFlag(reset)) = self.reset.as_ref() {
+                    reset.store(true, Ordering::Release);
+                }
+                self.receiver.close();
+                Poll::Ready(None)
+            }
+            Some(Err(status)) => Poll::Ready(Some(Err(status))),
+            None => Poll::Ready(None),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl ShredstreamProxy for MockShredstream {
+    type SubscribeEntriesStream =
+        Pin<Box<dyn Stream<Item = Result<Entry, Status>> + Send + 'static>>;
+
+    async fn subscribe_entries(
+        &self,
+        request: Request<SubscribeEntriesRequest>,
+    ) -> Result<Response<Self::SubscribeEntriesStream>, Status> {
+        let reset = request.extensions().get::<ResetFlag>().cloned();
+        let events = {
+            let mut state = self.state.lock().unwrap();
+            state.requests.push(request.into_inner());
+            if let Some(status) = state.failures.pop_front() {
+                return Err(status);
+            }
+            state.subscriptions.pop_front()
+        };
+
+        let Some(events) = events else {
+            return Ok(Response::new(Box::pin(futures::stream::pending())));
+        };
+
+        let (sender, receiver) = mpsc::unbounded();
+        tokio::spawn(play(events, sender));
+
+        Ok(Response::new(Box::pin(FrameStream { receiver, reset })))
+    }
+}
+
+/// Wraps the generated [`ShredstreamProxyServer`] so that scripts can reset
+/// their stream.
+#[derive(Clone)]
+struct MockService {
+    server: ShredstreamProxyServer<MockShredstream>,
+}
+
+impl NamedService for MockService {
+    const NAME: &'static str = SERVICE_NAME;
+}
+
+impl<B> Service<http::Request<B>> for MockService
+where
+    B: http_body::Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<Body>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, mut request: http::Request<B>) -> Self::Future {
+        let reset = Arc::new(AtomicBool::new(false));
+        request.extensions_mut().insert(ResetFlag(reset.clone()));
+        let response = self.server.call(request);
+
+        Box::pin(async move {
+            let response = response.await?;
+            Ok(response.map(|inner| Body::new(ResetBody { inner, reset })))
+        })
+    }
+}
+
+/// Turns the trailers of a reset stream into a body error, on which the
+/// HTTP/2 stream is reset instead of being ended with a status.
+struct ResetBody {
+    inner: Body,
+    reset: Arc<AtomicBool>,
+}
+
+impl http_body::Body for ResetBody {
+    type Data = Bytes;
+    type Error = Status;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        match ready!(Pin::new(&mut self.inner).poll_frame(cx)) {
+            Some(Ok(frame)) if frame.is_trailers() && self.reset.load(Ordering::Acquire) => {
+                Poll::Ready(Some(Err(Status::internal("stream reset by mock"))))
+            }
+            frame => Poll::Ready(frame),
+        }
+    }
+}
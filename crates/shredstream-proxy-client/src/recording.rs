@@ -0,0 +1,99 @@
+use futures::Stream;
+use std::{
+    path::{Path, PathBuf},
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+use tonic::Status;
+
+use crate::{
+    error::ShredstreamClientError,
+    proto::{Entry, SubscribeEntriesRequest},
+};
+
+pub use grpc_transport::recording::{
+    Compression, RecordReader, Recorded, Recorder, RecordingStream, Replay, ReplaySpeed,
+};
+
+/// Serves `SubscribeEntries` streams from a recording instead of a proxy.
+///
+/// Every subscription replays the recording from its start. Record one by
+/// wrapping a live stream in a [`RecordingStream`].
+#[derive(Debug, Clone)]
+pub struct ReplayClient {
+    path: PathBuf,
+    speed: ReplaySpeed,
+}
+
+impl ReplayClient {
+    pub fn new(path: impl Into<PathBuf>, speed: ReplaySpeed) -> Self {
+        Self {
+            path: path.into(),
+            speed,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Opens the recording. Entries carry no accounts or transactions to match
+    /// against before decoding, so the filters of `request` are ignored.
+    pub async fn subscribe_entries(
+        &mut self,
+        _request: SubscribeEntriesRequest,
+    ) -> Result<ReplayEntryStream, ShredstreamClientError> {
+        let replay =
+            Replay::open(&self.path, self.speed).map_err(ShredstreamClientError::Recording)?;
+
+        Ok(ReplayEntryStream { replay })
+    }
+}
+
+/// A `SubscribeEntries` stream replayed from a recording.
+pub struct ReplayEntryStream {
+    replay: Replay<Entry>,
+}
+
+impl ReplayEntryStream {
+    /// Returns the next entry, `None` at the end of the recording.
+    pub async fn message(&mut self) -> Result<Option<Entry>, ShredstreamClientError> {
+        let message = futures::future::poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await;
+
+        Ok(message.transpose()?)
+    }
+
+    /// Like [`Self::message`], with the time the entry was originally received at.
+    pub async fn timestamped_message(
+        &mut self,
+    ) -> Result<Option<Recorded<Entry>>, ShredstreamClientError> {
+        let record = futures::future::poll_fn(|cx| self.poll_record(cx)).await;
+
+        Ok(record.transpose()?)
+    }
+
+    fn poll_record(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Recorded<Entry>, Status>>> {
+        Poll::Ready(match ready!(Pin::new(&mut self.replay).poll_next(cx)) {
+            Some(Ok(record)) => Some(Ok(record)),
+            Some(Err(error)) => Some(Err(Status::data_loss(format!(
+                "failed to read recording: {error}"
+            )))),
+            None => None,
+        })
+    }
+}
+
+impl Stream for ReplayEntryStream {
+    type Item = Result<Entry, Status>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Poll::Ready(match ready!(self.poll_record(cx)) {
+            Some(Ok(record)) => Some(Ok(record.message)),
+            Some(Err(status)) => Some(Err(status)),
+            None => None,
+        })
+    }
+}
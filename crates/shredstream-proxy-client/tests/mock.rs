@@ -0,0 +1,131 @@
+#![cfg(feature = "mock")]
+
+use shredstream_proxy_client::{
+    error::ShredstreamClientError,
+    mock::{MockEvent, MockShredstream, MockSlots},
+    proto::SubscribeEntriesRequest,
+    ShredstreamClient,
+};
+use solana_entry_decoder::decode_entries;
+use std::time::Duration;
+use tonic::Code;
+
+/// Slot and number of decoded entries of every message until the stream
+/// ends, `None` for messages that fail to decode.
+async fn collect(
+    client: &mut ShredstreamClient,
+) -> (Vec<(u64, Option<usize>)>, Option<ShredstreamClientError>) {
+    let mut stream = client
+        .subscribe_entries(SubscribeEntriesRequest::default())
+        .await
+        .unwrap();
+
+    let mut messages = Vec::new();
+    loop {
+        match stream.message().await {
+            Ok(Some(entry)) => {
+                let entries = decode_entries(entry.entries)
+                    .ok()
+                    .map(|entries| entries.len());
+                messages.push((entry.slot, entries));
+            }
+            Ok(None) => return (messages, None),
+            Err(error) => return (messages, Some(error)),
+        }
+    }
+}
+
+#[tokio::test]
+async fn streams_skipped_and_duplicated_slots() {
+    let mock = MockShredstream::new();
+    mock.push_slots(
+        MockSlots::new(100, 5)
+            .entries_per_slot(2)
+            .skip(101)
+            .duplicate(102),
+    );
+    let server = mock.serve().await.unwrap();
+
+    let mut client = ShredstreamClient::new(server.endpoint(), None).unwrap();
+    let (messages, error) = collect(&mut client).await;
+
+    assert_eq!(
+        messages,
+        [
+            (100, Some(2)),
+            (102, Some(2)),
+            (102, Some(2)),
+            (103, Some(2)),
+            (104, Some(2)),
+        ]
+    );
+    assert!(error.is_none());
+}
+
+#[tokio::test]
+async fn fails_to_decode_corrupt_slot() {
+    let mock = MockShredstream::new();
+    mock.push_slots(MockSlots::new(100, 3).corrupt(101));
+    let server = mock.serve().await.unwrap();
+
+    let mut client = ShredstreamClient::new(server.endpoint(), None).unwrap();
+    let (messages, error) = collect(&mut client).await;
+
+    assert_eq!(messages, [(100, Some(1)), (101, None), (102, Some(1))]);
+    assert!(error.is_none());
+}
+
+#[tokio::test]
+async fn resumes_after_disconnect() {
+    let mock = MockShredstream::new();
+    mock.push_slots(MockSlots::new(100, 4).disconnect_after(101, Code::Unavailable, "restart"));
+    let server = mock.clone().serve().await.unwrap();
+
+    let mut client = ShredstreamClient::new(server.endpoint(), None).unwrap();
+    let (messages, error) = collect(&mut client).await;
+    assert_eq!(messages, [(100, Some(1)), (101, Some(1))]);
+    let error = error.unwrap();
+    assert_eq!(error.code(), Some(Code::Unavailable));
+    assert_eq!(error.status().unwrap().message(), "restart");
+
+    let (messages, error) = collect(&mut client).await;
+    assert_eq!(messages, [(102, Some(1)), (103, Some(1))]);
+    assert!(error.is_none());
+    assert_eq!(mock.requests().len(), 2);
+}
+
+#[tokio::test]
+async fn reports_reset() {
+    let mock = MockShredstream::new();
+    mock.push_subscription([
+        MockEvent::ticks(100, 1),
+        MockEvent::Delay(Duration::from_millis(50)),
+        MockEvent::Reset,
+    ]);
+    let server = mock.serve().await.unwrap();
+
+    let mut client = ShredstreamClient::new(server.endpoint(), None).unwrap();
+    let (messages, error) = collect(&mut client).await;
+
+    assert_eq!(messages, [(100, Some(1))]);
+    // A reset stream fails without a status from the server, it does not end cleanly.
+    assert!(
+        matches!(error, Some(ShredstreamClientError::Status(_))),
+        "{error:?}"
+    );
+}
+
+#[test]
+fn builds_slots_near_max() {
+    let scripts = MockSlots::new(u64::MAX - 1, 5).build();
+
+    assert_eq!(scripts.len(), 1);
+    let slots = scripts[0]
+        .iter()
+        .filter_map(|event| match event {
+            MockEvent::Entries { slot, .. } => Some(*slot),
+            _ => None,
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(slots, [u64::MAX - 1]);
+}
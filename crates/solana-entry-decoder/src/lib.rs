@@ -1,7 +1,14 @@
 use std::error::Error;
 
 pub fn decode_entries(data: Vec<u8>) -> Result<Vec<solana_entry::entry::Entry>, Box<dyn Error>> {
-    let entries: Vec<solana_entry::entry::Entry> = bincode::deserialize(&data)?;
+    let entries: Vec<solana_entry::entry::Entry> = match bincode::deserialize(&data) {
+        Ok(entries) => entries,
+        Err(error) => {
+            #[cfg(feature = "tracing")]
+            tracing::warn!(bytes = data.len(), %error, "failed to decode entries");
+            return Err(error.into());
+        }
+    };
 
     Ok(entries)
 }
@@ -0,0 +1,81 @@
+use solana_pubkey::Pubkey;
+
+use crate::reader::{account, Reader};
+
+pub const ID: Pubkey = Pubkey::from_str_const("AddressLookupTab1e1111111111111111111111111");
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum AddressLookupTableInstruction {
+    CreateLookupTable {
+        lookup_table_account: Pubkey,
+        lookup_table_authority: Pubkey,
+        payer_account: Pubkey,
+        system_program: Pubkey,
+        recent_slot: u64,
+        bump_seed: u8,
+    },
+    FreezeLookupTable {
+        lookup_table_account: Pubkey,
+        lookup_table_authority: Pubkey,
+    },
+    ExtendLookupTable {
+        lookup_table_account: Pubkey,
+        lookup_table_authority: Pubkey,
+        /// Only needed when the table has to grow.
+        payer_account: Option<Pubkey>,
+        system_program: Option<Pubkey>,
+        new_addresses: Vec<Pubkey>,
+    },
+    DeactivateLookupTable {
+        lookup_table_account: Pubkey,
+        lookup_table_authority: Pubkey,
+    },
+    CloseLookupTable {
+        lookup_table_account: Pubkey,
+        lookup_table_authority: Pubkey,
+        recipient: Pubkey,
+    },
+}
+
+pub(crate) fn parse(accounts: &[Pubkey], data: &[u8]) -> Option<AddressLookupTableInstruction> {
+    let mut data = Reader::new(data);
+    let account = |index| account(accounts, index);
+
+    let instruction = match data.u32()? {
+        0 => AddressLookupTableInstruction::CreateLookupTable {
+            lookup_table_account: account(0)?,
+            lookup_table_authority: account(1)?,
+            payer_account: account(2)?,
+            system_program: account(3)?,
+            recent_slot: data.u64()?,
+            bump_seed: data.u8()?,
+        },
+        1 => AddressLookupTableInstruction::FreezeLookupTable {
+            lookup_table_account: account(0)?,
+            lookup_table_authority: account(1)?,
+        },
+        2 => {
+            let count = data.u64()?;
+            let new_addresses = (0..count).map(|_| data.pubkey()).collect::<Option<_>>()?;
+            AddressLookupTableInstruction::ExtendLookupTable {
+                lookup_table_account: account(0)?,
+                lookup_table_authority: account(1)?,
+                payer_account: account(2),
+                system_program: account(3),
+                new_addresses,
+            }
+        }
+        3 => AddressLookupTableInstruction::DeactivateLookupTable {
+            lookup_table_account: account(0)?,
+            lookup_table_authority: account(1)?,
+        },
+        4 => AddressLookupTableInstruction::CloseLookupTable {
+            lookup_table_account: account(0)?,
+            lookup_table_authority: account(1)?,
+            recipient: account(2)?,
+        },
+        _ => return None,
+    };
+
+    Some(instruction)
+}
@@ -0,0 +1,70 @@
+use solana_pubkey::Pubkey;
+
+use crate::reader::account;
+
+pub const ID: Pubkey = Pubkey::from_str_const("ATokenGPvbdGVxr1b2hvZbsiqW5xWH25efTNsLJA8knL");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AssociatedTokenInstruction {
+    Create {
+        source: Pubkey,
+        account: Pubkey,
+        wallet: Pubkey,
+        mint: Pubkey,
+        system_program: Pubkey,
+        token_program: Pubkey,
+    },
+    CreateIdempotent {
+        source: Pubkey,
+        account: Pubkey,
+        wallet: Pubkey,
+        mint: Pubkey,
+        system_program: Pubkey,
+        token_program: Pubkey,
+    },
+    RecoverNested {
+        nested_source: Pubkey,
+        nested_mint: Pubkey,
+        destination: Pubkey,
+        nested_owner: Pubkey,
+        owner_mint: Pubkey,
+        wallet: Pubkey,
+        token_program: Pubkey,
+    },
+}
+
+pub(crate) fn parse(accounts: &[Pubkey], data: &[u8]) -> Option<AssociatedTokenInstruction> {
+    let account = |index| account(accounts, index);
+
+    // Empty data is the original `Create`.
+    let instruction = match data.first().copied().unwrap_or_default() {
+        0 => AssociatedTokenInstruction::Create {
+            source: account(0)?,
+            account: account(1)?,
+            wallet: account(2)?,
+            mint: account(3)?,
+            system_program: account(4)?,
+            token_program: account(5)?,
+        },
+        1 => AssociatedTokenInstruction::CreateIdempotent {
+            source: account(0)?,
+            account: account(1)?,
+            wallet: account(2)?,
+            mint: account(3)?,
+            system_program: account(4)?,
+            token_program: account(5)?,
+        },
+        2 => AssociatedTokenInstruction::RecoverNested {
+            nested_source: account(0)?,
+            nested_mint: account(1)?,
+            destination: account(2)?,
+            nested_owner: account(3)?,
+            owner_mint: account(4)?,
+            wallet: account(5)?,
+            token_program: account(6)?,
+        },
+        _ => return None,
+    };
+
+    Some(instruction)
+}
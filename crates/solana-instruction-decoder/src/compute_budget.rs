@@ -0,0 +1,47 @@
+use solana_pubkey::Pubkey;
+
+use crate::reader::Reader;
+
+pub const ID: Pubkey = Pubkey::from_str_const("ComputeBudget111111111111111111111111111111");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ComputeBudgetInstruction {
+    /// Deprecated, only seen in old transactions.
+    RequestUnits {
+        units: u32,
+        additional_fee: u32,
+    },
+    RequestHeapFrame {
+        bytes: u32,
+    },
+    SetComputeUnitLimit {
+        units: u32,
+    },
+    /// Price in micro-lamports per compute unit.
+    SetComputeUnitPrice {
+        micro_lamports: u64,
+    },
+    SetLoadedAccountsDataSizeLimit {
+        bytes: u32,
+    },
+}
+
+pub(crate) fn parse(data: &[u8]) -> Option<ComputeBudgetInstruction> {
+    let mut data = Reader::new(data);
+
+    let instruction = match data.u8()? {
+        0 => ComputeBudgetInstruction::RequestUnits {
+            units: data.u32()?,
+            additional_fee: data.u32()?,
+        },
+        1 => ComputeBudgetInstruction::RequestHeapFrame { bytes: data.u32()? },
+        2 => ComputeBudgetInstruction::SetComputeUnitLimit { units: data.u32()? },
+        3 => ComputeBudgetInstruction::SetComputeUnitPrice {
+            micro_lamports: data.u64()?,
+        },
+        4 => ComputeBudgetInstruction::SetLoadedAccountsDataSizeLimit { bytes: data.u32()? },
+        _ => return None,
+    };
+
+    Some(instruction)
+}
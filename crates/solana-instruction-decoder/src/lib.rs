@@ -0,0 +1,378 @@
+//! Decodes instructions of core native programs, close to what RPC returns
+//! for `jsonParsed` transactions.
+//!
+//! Works on SDK message types. The `instructions` feature of
+//! `yellowstone_geyser_client` adds `SubscribeUpdateTransactionInfo::decode_instructions`,
+//! the one of `shredstream_proxy_client` adds `decode_entry_instructions` for
+//! decoded entries.
+
+pub mod address_lookup_table;
+pub mod associated_token;
+pub mod compute_budget;
+mod reader;
+pub mod system;
+pub mod token;
+
+use solana_message::{
+    compiled_instruction::CompiledInstruction, v0::LoadedAddresses, VersionedMessage,
+};
+use solana_pubkey::Pubkey;
+use solana_transaction_status_client_types::InnerInstructions;
+
+pub use crate::{
+    address_lookup_table::AddressLookupTableInstruction,
+    associated_token::AssociatedTokenInstruction, compute_budget::ComputeBudgetInstruction,
+    system::SystemInstruction, token::TokenInstruction,
+};
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum InstructionDecodeError {
+    /// The index points past the known keys, e.g. into an address lookup
+    /// table while the loaded addresses were not provided.
+    #[error("account index {0} is out of range")]
+    AccountIndexOutOfRange(u8),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ParsedInstruction {
+    System(SystemInstruction),
+    ComputeBudget(ComputeBudgetInstruction),
+    /// SPL Token and the instructions Token-2022 shares with it.
+    Token(TokenInstruction),
+    AssociatedToken(AssociatedTokenInstruction),
+    AddressLookupTable(AddressLookupTableInstruction),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DecodedInstruction {
+    pub program_id: Pubkey,
+    pub accounts: Vec<Pubkey>,
+    pub data: Vec<u8>,
+    /// `None` for programs that are not supported and for data that is not
+    /// understood, which RPC reports as `partiallyDecoded`.
+    pub parsed: Option<ParsedInstruction>,
+    /// Set for inner instructions, top-level instructions have none like in RPC responses.
+    pub stack_height: Option<u32>,
+}
+
+/// A top-level instruction together with the instructions it invoked.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TransactionInstruction {
+    pub instruction: Result<DecodedInstruction, InstructionDecodeError>,
+    pub inner_instructions: Vec<Result<DecodedInstruction, InstructionDecodeError>>,
+}
+
+/// Keys in the order instructions index them: static keys, then loaded
+/// writable and loaded readonly addresses.
+pub fn account_keys(
+    message: &VersionedMessage,
+    loaded_addresses: Option<&LoadedAddresses>,
+) -> Vec<Pubkey> {
+    let mut keys = message.static_account_keys().to_vec();
+    if let Some(loaded_addresses) = loaded_addresses {
+        keys.extend(&loaded_addresses.writable);
+        keys.extend(&loaded_addresses.readonly);
+    }
+    keys
+}
+
+pub fn decode_instruction(
+    account_keys: &[Pubkey],
+    instruction: &CompiledInstruction,
+    stack_height: Option<u32>,
+) -> Result<DecodedInstruction, InstructionDecodeError> {
+    let key = |index: u8| {
+        account_keys
+            .get(usize::from(index))
+            .copied()
+            .ok_or(InstructionDecodeError::AccountIndexOutOfRange(index))
+    };
+    let program_id = key(instruction.program_id_index)?;
+    let accounts = instruction
+        .accounts
+        .iter()
+        .map(|index| key(*index))
+        .collect::<Result<Vec<_>, _>>()?;
+
+    Ok(DecodedInstruction {
+        parsed: parse(&program_id, &accounts, &instruction.data),
+        program_id,
+        accounts,
+        data: instruction.data.clone(),
+        stack_height,
+    })
+}
+
+/// Decodes every instruction of a transaction.
+///
+/// Without `loaded_addresses`, instructions of v0 messages using lookup
+/// tables fail to resolve their accounts, as is the case for shredstream
+/// entries. `inner_instructions` come from the transaction status meta.
+pub fn decode_transaction(
+    message: &VersionedMessage,
+    loaded_addresses: Option<&LoadedAddresses>,
+    inner_instructions: Option<&[InnerInstructions]>,
+) -> Vec<TransactionInstruction> {
+    let keys = account_keys(message, loaded_addresses);
+
+    message
+        .instructions()
+        .iter()
+        .enumerate()
+        .map(|(index, instruction)| TransactionInstruction {
+            instruction: decode_instruction(&keys, instruction, None),
+            inner_instructions: inner_instructions
+                .into_iter()
+                .flatten()
+                .filter(|inner| usize::from(inner.index) == index)
+                .flat_map(|inner| &inner.instructions)
+                .map(|inner| decode_instruction(&keys, &inner.instruction, inner.stack_height))
+                .collect(),
+        })
+        .collect()
+}
+
+/// Parses `data` of a supported program with its resolved accounts.
+pub fn parse(program_id: &Pubkey, accounts: &[Pubkey], data: &[u8]) -> Option<ParsedInstruction> {
+    match *program_id {
+        system::ID => system::parse(accounts, data).map(ParsedInstruction::System),
+        compute_budget::ID => compute_budget::parse(data).map(ParsedInstruction::ComputeBudget),
+        token::ID | token::TOKEN_2022_ID => {
+            token::parse(accounts, data).map(ParsedInstruction::Token)
+        }
+        associated_token::ID => {
+            associated_token::parse(accounts, data).map(ParsedInstruction::AssociatedToken)
+        }
+        address_lookup_table::ID => {
+            address_lookup_table::parse(accounts, data).map(ParsedInstruction::AddressLookupTable)
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{address_lookup_table::AddressLookupTableInstruction, token::AuthorityType};
+    use solana_message::{legacy, MessageHeader};
+    use solana_transaction_status_client_types::InnerInstruction;
+
+    fn key(byte: u8) -> Pubkey {
+        Pubkey::new_from_array([byte; 32])
+    }
+
+    fn keys(bytes: &[u8]) -> Vec<Pubkey> {
+        bytes.iter().copied().map(key).collect()
+    }
+
+    #[test]
+    fn parses_system() {
+        // Transfer of 1_000_000 lamports.
+        let data = [2, 0, 0, 0, 0x40, 0x42, 0x0f, 0, 0, 0, 0, 0];
+        assert_eq!(
+            parse(&system::ID, &keys(&[1, 2]), &data),
+            Some(ParsedInstruction::System(SystemInstruction::Transfer {
+                source: key(1),
+                destination: key(2),
+                lamports: 1_000_000,
+            }))
+        );
+
+        // CreateAccountWithSeed with the seed "ab".
+        let data = [
+            [3, 0, 0, 0].as_slice(),
+            &[3; 32],
+            &[2, 0, 0, 0, 0, 0, 0, 0],
+            b"ab",
+            &[10, 0, 0, 0, 0, 0, 0, 0],
+            &[165, 0, 0, 0, 0, 0, 0, 0],
+            &[4; 32],
+        ]
+        .concat();
+        assert_eq!(
+            parse(&system::ID, &keys(&[1, 2]), &data),
+            Some(ParsedInstruction::System(
+                SystemInstruction::CreateAccountWithSeed {
+                    source: key(1),
+                    new_account: key(2),
+                    base: key(3),
+                    seed: "ab".to_string(),
+                    lamports: 10,
+                    space: 165,
+                    owner: key(4),
+                }
+            ))
+        );
+
+        // Truncated data and missing accounts are not understood.
+        assert_eq!(parse(&system::ID, &keys(&[1, 2]), &data[..20]), None);
+        assert_eq!(
+            parse(
+                &system::ID,
+                &keys(&[1]),
+                &[2, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0]
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn parses_compute_budget() {
+        assert_eq!(
+            parse(&compute_budget::ID, &[], &[2, 0x40, 0x0d, 0x03, 0]),
+            Some(ParsedInstruction::ComputeBudget(
+                ComputeBudgetInstruction::SetComputeUnitLimit { units: 200_000 }
+            ))
+        );
+        assert_eq!(
+            parse(&compute_budget::ID, &[], &[3, 0xe8, 0x03, 0, 0, 0, 0, 0, 0]),
+            Some(ParsedInstruction::ComputeBudget(
+                ComputeBudgetInstruction::SetComputeUnitPrice {
+                    micro_lamports: 1000
+                }
+            ))
+        );
+        assert_eq!(parse(&compute_budget::ID, &[], &[9]), None);
+    }
+
+    #[test]
+    fn parses_token() {
+        // TransferChecked of 1_500_000 with 6 decimals and a 2-of-n multisig authority.
+        let data = [12, 0x60, 0xe3, 0x16, 0, 0, 0, 0, 0, 6];
+        let expected = ParsedInstruction::Token(TokenInstruction::TransferChecked {
+            source: key(1),
+            mint: key(2),
+            destination: key(3),
+            authority: key(4),
+            signers: keys(&[5, 6]),
+            amount: 1_500_000,
+            decimals: 6,
+        });
+        let accounts = keys(&[1, 2, 3, 4, 5, 6]);
+        assert_eq!(parse(&token::ID, &accounts, &data), Some(expected.clone()));
+        assert_eq!(
+            parse(&token::TOKEN_2022_ID, &accounts, &data),
+            Some(expected)
+        );
+
+        // SetAuthority removing the freeze authority.
+        assert_eq!(
+            parse(&token::ID, &keys(&[1, 2]), &[6, 1, 0]),
+            Some(ParsedInstruction::Token(TokenInstruction::SetAuthority {
+                account: key(1),
+                authority: key(2),
+                signers: Vec::new(),
+                authority_type: AuthorityType::FreezeAccount,
+                new_authority: None,
+            }))
+        );
+
+        // An invalid option tag.
+        assert_eq!(parse(&token::ID, &keys(&[1, 2]), &[6, 1, 2]), None);
+    }
+
+    #[test]
+    fn parses_associated_token() {
+        let accounts = keys(&[1, 2, 3, 4, 5, 6]);
+        let create = |data: &[u8]| parse(&associated_token::ID, &accounts, data);
+
+        assert!(matches!(
+            create(&[]),
+            Some(ParsedInstruction::AssociatedToken(
+                AssociatedTokenInstruction::Create { .. }
+            ))
+        ));
+        assert_eq!(
+            create(&[1]),
+            Some(ParsedInstruction::AssociatedToken(
+                AssociatedTokenInstruction::CreateIdempotent {
+                    source: key(1),
+                    account: key(2),
+                    wallet: key(3),
+                    mint: key(4),
+                    system_program: key(5),
+                    token_program: key(6),
+                }
+            ))
+        );
+        assert_eq!(create(&[3]), None);
+    }
+
+    #[test]
+    fn parses_address_lookup_table() {
+        // ExtendLookupTable with one address and no payer.
+        let data = [[2, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0].as_slice(), &[7; 32]].concat();
+        assert_eq!(
+            parse(&address_lookup_table::ID, &keys(&[1, 2]), &data),
+            Some(ParsedInstruction::AddressLookupTable(
+                AddressLookupTableInstruction::ExtendLookupTable {
+                    lookup_table_account: key(1),
+                    lookup_table_authority: key(2),
+                    payer_account: None,
+                    system_program: None,
+                    new_addresses: vec![key(7)],
+                }
+            ))
+        );
+        // The address count runs past the data.
+        assert_eq!(
+            parse(&address_lookup_table::ID, &keys(&[1, 2]), &data[..40]),
+            None
+        );
+    }
+
+    #[test]
+    fn decodes_transaction() {
+        let message = VersionedMessage::Legacy(legacy::Message {
+            header: MessageHeader::default(),
+            account_keys: vec![key(1), key(2), compute_budget::ID, key(9)],
+            recent_blockhash: Default::default(),
+            instructions: vec![
+                CompiledInstruction::new_from_raw_parts(
+                    2,
+                    vec![3, 0xe8, 3, 0, 0, 0, 0, 0, 0],
+                    vec![],
+                ),
+                CompiledInstruction::new_from_raw_parts(3, vec![1], vec![0, 1]),
+            ],
+        });
+        let inner = [InnerInstructions {
+            index: 1,
+            instructions: vec![InnerInstruction {
+                instruction: CompiledInstruction::new_from_raw_parts(
+                    2,
+                    vec![2, 1, 0, 0, 0],
+                    vec![],
+                ),
+                stack_height: Some(2),
+            }],
+        }];
+
+        let decoded = decode_transaction(&message, None, Some(&inner));
+        assert_eq!(decoded.len(), 2);
+        assert!(decoded[0].inner_instructions.is_empty());
+        assert!(matches!(
+            decoded[0].instruction.as_ref().unwrap().parsed,
+            Some(ParsedInstruction::ComputeBudget(_))
+        ));
+        // Unknown programs keep the raw data.
+        let unknown = decoded[1].instruction.as_ref().unwrap();
+        assert_eq!(unknown.program_id, key(9));
+        assert_eq!(unknown.accounts, keys(&[1, 2]));
+        assert_eq!(unknown.parsed, None);
+        let inner = decoded[1].inner_instructions[0].as_ref().unwrap();
+        assert_eq!(inner.stack_height, Some(2));
+        assert_eq!(
+            inner.parsed,
+            Some(ParsedInstruction::ComputeBudget(
+                ComputeBudgetInstruction::SetComputeUnitLimit { units: 1 }
+            ))
+        );
+
+        let out_of_range = CompiledInstruction::new_from_raw_parts(7, vec![], vec![]);
+        assert_eq!(
+            decode_instruction(&keys(&[1]), &out_of_range, None),
+            Err(InstructionDecodeError::AccountIndexOutOfRange(7))
+        );
+    }
+}
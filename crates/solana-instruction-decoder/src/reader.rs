@@ -0,0 +1,65 @@
+use solana_pubkey::Pubkey;
+
+/// Little-endian reader over instruction data, `None` once the data runs out.
+pub(crate) struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    pub(crate) fn new(data: &'a [u8]) -> Self {
+        Self { data }
+    }
+
+    pub(crate) fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.data.len() < len {
+            return None;
+        }
+        let (taken, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some(taken)
+    }
+
+    pub(crate) fn rest(&mut self) -> &'a [u8] {
+        std::mem::take(&mut self.data)
+    }
+
+    fn array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        self.take(N)?.try_into().ok()
+    }
+
+    pub(crate) fn u8(&mut self) -> Option<u8> {
+        self.array::<1>().map(|[byte]| byte)
+    }
+
+    pub(crate) fn u32(&mut self) -> Option<u32> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    pub(crate) fn u64(&mut self) -> Option<u64> {
+        self.array().map(u64::from_le_bytes)
+    }
+
+    pub(crate) fn pubkey(&mut self) -> Option<Pubkey> {
+        self.array().map(Pubkey::new_from_array)
+    }
+
+    /// A pubkey behind a one byte tag, as used by SPL Token instructions.
+    pub(crate) fn option_pubkey(&mut self) -> Option<Option<Pubkey>> {
+        match self.u8()? {
+            0 => Some(None),
+            1 => self.pubkey().map(Some),
+            _ => None,
+        }
+    }
+
+    /// A bincode string with its u64 length prefix.
+    pub(crate) fn string(&mut self) -> Option<String> {
+        let len = self.u64()?;
+        let bytes = self.take(len.try_into().ok()?)?;
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+}
+
+pub(crate) fn account(accounts: &[Pubkey], index: usize) -> Option<Pubkey> {
+    accounts.get(index).copied()
+}
@@ -0,0 +1,174 @@
+use solana_pubkey::Pubkey;
+
+use crate::reader::{account, Reader};
+
+pub const ID: Pubkey = Pubkey::from_str_const("11111111111111111111111111111111");
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SystemInstruction {
+    CreateAccount {
+        source: Pubkey,
+        new_account: Pubkey,
+        lamports: u64,
+        space: u64,
+        owner: Pubkey,
+    },
+    Assign {
+        account: Pubkey,
+        owner: Pubkey,
+    },
+    Transfer {
+        source: Pubkey,
+        destination: Pubkey,
+        lamports: u64,
+    },
+    CreateAccountWithSeed {
+        source: Pubkey,
+        new_account: Pubkey,
+        base: Pubkey,
+        seed: String,
+        lamports: u64,
+        space: u64,
+        owner: Pubkey,
+    },
+    AdvanceNonce {
+        nonce_account: Pubkey,
+        recent_blockhashes_sysvar: Pubkey,
+        nonce_authority: Pubkey,
+    },
+    WithdrawFromNonce {
+        nonce_account: Pubkey,
+        destination: Pubkey,
+        recent_blockhashes_sysvar: Pubkey,
+        rent_sysvar: Pubkey,
+        nonce_authority: Pubkey,
+        lamports: u64,
+    },
+    InitializeNonce {
+        nonce_account: Pubkey,
+        recent_blockhashes_sysvar: Pubkey,
+        rent_sysvar: Pubkey,
+        nonce_authority: Pubkey,
+    },
+    AuthorizeNonce {
+        nonce_account: Pubkey,
+        nonce_authority: Pubkey,
+        new_authorized: Pubkey,
+    },
+    Allocate {
+        account: Pubkey,
+        space: u64,
+    },
+    AllocateWithSeed {
+        account: Pubkey,
+        base: Pubkey,
+        seed: String,
+        space: u64,
+        owner: Pubkey,
+    },
+    AssignWithSeed {
+        account: Pubkey,
+        base: Pubkey,
+        seed: String,
+        owner: Pubkey,
+    },
+    TransferWithSeed {
+        source: Pubkey,
+        source_base: Pubkey,
+        destination: Pubkey,
+        lamports: u64,
+        source_seed: String,
+        source_owner: Pubkey,
+    },
+    UpgradeNonce {
+        nonce_account: Pubkey,
+    },
+}
+
+pub(crate) fn parse(accounts: &[Pubkey], data: &[u8]) -> Option<SystemInstruction> {
+    let mut data = Reader::new(data);
+    let account = |index| account(accounts, index);
+
+    let instruction = match data.u32()? {
+        0 => SystemInstruction::CreateAccount {
+            source: account(0)?,
+            new_account: account(1)?,
+            lamports: data.u64()?,
+            space: data.u64()?,
+            owner: data.pubkey()?,
+        },
+        1 => SystemInstruction::Assign {
+            account: account(0)?,
+            owner: data.pubkey()?,
+        },
+        2 => SystemInstruction::Transfer {
+            source: account(0)?,
+            destination: account(1)?,
+            lamports: data.u64()?,
+        },
+        3 => SystemInstruction::CreateAccountWithSeed {
+            source: account(0)?,
+            new_account: account(1)?,
+            base: data.pubkey()?,
+            seed: data.string()?,
+            lamports: data.u64()?,
+            space: data.u64()?,
+            owner: data.pubkey()?,
+        },
+        4 => SystemInstruction::AdvanceNonce {
+            nonce_account: account(0)?,
+            recent_blockhashes_sysvar: account(1)?,
+            nonce_authority: account(2)?,
+        },
+        5 => SystemInstruction::WithdrawFromNonce {
+            nonce_account: account(0)?,
+            destination: account(1)?,
+            recent_blockhashes_sysvar: account(2)?,
+            rent_sysvar: account(3)?,
+            nonce_authority: account(4)?,
+            lamports: data.u64()?,
+        },
+        6 => SystemInstruction::InitializeNonce {
+            nonce_account: account(0)?,
+            recent_blockhashes_sysvar: account(1)?,
+            rent_sysvar: account(2)?,
+            nonce_authority: data.pubkey()?,
+        },
+        7 => SystemInstruction::AuthorizeNonce {
+            nonce_account: account(0)?,
+            nonce_authority: account(1)?,
+            new_authorized: data.pubkey()?,
+        },
+        8 => SystemInstruction::Allocate {
+            account: account(0)?,
+            space: data.u64()?,
+        },
+        9 => SystemInstruction::AllocateWithSeed {
+            account: account(0)?,
+            base: data.pubkey()?,
+            seed: data.string()?,
+            space: data.u64()?,
+            owner: data.pubkey()?,
+        },
+        10 => SystemInstruction::AssignWithSeed {
+            account: account(0)?,
+            base: data.pubkey()?,
+            seed: data.string()?,
+            owner: data.pubkey()?,
+        },
+        11 => SystemInstruction::TransferWithSeed {
+            source: account(0)?,
+            source_base: account(1)?,
+            destination: account(2)?,
+            lamports: data.u64()?,
+            source_seed: data.string()?,
+            source_owner: data.pubkey()?,
+        },
+        12 => SystemInstruction::UpgradeNonce {
+            nonce_account: account(0)?,
+        },
+        _ => return None,
+    };
+
+    Some(instruction)
+}
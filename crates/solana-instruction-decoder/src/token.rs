@@ -0,0 +1,387 @@
+use solana_pubkey::Pubkey;
+
+use crate::reader::{account, Reader};
+
+pub const ID: Pubkey = Pubkey::from_str_const("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA");
+pub const TOKEN_2022_ID: Pubkey =
+    Pubkey::from_str_const("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb");
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AuthorityType {
+    MintTokens,
+    FreezeAccount,
+    AccountOwner,
+    CloseAccount,
+    TransferFeeConfig,
+    WithheldWithdraw,
+    CloseMint,
+    InterestRate,
+    PermanentDelegate,
+    ConfidentialTransferMint,
+    TransferHookProgramId,
+    ConfidentialTransferFeeConfig,
+    MetadataPointer,
+    GroupPointer,
+    GroupMemberPointer,
+    ScaledUiAmount,
+    Pause,
+}
+
+impl AuthorityType {
+    fn from_u8(value: u8) -> Option<Self> {
+        Some(match value {
+            0 => Self::MintTokens,
+            1 => Self::FreezeAccount,
+            2 => Self::AccountOwner,
+            3 => Self::CloseAccount,
+            4 => Self::TransferFeeConfig,
+            5 => Self::WithheldWithdraw,
+            6 => Self::CloseMint,
+            7 => Self::InterestRate,
+            8 => Self::PermanentDelegate,
+            9 => Self::ConfidentialTransferMint,
+            10 => Self::TransferHookProgramId,
+            11 => Self::ConfidentialTransferFeeConfig,
+            12 => Self::MetadataPointer,
+            13 => Self::GroupPointer,
+            14 => Self::GroupMemberPointer,
+            15 => Self::ScaledUiAmount,
+            16 => Self::Pause,
+            _ => return None,
+        })
+    }
+}
+
+/// Instructions shared by SPL Token and Token-2022.
+///
+/// `signers` lists the signers of a multisig `authority`, it is empty for a
+/// single authority. Token-2022 extension instructions are not parsed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TokenInstruction {
+    InitializeMint {
+        mint: Pubkey,
+        rent_sysvar: Option<Pubkey>,
+        decimals: u8,
+        mint_authority: Pubkey,
+        freeze_authority: Option<Pubkey>,
+    },
+    InitializeAccount {
+        account: Pubkey,
+        mint: Pubkey,
+        owner: Pubkey,
+        rent_sysvar: Option<Pubkey>,
+    },
+    InitializeMultisig {
+        multisig: Pubkey,
+        rent_sysvar: Option<Pubkey>,
+        signers: Vec<Pubkey>,
+        m: u8,
+    },
+    Transfer {
+        source: Pubkey,
+        destination: Pubkey,
+        authority: Pubkey,
+        signers: Vec<Pubkey>,
+        amount: u64,
+    },
+    Approve {
+        source: Pubkey,
+        delegate: Pubkey,
+        owner: Pubkey,
+        signers: Vec<Pubkey>,
+        amount: u64,
+    },
+    Revoke {
+        source: Pubkey,
+        owner: Pubkey,
+        signers: Vec<Pubkey>,
+    },
+    SetAuthority {
+        /// The mint or account whose authority changes.
+        account: Pubkey,
+        authority: Pubkey,
+        signers: Vec<Pubkey>,
+        authority_type: AuthorityType,
+        new_authority: Option<Pubkey>,
+    },
+    MintTo {
+        mint: Pubkey,
+        account: Pubkey,
+        mint_authority: Pubkey,
+        signers: Vec<Pubkey>,
+        amount: u64,
+    },
+    Burn {
+        account: Pubkey,
+        mint: Pubkey,
+        authority: Pubkey,
+        signers: Vec<Pubkey>,
+        amount: u64,
+    },
+    CloseAccount {
+        account: Pubkey,
+        destination: Pubkey,
+        owner: Pubkey,
+        signers: Vec<Pubkey>,
+    },
+    FreezeAccount {
+        account: Pubkey,
+        mint: Pubkey,
+        freeze_authority: Pubkey,
+        signers: Vec<Pubkey>,
+    },
+    ThawAccount {
+        account: Pubkey,
+        mint: Pubkey,
+        freeze_authority: Pubkey,
+        signers: Vec<Pubkey>,
+    },
+    TransferChecked {
+        source: Pubkey,
+        mint: Pubkey,
+        destination: Pubkey,
+        authority: Pubkey,
+        signers: Vec<Pubkey>,
+        amount: u64,
+        decimals: u8,
+    },
+    ApproveChecked {
+        source: Pubkey,
+        mint: Pubkey,
+        delegate: Pubkey,
+        owner: Pubkey,
+        signers: Vec<Pubkey>,
+        amount: u64,
+        decimals: u8,
+    },
+    MintToChecked {
+        mint: Pubkey,
+        account: Pubkey,
+        mint_authority: Pubkey,
+        signers: Vec<Pubkey>,
+        amount: u64,
+        decimals: u8,
+    },
+    BurnChecked {
+        account: Pubkey,
+        mint: Pubkey,
+        authority: Pubkey,
+        signers: Vec<Pubkey>,
+        amount: u64,
+        decimals: u8,
+    },
+    InitializeAccount2 {
+        account: Pubkey,
+        mint: Pubkey,
+        rent_sysvar: Option<Pubkey>,
+        owner: Pubkey,
+    },
+    SyncNative {
+        account: Pubkey,
+    },
+    InitializeAccount3 {
+        account: Pubkey,
+        mint: Pubkey,
+        owner: Pubkey,
+    },
+    InitializeMultisig2 {
+        multisig: Pubkey,
+        signers: Vec<Pubkey>,
+        m: u8,
+    },
+    InitializeMint2 {
+        mint: Pubkey,
+        decimals: u8,
+        mint_authority: Pubkey,
+        freeze_authority: Option<Pubkey>,
+    },
+    GetAccountDataSize {
+        mint: Pubkey,
+        /// Token-2022 extension types to size the account for.
+        extension_types: Vec<u16>,
+    },
+    InitializeImmutableOwner {
+        account: Pubkey,
+    },
+    AmountToUiAmount {
+        mint: Pubkey,
+        amount: u64,
+    },
+    UiAmountToAmount {
+        mint: Pubkey,
+        ui_amount: String,
+    },
+}
+
+pub(crate) fn parse(accounts: &[Pubkey], data: &[u8]) -> Option<TokenInstruction> {
+    let mut data = Reader::new(data);
+    let account = |index| account(accounts, index);
+    let signers = |from: usize| accounts.get(from..).unwrap_or_default().to_vec();
+
+    let instruction = match data.u8()? {
+        0 => TokenInstruction::InitializeMint {
+            mint: account(0)?,
+            rent_sysvar: account(1),
+            decimals: data.u8()?,
+            mint_authority: data.pubkey()?,
+            freeze_authority: data.option_pubkey()?,
+        },
+        1 => TokenInstruction::InitializeAccount {
+            account: account(0)?,
+            mint: account(1)?,
+            owner: account(2)?,
+            rent_sysvar: account(3),
+        },
+        2 => TokenInstruction::InitializeMultisig {
+            multisig: account(0)?,
+            rent_sysvar: account(1),
+            signers: signers(2),
+            m: data.u8()?,
+        },
+        3 => TokenInstruction::Transfer {
+            source: account(0)?,
+            destination: account(1)?,
+            authority: account(2)?,
+            signers: signers(3),
+            amount: data.u64()?,
+        },
+        4 => TokenInstruction::Approve {
+            source: account(0)?,
+            delegate: account(1)?,
+            owner: account(2)?,
+            signers: signers(3),
+            amount: data.u64()?,
+        },
+        5 => TokenInstruction::Revoke {
+            source: account(0)?,
+            owner: account(1)?,
+            signers: signers(2),
+        },
+        6 => TokenInstruction::SetAuthority {
+            account: account(0)?,
+            authority: account(1)?,
+            signers: signers(2),
+            authority_type: AuthorityType::from_u8(data.u8()?)?,
+            new_authority: data.option_pubkey()?,
+        },
+        7 => TokenInstruction::MintTo {
+            mint: account(0)?,
+            account: account(1)?,
+            mint_authority: account(2)?,
+            signers: signers(3),
+            amount: data.u64()?,
+        },
+        8 => TokenInstruction::Burn {
+            account: account(0)?,
+            mint: account(1)?,
+            authority: account(2)?,
+            signers: signers(3),
+            amount: data.u64()?,
+        },
+        9 => TokenInstruction::CloseAccount {
+            account: account(0)?,
+            destination: account(1)?,
+            owner: account(2)?,
+            signers: signers(3),
+        },
+        10 => TokenInstruction::FreezeAccount {
+            account: account(0)?,
+            mint: account(1)?,
+            freeze_authority: account(2)?,
+            signers: signers(3),
+        },
+        11 => TokenInstruction::ThawAccount {
+            account: account(0)?,
+            mint: account(1)?,
+            freeze_authority: account(2)?,
+            signers: signers(3),
+        },
+        12 => TokenInstruction::TransferChecked {
+            source: account(0)?,
+            mint: account(1)?,
+            destination: account(2)?,
+            authority: account(3)?,
+            signers: signers(4),
+            amount: data.u64()?,
+            decimals: data.u8()?,
+        },
+        13 => TokenInstruction::ApproveChecked {
+            source: account(0)?,
+            mint: account(1)?,
+            delegate: account(2)?,
+            owner: account(3)?,
+            signers: signers(4),
+            amount: data.u64()?,
+            decimals: data.u8()?,
+        },
+        14 => TokenInstruction::MintToChecked {
+            mint: account(0)?,
+            account: account(1)?,
+            mint_authority: account(2)?,
+            signers: signers(3),
+            amount: data.u64()?,
+            decimals: data.u8()?,
+        },
+        15 => TokenInstruction::BurnChecked {
+            account: account(0)?,
+            mint: account(1)?,
+            authority: account(2)?,
+            signers: signers(3),
+            amount: data.u64()?,
+            decimals: data.u8()?,
+        },
+        16 => TokenInstruction::InitializeAccount2 {
+            account: account(0)?,
+            mint: account(1)?,
+            rent_sysvar: account(2),
+            owner: data.pubkey()?,
+        },
+        17 => TokenInstruction::SyncNative {
+            account: account(0)?,
+        },
+        18 => TokenInstruction::InitializeAccount3 {
+            account: account(0)?,
+            mint: account(1)?,
+            owner: data.pubkey()?,
+        },
+        19 => TokenInstruction::InitializeMultisig2 {
+            multisig: account(0)?,
+            signers: signers(1),
+            m: data.u8()?,
+        },
+        20 => TokenInstruction::InitializeMint2 {
+            mint: account(0)?,
+            decimals: data.u8()?,
+            mint_authority: data.pubkey()?,
+            freeze_authority: data.option_pubkey()?,
+        },
+        21 => {
+            let mint = account(0)?;
+            let rest = data.rest();
+            if !rest.len().is_multiple_of(2) {
+                return None;
+            }
+            TokenInstruction::GetAccountDataSize {
+                mint,
+                extension_types: rest
+                    .chunks_exact(2)
+                    .map(|chunk| u16::from_le_bytes([chunk[0], chunk[1]]))
+                    .collect(),
+            }
+        }
+        22 => TokenInstruction::InitializeImmutableOwner {
+            account: account(0)?,
+        },
+        23 => TokenInstruction::AmountToUiAmount {
+            mint: account(0)?,
+            amount: data.u64()?,
+        },
+        24 => TokenInstruction::UiAmountToAmount {
+            mint: account(0)?,
+            ui_amount: String::from_utf8(data.rest().to_vec()).ok()?,
+        },
+        _ => return None,
+    };
+
+    Some(instruction)
+}
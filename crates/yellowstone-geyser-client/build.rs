@@ -1,5 +1,7 @@
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    tonic_build::compile_protos("proto/geyser.proto")?;
+    tonic_build::configure()
+        .codec_path("grpc_transport::codec::ProstCodec")
+        .compile_protos(&["proto/geyser.proto"], &["proto"])?;
 
     Ok(())
 }
@@ -0,0 +1,545 @@
+use futures::channel::mpsc;
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    sync::Arc,
+};
+
+use crate::{
+    proto::geyser::{
+        subscribe_update::UpdateOneof, CommitmentLevel, SlotStatus, SubscribeUpdate,
+        SubscribeUpdateAccount, SubscribeUpdateAccountInfo, SubscribeUpdateSlot,
+    },
+    slot_tracker::{ForkState, SlotEvent, SlotTracker},
+};
+
+/// Writes kept per account while none of them is finalized.
+///
+/// Every write holds its own copy of the account data, so an account written
+/// in every slot costs up to this many copies until a write is finalized, for
+/// example 320 MiB for an account at the 10 MiB size limit.
+const MAX_VERSIONS: usize = 32;
+
+/// One write of an account.
+#[derive(Debug, Clone)]
+pub struct CachedAccount {
+    pub slot: u64,
+    /// The write came from the startup snapshot.
+    pub is_startup: bool,
+    pub info: Arc<SubscribeUpdateAccountInfo>,
+}
+
+impl CachedAccount {
+    fn version(&self) -> (u64, u64) {
+        (self.slot, self.info.write_version)
+    }
+}
+
+/// Change of the visible processed state of a watched account.
+#[derive(Debug, Clone)]
+pub enum AccountChange {
+    /// A new latest write, or an older write that is visible again after a rollback.
+    Updated(CachedAccount),
+    /// Every cached write was rolled back with `slot`, the account is no longer cached.
+    Removed { pubkey: Vec<u8>, slot: u64 },
+}
+
+/// Writes of one account that are not superseded by a finalized write yet.
+#[derive(Debug, Default)]
+struct AccountVersions {
+    versions: BTreeMap<(u64, u64), CachedAccount>,
+}
+
+/// Latest state of every account seen on a `Subscribe` stream.
+///
+/// Writes are ordered by slot and `write_version`, so late or replayed
+/// updates never replace newer state. Slot updates on the same stream drive
+/// the processed, confirmed and finalized views, and writes in dead or
+/// abandoned slots are dropped. Startup snapshot writes count as finalized.
+///
+/// Without slot updates, writes more than the tracker's retention behind the
+/// newest write count as finalized, so that old writes are still pruned.
+#[derive(Debug, Default)]
+pub struct AccountCache {
+    accounts: HashMap<Vec<u8>, AccountVersions>,
+    by_owner: HashMap<Vec<u8>, HashSet<Vec<u8>>>,
+    by_slot: BTreeMap<u64, HashSet<Vec<u8>>>,
+    /// Newest slot of any write.
+    newest_slot: Option<u64>,
+    tracker: SlotTracker,
+    startup_seen: bool,
+    snapshot_complete: bool,
+    watchers: HashMap<Vec<u8>, Vec<mpsc::UnboundedSender<AccountChange>>>,
+}
+
+impl AccountCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Feeds account and slot updates, anything else is ignored.
+    pub fn handle(&mut self, update: &SubscribeUpdate) {
+        match update.update_oneof.as_ref() {
+            Some(UpdateOneof::Account(account)) => self.insert(account),
+            Some(UpdateOneof::Slot(slot)) => self.update_slot(slot),
+            _ => {}
+        }
+    }
+
+    pub fn insert(&mut self, account: &SubscribeUpdateAccount) {
+        let Some(info) = account.account.as_ref() else {
+            return;
+        };
+        if account.is_startup {
+            self.startup_seen = true;
+        } else if self.startup_seen {
+            self.snapshot_complete = true;
+        }
+        if self
+            .tracker
+            .get(account.slot)
+            .is_some_and(|slot| matches!(slot.fork, ForkState::Dead | ForkState::Abandoned))
+        {
+            return;
+        }
+
+        let pubkey = info.pubkey.clone();
+        let write = CachedAccount {
+            slot: account.slot,
+            is_startup: account.is_startup,
+            info: Arc::new(info.clone()),
+        };
+        let entry = self.accounts.entry(pubkey.clone()).or_default();
+        let is_latest = entry
+            .versions
+            .last_key_value()
+            .is_none_or(|(version, _)| *version < write.version());
+        if entry
+            .versions
+            .insert(write.version(), write.clone())
+            .is_some()
+        {
+            return;
+        }
+
+        self.by_owner
+            .entry(info.owner.clone())
+            .or_default()
+            .insert(pubkey.clone());
+        self.by_slot
+            .entry(account.slot)
+            .or_default()
+            .insert(pubkey.clone());
+        self.newest_slot = Some(
+            self.newest_slot
+                .map_or(account.slot, |x| x.max(account.slot)),
+        );
+        self.trim_slots();
+        self.prune(&pubkey);
+
+        if is_latest {
+            self.notify(&pubkey, AccountChange::Updated(write));
+        }
+    }
+
+    pub fn update_slot(&mut self, slot: &SubscribeUpdateSlot) {
+        for event in self.tracker.update(slot) {
+            match event {
+                SlotEvent::Dead { slot, .. }
+                | SlotEvent::Abandoned { slot }
+                | SlotEvent::Skipped { slot } => self.drop_slot(slot),
+                SlotEvent::Rooted { slot } => {
+                    let pubkeys = self.by_slot.get(&slot).cloned().unwrap_or_default();
+                    for pubkey in pubkeys {
+                        self.prune(&pubkey);
+                    }
+                }
+            }
+        }
+
+        self.trim_slots();
+    }
+
+    /// Latest write of `pubkey` whose slot reached `commitment`.
+    pub fn get(&self, pubkey: &[u8], commitment: CommitmentLevel) -> Option<&CachedAccount> {
+        self.accounts
+            .get(pubkey)?
+            .versions
+            .values()
+            .rev()
+            .find(|write| self.reached(write, commitment))
+    }
+
+    /// Accounts currently owned by `owner` in the given view.
+    pub fn by_owner<'a>(
+        &'a self,
+        owner: &'a [u8],
+        commitment: CommitmentLevel,
+    ) -> impl Iterator<Item = &'a CachedAccount> + 'a {
+        self.by_owner
+            .get(owner)
+            .into_iter()
+            .flatten()
+            .filter_map(move |pubkey| self.get(pubkey, commitment))
+            .filter(move |write| write.info.owner == owner)
+    }
+
+    pub fn len(&self) -> usize {
+        self.accounts.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.accounts.is_empty()
+    }
+
+    /// Whether the startup snapshot was received in full, that is a regular
+    /// write followed the startup writes.
+    pub fn snapshot_complete(&self) -> bool {
+        self.snapshot_complete
+    }
+
+    pub fn tracker(&self) -> &SlotTracker {
+        &self.tracker
+    }
+
+    /// Receives every change of the latest write of `pubkey`, at processed commitment.
+    pub fn watch(&mut self, pubkey: impl Into<Vec<u8>>) -> mpsc::UnboundedReceiver<AccountChange> {
+        let (sender, receiver) = mpsc::unbounded();
+        self.watchers.entry(pubkey.into()).or_default().push(sender);
+        receiver
+    }
+
+    fn reached(&self, write: &CachedAccount, commitment: CommitmentLevel) -> bool {
+        if write.is_startup {
+            return true;
+        }
+
+        let status = match commitment {
+            CommitmentLevel::Processed => SlotStatus::SlotProcessed,
+            CommitmentLevel::Confirmed => SlotStatus::SlotConfirmed,
+            CommitmentLevel::Finalized => SlotStatus::SlotFinalized,
+        };
+        match self.tracker.get(write.slot) {
+            Some(slot) => match slot.fork {
+                ForkState::Dead | ForkState::Abandoned => false,
+                ForkState::Rooted => true,
+                ForkState::Pending => {
+                    status == SlotStatus::SlotProcessed
+                        || slot
+                            .commitment
+                            .is_some_and(|commitment| commitment >= status)
+                }
+            },
+            None if self
+                .settled_below()
+                .is_some_and(|settled| write.slot < settled) =>
+            {
+                true
+            }
+            None => status == SlotStatus::SlotProcessed,
+        }
+    }
+
+    /// Slots below this are long settled: older than the tracked slots or,
+    /// while no slot updates arrived, too far behind the newest write.
+    fn settled_below(&self) -> Option<u64> {
+        self.tracker.oldest_slot().or_else(|| {
+            self.newest_slot
+                .map(|newest| newest.saturating_sub(self.tracker.retention_slots()))
+        })
+    }
+
+    /// Drops the slot index below the settled slots, writes there are never rolled back.
+    fn trim_slots(&mut self) {
+        let Some(settled) = self.settled_below() else {
+            return;
+        };
+        if self
+            .by_slot
+            .first_key_value()
+            .is_some_and(|(slot, _)| *slot < settled)
+        {
+            self.by_slot = self.by_slot.split_off(&settled);
+        }
+    }
+
+    /// Drops writes superseded by a newer finalized write, and the oldest
+    /// writes beyond `MAX_VERSIONS` when slots are not finalized.
+    fn prune(&mut self, pubkey: &[u8]) {
+        let Some(entry) = self.accounts.get(pubkey) else {
+            return;
+        };
+        let finalized = entry
+            .versions
+            .values()
+            .rev()
+            .find(|write| self.reached(write, CommitmentLevel::Finalized))
+            .map(CachedAccount::version);
+
+        let entry = self.accounts.get_mut(pubkey).expect("checked above");
+        let mut removed = match finalized {
+            Some(finalized) => {
+                let kept = entry.versions.split_off(&finalized);
+                std::mem::replace(&mut entry.versions, kept)
+                    .into_values()
+                    .collect::<Vec<_>>()
+            }
+            None => Vec::new(),
+        };
+        while entry.versions.len() > MAX_VERSIONS {
+            removed.extend(entry.versions.pop_first().map(|(_, write)| write));
+        }
+
+        for write in removed {
+            self.forget(pubkey, &write);
+        }
+    }
+
+    /// Removes all writes made in `slot`.
+    fn drop_slot(&mut self, slot: u64) {
+        let Some(pubkeys) = self.by_slot.remove(&slot) else {
+            return;
+        };
+
+        for pubkey in pubkeys {
+            let Some(entry) = self.accounts.get_mut(&pubkey) else {
+                continue;
+            };
+            let latest = entry.versions.last_key_value().map(|(version, _)| *version);
+            let versions = entry
+                .versions
+                .range((slot, 0)..=(slot, u64::MAX))
+                .map(|(version, _)| *version)
+                .collect::<Vec<_>>();
+            let removed = versions
+                .iter()
+                .filter_map(|version| entry.versions.remove(version))
+                .collect::<Vec<_>>();
+            let new_latest = entry
+                .versions
+                .last_key_value()
+                .map(|(_, write)| write.clone());
+            if entry.versions.is_empty() {
+                self.accounts.remove(&pubkey);
+            }
+
+            for write in &removed {
+                self.forget(&pubkey, write);
+            }
+            // The rolled back write was the visible one, announce what is visible now.
+            let change = match new_latest {
+                Some(write) if latest == Some(write.version()) => continue,
+                Some(write) => AccountChange::Updated(write),
+                None => AccountChange::Removed {
+                    pubkey: pubkey.clone(),
+                    slot,
+                },
+            };
+            self.notify(&pubkey, change);
+        }
+    }
+
+    /// Removes index entries of a write that is no longer stored.
+    fn forget(&mut self, pubkey: &[u8], write: &CachedAccount) {
+        let versions = self.accounts.get(pubkey).map(|entry| &entry.versions);
+        let owner = &write.info.owner;
+        let owner_used = versions
+            .is_some_and(|versions| versions.values().any(|other| &other.info.owner == owner));
+        if !owner_used {
+            if let Some(pubkeys) = self.by_owner.get_mut(owner) {
+                pubkeys.remove(pubkey);
+                if pubkeys.is_empty() {
+                    self.by_owner.remove(owner);
+                }
+            }
+        }
+
+        let slot_used =
+            versions.is_some_and(|versions| versions.keys().any(|(slot, _)| *slot == write.slot));
+        if !slot_used {
+            if let Some(pubkeys) = self.by_slot.get_mut(&write.slot) {
+                pubkeys.remove(pubkey);
+                if pubkeys.is_empty() {
+                    self.by_slot.remove(&write.slot);
+                }
+            }
+        }
+    }
+
+    fn notify(&mut self, pubkey: &[u8], change: AccountChange) {
+        let Some(watchers) = self.watchers.get_mut(pubkey) else {
+            return;
+        };
+        watchers.retain(|watcher| watcher.unbounded_send(change.clone()).is_ok());
+        if watchers.is_empty() {
+            self.watchers.remove(pubkey);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{FutureExt, StreamExt};
+
+    const OWNER: [u8; 32] = [9; 32];
+
+    fn account(pubkey: u8, slot: u64, write_version: u64, lamports: u64) -> SubscribeUpdate {
+        SubscribeUpdate {
+            update_oneof: Some(UpdateOneof::Account(SubscribeUpdateAccount {
+                account: Some(SubscribeUpdateAccountInfo {
+                    pubkey: vec![pubkey; 32],
+                    owner: OWNER.to_vec(),
+                    lamports,
+                    write_version,
+                    ..Default::default()
+                }),
+                slot,
+                is_startup: false,
+            })),
+            ..Default::default()
+        }
+    }
+
+    fn slot(slot: u64, parent: Option<u64>, status: SlotStatus) -> SubscribeUpdate {
+        SubscribeUpdate {
+            update_oneof: Some(UpdateOneof::Slot(SubscribeUpdateSlot {
+                slot,
+                parent,
+                status: status as i32,
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+    }
+
+    fn lamports(cache: &AccountCache, pubkey: u8, commitment: CommitmentLevel) -> Option<u64> {
+        cache
+            .get(&[pubkey; 32], commitment)
+            .map(|write| write.info.lamports)
+    }
+
+    #[test]
+    fn keeps_newest_write() {
+        let mut cache = AccountCache::new();
+        cache.handle(&account(1, 10, 2, 200));
+        // Older slot and older write version in the same slot.
+        cache.handle(&account(1, 9, 5, 90));
+        cache.handle(&account(1, 10, 1, 100));
+
+        assert_eq!(lamports(&cache, 1, CommitmentLevel::Processed), Some(200));
+        assert_eq!(cache.len(), 1);
+        assert_eq!(
+            cache.by_owner(&OWNER, CommitmentLevel::Processed).count(),
+            1
+        );
+    }
+
+    #[test]
+    fn serves_commitment_views() {
+        let mut cache = AccountCache::new();
+        cache.handle(&slot(10, Some(9), SlotStatus::SlotProcessed));
+        cache.handle(&slot(11, Some(10), SlotStatus::SlotProcessed));
+        cache.handle(&account(1, 10, 1, 100));
+        cache.handle(&account(1, 11, 2, 110));
+
+        assert_eq!(lamports(&cache, 1, CommitmentLevel::Processed), Some(110));
+        assert_eq!(lamports(&cache, 1, CommitmentLevel::Confirmed), None);
+
+        cache.handle(&slot(10, None, SlotStatus::SlotConfirmed));
+        assert_eq!(lamports(&cache, 1, CommitmentLevel::Confirmed), Some(100));
+        assert_eq!(lamports(&cache, 1, CommitmentLevel::Finalized), None);
+
+        cache.handle(&slot(11, None, SlotStatus::SlotFinalized));
+        assert_eq!(lamports(&cache, 1, CommitmentLevel::Finalized), Some(110));
+    }
+
+    #[test]
+    fn rolls_back_dead_slot() {
+        let mut cache = AccountCache::new();
+        cache.handle(&slot(10, Some(9), SlotStatus::SlotProcessed));
+        cache.handle(&slot(11, Some(10), SlotStatus::SlotProcessed));
+        cache.handle(&account(1, 10, 1, 100));
+        let mut changes = cache.watch(vec![1; 32]);
+        cache.handle(&account(1, 11, 2, 110));
+
+        cache.handle(&slot(11, None, SlotStatus::SlotDead));
+        assert_eq!(lamports(&cache, 1, CommitmentLevel::Processed), Some(100));
+        // Late writes to the dead slot are ignored.
+        cache.handle(&account(1, 11, 3, 111));
+        assert_eq!(lamports(&cache, 1, CommitmentLevel::Processed), Some(100));
+
+        let changes = std::iter::from_fn(|| changes.next().now_or_never().flatten())
+            .map(|change| match change {
+                AccountChange::Updated(write) => Some(write.info.lamports),
+                AccountChange::Removed { .. } => None,
+            })
+            .collect::<Vec<_>>();
+        assert_eq!(changes, [Some(110), Some(100)]);
+    }
+
+    #[test]
+    fn notifies_removal_when_every_write_rolls_back() {
+        let mut cache = AccountCache::new();
+        cache.handle(&slot(10, Some(9), SlotStatus::SlotProcessed));
+        let mut changes = cache.watch(vec![1; 32]);
+        cache.handle(&account(1, 10, 1, 100));
+
+        cache.handle(&slot(10, None, SlotStatus::SlotDead));
+        assert!(cache.is_empty());
+        assert_eq!(
+            cache.by_owner(&OWNER, CommitmentLevel::Processed).count(),
+            0
+        );
+
+        assert!(matches!(
+            changes.next().now_or_never(),
+            Some(Some(AccountChange::Updated(_)))
+        ));
+        match changes.next().now_or_never() {
+            Some(Some(AccountChange::Removed { pubkey, slot })) => {
+                assert_eq!(pubkey, [1; 32]);
+                assert_eq!(slot, 10);
+            }
+            change => panic!("expected a removal, got {change:?}"),
+        }
+    }
+
+    #[test]
+    fn prunes_versions() {
+        let mut cache = AccountCache::new();
+        for slot in 1..=MAX_VERSIONS as u64 + 8 {
+            cache.handle(&account(1, slot, slot, slot));
+        }
+        assert_eq!(
+            cache.accounts[[1; 32].as_slice()].versions.len(),
+            MAX_VERSIONS
+        );
+        assert_eq!(cache.by_slot.len(), MAX_VERSIONS);
+
+        // A finalized write supersedes everything older.
+        cache.handle(&slot(30, Some(29), SlotStatus::SlotFinalized));
+        assert_eq!(cache.accounts[[1; 32].as_slice()].versions.len(), 11);
+        assert_eq!(lamports(&cache, 1, CommitmentLevel::Finalized), Some(30));
+    }
+
+    #[test]
+    fn prunes_without_slot_updates() {
+        let mut cache = AccountCache {
+            tracker: SlotTracker::new(4),
+            ..Default::default()
+        };
+        for slot in 1..=20 {
+            cache.handle(&account(1, slot, slot, slot));
+        }
+
+        // Writes more than 4 slots behind the newest one are settled, the
+        // newest of them supersedes everything older.
+        assert_eq!(cache.accounts[[1; 32].as_slice()].versions.len(), 6);
+        assert_eq!(
+            cache.by_slot.keys().copied().collect::<Vec<_>>(),
+            [16, 17, 18, 19, 20]
+        );
+        assert_eq!(lamports(&cache, 1, CommitmentLevel::Finalized), Some(15));
+        assert_eq!(lamports(&cache, 1, CommitmentLevel::Processed), Some(20));
+    }
+}
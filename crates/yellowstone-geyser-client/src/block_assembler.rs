@@ -0,0 +1,431 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+
+use crate::proto::geyser::{
+    subscribe_update::UpdateOneof, SlotStatus, SubscribeUpdate, SubscribeUpdateAccountInfo,
+    SubscribeUpdateBlock, SubscribeUpdateBlockMeta, SubscribeUpdateEntry,
+    SubscribeUpdateTransactionInfo,
+};
+
+#[derive(Debug, Clone)]
+pub struct BlockAssemblerConfig {
+    /// Wait for `executed_transaction_count` transactions. The subscription
+    /// must include vote and failed transactions for this to ever complete.
+    pub require_transactions: bool,
+    /// Wait for `entries_count` entries.
+    pub require_entries: bool,
+    /// Blocks this many slots behind the newest slot seen are reported as incomplete.
+    pub max_pending_slots: u64,
+}
+
+impl Default for BlockAssemblerConfig {
+    fn default() -> Self {
+        Self {
+            require_transactions: true,
+            require_entries: true,
+            max_pending_slots: 64,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IncompleteReason {
+    /// The slot was marked dead.
+    Dead,
+    /// Parts were still missing after `max_pending_slots`.
+    Expired,
+    /// Reported by [`BlockAssembler::flush`].
+    Flushed,
+}
+
+#[derive(Debug, Clone)]
+pub struct IncompleteBlock {
+    pub reason: IncompleteReason,
+    /// Whether the block meta arrived, without it the counts and hashes of `block` are unset.
+    pub meta_received: bool,
+    /// Everything received for the slot.
+    pub block: SubscribeUpdateBlock,
+}
+
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone)]
+pub enum BlockEvent {
+    Complete(SubscribeUpdateBlock),
+    Incomplete(IncompleteBlock),
+}
+
+#[derive(Debug, Default)]
+struct PartialBlock {
+    meta: Option<SubscribeUpdateBlockMeta>,
+    transactions: BTreeMap<u64, SubscribeUpdateTransactionInfo>,
+    accounts: HashMap<Vec<u8>, SubscribeUpdateAccountInfo>,
+    entries: BTreeMap<u64, SubscribeUpdateEntry>,
+}
+
+impl PartialBlock {
+    fn into_block(self, slot: u64) -> SubscribeUpdateBlock {
+        let meta = self.meta.unwrap_or_default();
+        let mut accounts = self.accounts.into_values().collect::<Vec<_>>();
+        accounts.sort_by_key(|account| account.write_version);
+
+        SubscribeUpdateBlock {
+            slot,
+            blockhash: meta.blockhash,
+            rewards: meta.rewards,
+            block_time: meta.block_time,
+            block_height: meta.block_height,
+            parent_slot: meta.parent_slot,
+            parent_blockhash: meta.parent_blockhash,
+            executed_transaction_count: meta.executed_transaction_count,
+            transactions: self.transactions.into_values().collect(),
+            updated_account_count: accounts.len() as u64,
+            accounts,
+            entries_count: meta.entries_count,
+            entries: self.entries.into_values().collect(),
+        }
+    }
+}
+
+/// Joins transaction, account, entry and block meta updates into blocks.
+///
+/// A block is complete once its block meta arrived and the required parts
+/// match `executed_transaction_count` and `entries_count` of the meta.
+/// Block meta carries no account count, so accounts never hold a block back.
+/// They are attached as received, one per pubkey at its latest write, and
+/// `updated_account_count` is the number attached.
+#[derive(Debug, Default)]
+pub struct BlockAssembler {
+    config: BlockAssemblerConfig,
+    pending: BTreeMap<u64, PartialBlock>,
+    finished: BTreeSet<u64>,
+    newest_slot: u64,
+}
+
+impl BlockAssembler {
+    pub fn new(config: BlockAssemblerConfig) -> Self {
+        Self {
+            config,
+            ..Default::default()
+        }
+    }
+
+    /// Slots still waiting for parts.
+    pub fn pending_slots(&self) -> impl Iterator<Item = u64> + '_ {
+        self.pending.keys().copied()
+    }
+
+    pub fn push(&mut self, update: SubscribeUpdate) -> Vec<BlockEvent> {
+        let mut events = Vec::new();
+        let Some(update) = update.update_oneof else {
+            return events;
+        };
+
+        let slot = match update {
+            UpdateOneof::Transaction(transaction) => {
+                if let Some(info) = transaction.transaction {
+                    if let Some(block) = self.partial(transaction.slot) {
+                        block.transactions.insert(info.index, info);
+                    }
+                }
+                transaction.slot
+            }
+            UpdateOneof::Account(account) => {
+                if let Some(info) = account.account {
+                    if let Some(block) = self.partial(account.slot) {
+                        let newer = block
+                            .accounts
+                            .get(&info.pubkey)
+                            .is_none_or(|current| current.write_version < info.write_version);
+                        if newer {
+                            block.accounts.insert(info.pubkey.clone(), info);
+                        }
+                    }
+                }
+                account.slot
+            }
+            UpdateOneof::Entry(entry) => {
+                let slot = entry.slot;
+                if let Some(block) = self.partial(slot) {
+                    block.entries.insert(entry.index, entry);
+                }
+                slot
+            }
+            UpdateOneof::BlockMeta(meta) => {
+                let slot = meta.slot;
+                if let Some(block) = self.partial(slot) {
+                    block.meta = Some(meta);
+                }
+                slot
+            }
+            UpdateOneof::Slot(slot) => {
+                if slot.status() == SlotStatus::SlotDead {
+                    match self.pending.remove(&slot.slot) {
+                        Some(block) => {
+                            self.finish(slot.slot, Some(IncompleteReason::Dead), block, &mut events)
+                        }
+                        // Nothing to report yet, but parts arriving later are ignored.
+                        None => {
+                            self.finished.insert(slot.slot);
+                        }
+                    }
+                }
+                slot.slot
+            }
+            UpdateOneof::TransactionStatus(_)
+            | UpdateOneof::Block(_)
+            | UpdateOneof::Ping(_)
+            | UpdateOneof::Pong(_) => return events,
+        };
+
+        if self
+            .pending
+            .get(&slot)
+            .is_some_and(|block| self.is_complete(block))
+        {
+            let block = self.pending.remove(&slot).expect("checked above");
+            self.finish(slot, None, block, &mut events);
+        }
+
+        self.newest_slot = self.newest_slot.max(slot);
+        self.expire(&mut events);
+        events
+    }
+
+    /// Reports every pending block as incomplete, e.g. when the stream ends.
+    pub fn flush(&mut self) -> Vec<BlockEvent> {
+        let mut events = Vec::new();
+        for (slot, block) in std::mem::take(&mut self.pending) {
+            self.finish(slot, Some(IncompleteReason::Flushed), block, &mut events);
+        }
+        events
+    }
+
+    /// The partial block of `slot`, `None` once the slot was reported.
+    fn partial(&mut self, slot: u64) -> Option<&mut PartialBlock> {
+        if self.finished.contains(&slot)
+            || slot.saturating_add(self.config.max_pending_slots) < self.newest_slot
+        {
+            return None;
+        }
+        Some(self.pending.entry(slot).or_default())
+    }
+
+    fn is_complete(&self, block: &PartialBlock) -> bool {
+        let Some(meta) = block.meta.as_ref() else {
+            return false;
+        };
+
+        (!self.config.require_transactions
+            || block.transactions.len() as u64 >= meta.executed_transaction_count)
+            && (!self.config.require_entries || block.entries.len() as u64 >= meta.entries_count)
+    }
+
+    fn finish(
+        &mut self,
+        slot: u64,
+        reason: Option<IncompleteReason>,
+        block: PartialBlock,
+        events: &mut Vec<BlockEvent>,
+    ) {
+        self.finished.insert(slot);
+        let meta_received = block.meta.is_some();
+        let block = block.into_block(slot);
+
+        events.push(match reason {
+            None => BlockEvent::Complete(block),
+            Some(reason) => BlockEvent::Incomplete(IncompleteBlock {
+                reason,
+                meta_received,
+                block,
+            }),
+        });
+    }
+
+    fn expire(&mut self, events: &mut Vec<BlockEvent>) {
+        let oldest_kept = self
+            .newest_slot
+            .saturating_sub(self.config.max_pending_slots);
+
+        let kept = self.pending.split_off(&oldest_kept);
+        for (slot, block) in std::mem::replace(&mut self.pending, kept) {
+            self.finish(slot, Some(IncompleteReason::Expired), block, events);
+        }
+        self.finished = self.finished.split_off(&oldest_kept);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::geyser::{
+        SubscribeUpdateAccount, SubscribeUpdateSlot, SubscribeUpdateTransaction,
+    };
+
+    fn update(update: UpdateOneof) -> SubscribeUpdate {
+        SubscribeUpdate {
+            update_oneof: Some(update),
+            ..Default::default()
+        }
+    }
+
+    fn meta(slot: u64, transactions: u64, entries: u64) -> SubscribeUpdate {
+        update(UpdateOneof::BlockMeta(SubscribeUpdateBlockMeta {
+            slot,
+            blockhash: format!("block-{slot}"),
+            executed_transaction_count: transactions,
+            entries_count: entries,
+            ..Default::default()
+        }))
+    }
+
+    fn transaction(slot: u64, index: u64) -> SubscribeUpdate {
+        update(UpdateOneof::Transaction(SubscribeUpdateTransaction {
+            transaction: Some(SubscribeUpdateTransactionInfo {
+                index,
+                ..Default::default()
+            }),
+            slot,
+        }))
+    }
+
+    fn entry(slot: u64, index: u64) -> SubscribeUpdate {
+        update(UpdateOneof::Entry(SubscribeUpdateEntry {
+            slot,
+            index,
+            ..Default::default()
+        }))
+    }
+
+    fn account(slot: u64, write_version: u64) -> SubscribeUpdate {
+        update(UpdateOneof::Account(SubscribeUpdateAccount {
+            account: Some(SubscribeUpdateAccountInfo {
+                pubkey: vec![1; 32],
+                write_version,
+                ..Default::default()
+            }),
+            slot,
+            is_startup: false,
+        }))
+    }
+
+    fn dead(slot: u64) -> SubscribeUpdate {
+        update(UpdateOneof::Slot(SubscribeUpdateSlot {
+            slot,
+            status: SlotStatus::SlotDead as i32,
+            ..Default::default()
+        }))
+    }
+
+    fn incomplete(events: &[BlockEvent]) -> Vec<(u64, IncompleteReason)> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                BlockEvent::Incomplete(block) => Some((block.block.slot, block.reason)),
+                BlockEvent::Complete(_) => None,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn assembles_block() {
+        let mut assembler = BlockAssembler::new(BlockAssemblerConfig::default());
+        assert!(assembler.push(transaction(10, 1)).is_empty());
+        assert!(assembler.push(account(10, 2)).is_empty());
+        assert!(assembler.push(account(10, 1)).is_empty());
+        assert!(assembler.push(entry(10, 0)).is_empty());
+        assert!(assembler.push(meta(10, 2, 1)).is_empty());
+
+        let events = assembler.push(transaction(10, 0));
+        let [BlockEvent::Complete(block)] = events.as_slice() else {
+            panic!("expected a complete block, got {events:?}");
+        };
+        assert_eq!(block.blockhash, "block-10");
+        assert_eq!(
+            block
+                .transactions
+                .iter()
+                .map(|transaction| transaction.index)
+                .collect::<Vec<_>>(),
+            [0, 1]
+        );
+        assert_eq!(block.updated_account_count, 1);
+        assert_eq!(block.accounts[0].write_version, 2);
+        assert_eq!(assembler.pending_slots().count(), 0);
+        // Parts of a reported block are ignored.
+        assert!(assembler.push(transaction(10, 2)).is_empty());
+        assert_eq!(assembler.pending_slots().count(), 0);
+    }
+
+    #[test]
+    fn skips_parts_that_are_not_required() {
+        let mut assembler = BlockAssembler::new(BlockAssemblerConfig {
+            require_transactions: false,
+            require_entries: false,
+            ..Default::default()
+        });
+
+        assert!(matches!(
+            assembler.push(meta(10, 5, 5)).as_slice(),
+            [BlockEvent::Complete(_)]
+        ));
+    }
+
+    #[test]
+    fn reports_dead_slot() {
+        let mut assembler = BlockAssembler::new(BlockAssemblerConfig::default());
+        assembler.push(transaction(10, 0));
+
+        let events = assembler.push(dead(10));
+        assert_eq!(incomplete(&events), [(10, IncompleteReason::Dead)]);
+        let BlockEvent::Incomplete(block) = &events[0] else {
+            unreachable!();
+        };
+        assert!(!block.meta_received);
+        assert_eq!(block.block.transactions.len(), 1);
+    }
+
+    #[test]
+    fn ignores_parts_after_dead_slot() {
+        let mut assembler = BlockAssembler::new(BlockAssemblerConfig::default());
+
+        assert!(assembler.push(dead(10)).is_empty());
+        assert!(assembler.push(transaction(10, 0)).is_empty());
+        assert!(assembler.push(meta(10, 1, 0)).is_empty());
+        assert_eq!(assembler.pending_slots().count(), 0);
+    }
+
+    #[test]
+    fn expires_old_slots() {
+        let mut assembler = BlockAssembler::new(BlockAssemblerConfig {
+            max_pending_slots: 2,
+            ..Default::default()
+        });
+        assembler.push(meta(10, 1, 0));
+        assembler.push(meta(11, 1, 0));
+
+        assert!(assembler.push(transaction(12, 0)).is_empty());
+        assert_eq!(
+            incomplete(&assembler.push(transaction(13, 0))),
+            [(10, IncompleteReason::Expired)]
+        );
+        // Too old to start a new block.
+        assert!(assembler.push(transaction(10, 0)).is_empty());
+        assert_eq!(assembler.pending_slots().collect::<Vec<_>>(), [11, 12, 13]);
+
+        assert_eq!(
+            incomplete(&assembler.flush()),
+            [
+                (11, IncompleteReason::Flushed),
+                (12, IncompleteReason::Flushed),
+                (13, IncompleteReason::Flushed),
+            ]
+        );
+    }
+
+    #[test]
+    fn handles_slots_near_max() {
+        let mut assembler = BlockAssembler::new(BlockAssemblerConfig::default());
+
+        assert!(assembler.push(transaction(u64::MAX, 0)).is_empty());
+        assert_eq!(assembler.pending_slots().collect::<Vec<_>>(), [u64::MAX]);
+    }
+}
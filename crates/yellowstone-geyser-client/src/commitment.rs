@@ -0,0 +1,414 @@
+use futures::Stream;
+use std::{
+    collections::{BTreeMap, VecDeque},
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+
+use crate::{
+    proto::geyser::{subscribe_update::UpdateOneof, SlotStatus, SubscribeUpdate},
+    slot_tracker::{ForkState, SlotEvent, SlotInfo, SlotTracker},
+};
+
+const DEFAULT_MAX_PENDING_SLOTS: u64 = 256;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RollbackReason {
+    Dead,
+    /// The slot ended up on a fork that was not finalized.
+    Abandoned,
+    /// The slot fell out of the tracker's retention, or more than
+    /// `max_pending_slots` behind the newest slot, before reaching the threshold.
+    Expired,
+}
+
+#[allow(clippy::large_enum_variant)]
+#[derive(Debug, Clone)]
+pub enum CommitmentEvent {
+    /// An update released after its slot reached the threshold, or one that is never held back.
+    Update(SubscribeUpdate),
+    /// Buffered updates of `slot` that will never be released.
+    Rollback {
+        slot: u64,
+        reason: RollbackReason,
+        updates: Vec<SubscribeUpdate>,
+    },
+}
+
+/// Holds back updates until their slot reaches a chosen `SlotStatus`.
+///
+/// Updates carrying a slot are buffered per slot and released in slot order
+/// once the slot reaches `release_at`, or rolled back when the slot dies or
+/// is abandoned. Slot, ping and pong updates are passed through immediately.
+/// `SlotFirstShredReceived` and `SlotCreatedBank` release as soon as the slot
+/// is known, `SlotDead` never releases.
+///
+/// The subscription needs a slots filter for slots to ever be released. Slots
+/// more than `max_pending_slots` behind the newest slot seen are rolled back
+/// as expired, which bounds the buffer when slot updates are missing.
+#[derive(Debug, Clone)]
+pub struct CommitmentBuffer {
+    release_at: SlotStatus,
+    tracker: SlotTracker,
+    pending: BTreeMap<u64, Vec<SubscribeUpdate>>,
+    max_pending_slots: u64,
+    newest_slot: u64,
+}
+
+impl CommitmentBuffer {
+    pub fn new(release_at: SlotStatus) -> Self {
+        Self::with_tracker(release_at, SlotTracker::default())
+    }
+
+    pub fn with_tracker(release_at: SlotStatus, tracker: SlotTracker) -> Self {
+        Self {
+            release_at,
+            tracker,
+            pending: BTreeMap::new(),
+            max_pending_slots: DEFAULT_MAX_PENDING_SLOTS,
+            newest_slot: 0,
+        }
+    }
+
+    /// Rolls back slots this many slots behind the newest one, 256 by default.
+    pub fn max_pending_slots(mut self, max_pending_slots: u64) -> Self {
+        self.max_pending_slots = max_pending_slots;
+        self
+    }
+
+    pub fn tracker(&self) -> &SlotTracker {
+        &self.tracker
+    }
+
+    /// Number of updates waiting for their slot.
+    pub fn pending_updates(&self) -> usize {
+        self.pending.values().map(Vec::len).sum()
+    }
+
+    pub fn push(&mut self, update: SubscribeUpdate) -> Vec<CommitmentEvent> {
+        let mut events = Vec::new();
+
+        let slot = match update.update_oneof.as_ref() {
+            Some(UpdateOneof::Slot(slot)) => {
+                let slot_events = self.tracker.update(slot);
+                self.newest_slot = self.newest_slot.max(slot.slot);
+                events.push(CommitmentEvent::Update(update));
+                self.rollback(slot_events, &mut events);
+                self.release(&mut events);
+                self.expire(&mut events);
+                return events;
+            }
+            _ => update.slot(),
+        };
+        let Some(slot) = slot else {
+            events.push(CommitmentEvent::Update(update));
+            return events;
+        };
+        self.newest_slot = self.newest_slot.max(slot);
+
+        match self.tracker.get(slot) {
+            Some(info) if matches!(info.fork, ForkState::Dead | ForkState::Abandoned) => {
+                let reason = match info.fork {
+                    ForkState::Dead => RollbackReason::Dead,
+                    _ => RollbackReason::Abandoned,
+                };
+                events.push(CommitmentEvent::Rollback {
+                    slot,
+                    reason,
+                    updates: vec![update],
+                });
+            }
+            // Keep the slot order, an earlier slot may still be waiting.
+            Some(info)
+                if is_released(self.release_at, info)
+                    && self.pending.range(..slot).next().is_none() =>
+            {
+                events.push(CommitmentEvent::Update(update));
+            }
+            _ => self.pending.entry(slot).or_default().push(update),
+        }
+        self.expire(&mut events);
+
+        events
+    }
+
+    fn rollback(&mut self, slot_events: Vec<SlotEvent>, events: &mut Vec<CommitmentEvent>) {
+        for event in slot_events {
+            let (slot, reason) = match event {
+                SlotEvent::Dead { slot, .. } => (slot, RollbackReason::Dead),
+                // Skipped slots that were tracked are on an abandoned fork.
+                SlotEvent::Abandoned { slot } | SlotEvent::Skipped { slot } => {
+                    (slot, RollbackReason::Abandoned)
+                }
+                SlotEvent::Rooted { .. } => continue,
+            };
+            if let Some(updates) = self.pending.remove(&slot) {
+                events.push(CommitmentEvent::Rollback {
+                    slot,
+                    reason,
+                    updates,
+                });
+            }
+        }
+    }
+
+    /// Releases pending slots in order, stopping at the first one still waiting.
+    fn release(&mut self, events: &mut Vec<CommitmentEvent>) {
+        while let Some(entry) = self.pending.first_entry() {
+            let released = self
+                .tracker
+                .get(*entry.key())
+                .is_some_and(|info| is_released(self.release_at, info));
+            if !released {
+                break;
+            }
+            events.extend(entry.remove().into_iter().map(CommitmentEvent::Update));
+        }
+    }
+
+    /// Rolls back slots the tracker forgot about, they can no longer be
+    /// decided, and slots too far behind the newest one.
+    fn expire(&mut self, events: &mut Vec<CommitmentEvent>) {
+        while let Some(entry) = self.pending.first_entry() {
+            let slot = *entry.key();
+            let expired = self
+                .tracker
+                .oldest_slot()
+                .is_some_and(|oldest| slot < oldest)
+                || slot.saturating_add(self.max_pending_slots) < self.newest_slot;
+            if !expired {
+                break;
+            }
+            events.push(CommitmentEvent::Rollback {
+                slot,
+                reason: RollbackReason::Expired,
+                updates: entry.remove(),
+            });
+        }
+        // A waiting slot that expired may have blocked released ones behind it.
+        self.release(events);
+    }
+}
+
+fn is_released(release_at: SlotStatus, info: &SlotInfo) -> bool {
+    let commitment = info.commitment;
+    match release_at {
+        SlotStatus::SlotFirstShredReceived | SlotStatus::SlotCreatedBank => true,
+        SlotStatus::SlotCompleted => info.completed || commitment.is_some(),
+        SlotStatus::SlotDead => false,
+        status => commitment.is_some_and(|commitment| commitment >= status),
+    }
+}
+
+/// Stream adapter applying a [`CommitmentBuffer`] to a `Subscribe` stream.
+pub struct CommitmentStream<S> {
+    stream: S,
+    buffer: CommitmentBuffer,
+    ready: VecDeque<CommitmentEvent>,
+    #[cfg(feature = "metrics")]
+    metrics: Option<crate::metrics::GeyserMetrics>,
+}
+
+impl<S> CommitmentStream<S> {
+    pub fn new(stream: S, release_at: SlotStatus) -> Self {
+        Self::with_buffer(stream, CommitmentBuffer::new(release_at))
+    }
+
+    pub fn with_buffer(stream: S, buffer: CommitmentBuffer) -> Self {
+        Self {
+            stream,
+            buffer,
+            ready: VecDeque::new(),
+            #[cfg(feature = "metrics")]
+            metrics: None,
+        }
+    }
+
+    /// Reports the updates held back by the buffer as the queue depth of `metrics`.
+    #[cfg(feature = "metrics")]
+    pub fn with_metrics(mut self, metrics: crate::metrics::GeyserMetrics) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    pub fn buffer(&self) -> &CommitmentBuffer {
+        &self.buffer
+    }
+}
+
+impl<S, E> Stream for CommitmentStream<S>
+where
+    S: Stream<Item = Result<SubscribeUpdate, E>> + Unpin,
+{
+    type Item = Result<CommitmentEvent, E>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = &mut *self;
+
+        loop {
+            if let Some(event) = this.ready.pop_front() {
+                return Poll::Ready(Some(Ok(event)));
+            }
+
+            match ready!(Pin::new(&mut this.stream).poll_next(cx)) {
+                Some(Ok(update)) => this.ready.extend(this.buffer.push(update)),
+                Some(Err(error)) => return Poll::Ready(Some(Err(error))),
+                None => return Poll::Ready(None),
+            }
+            #[cfg(feature = "metrics")]
+            if let Some(metrics) = this.metrics.as_ref() {
+                metrics.observe_queue_depth(this.buffer.pending_updates());
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::geyser::{SubscribeUpdateEntry, SubscribeUpdatePing, SubscribeUpdateSlot};
+
+    fn slot(slot: u64, parent: Option<u64>, status: SlotStatus) -> SubscribeUpdate {
+        SubscribeUpdate {
+            update_oneof: Some(UpdateOneof::Slot(SubscribeUpdateSlot {
+                slot,
+                parent,
+                status: status as i32,
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+    }
+
+    fn entry(slot: u64) -> SubscribeUpdate {
+        SubscribeUpdate {
+            update_oneof: Some(UpdateOneof::Entry(SubscribeUpdateEntry {
+                slot,
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+    }
+
+    /// Slots of released entry updates and rolled back slots, in order.
+    fn summary(events: Vec<CommitmentEvent>) -> Vec<(u64, Option<RollbackReason>)> {
+        events
+            .into_iter()
+            .filter_map(|event| match event {
+                CommitmentEvent::Update(update) => match update.update_oneof {
+                    Some(UpdateOneof::Entry(entry)) => Some((entry.slot, None)),
+                    _ => None,
+                },
+                CommitmentEvent::Rollback { slot, reason, .. } => Some((slot, Some(reason))),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn releases_in_slot_order() {
+        let mut buffer = CommitmentBuffer::new(SlotStatus::SlotConfirmed);
+        buffer.push(slot(1, Some(0), SlotStatus::SlotProcessed));
+        buffer.push(slot(2, Some(1), SlotStatus::SlotProcessed));
+
+        assert!(summary(buffer.push(entry(2))).is_empty());
+        assert!(summary(buffer.push(entry(1))).is_empty());
+        assert_eq!(buffer.pending_updates(), 2);
+        // Confirming 2 confirms its parent 1 as well.
+        assert_eq!(
+            summary(buffer.push(slot(2, None, SlotStatus::SlotConfirmed))),
+            [(1, None), (2, None)]
+        );
+        // Released slots pass new updates straight through.
+        assert_eq!(summary(buffer.push(entry(2))), [(2, None)]);
+        assert_eq!(buffer.pending_updates(), 0);
+    }
+
+    #[test]
+    fn passes_through_updates_without_slot() {
+        let mut buffer = CommitmentBuffer::new(SlotStatus::SlotFinalized);
+        let ping = SubscribeUpdate {
+            update_oneof: Some(UpdateOneof::Ping(SubscribeUpdatePing {})),
+            ..Default::default()
+        };
+
+        assert!(matches!(
+            buffer.push(ping).as_slice(),
+            [CommitmentEvent::Update(_)]
+        ));
+    }
+
+    #[test]
+    fn rolls_back_dead_and_abandoned_slots() {
+        let mut buffer = CommitmentBuffer::new(SlotStatus::SlotFinalized);
+        buffer.push(slot(1, Some(0), SlotStatus::SlotProcessed));
+        buffer.push(slot(2, Some(1), SlotStatus::SlotProcessed));
+        buffer.push(slot(3, Some(1), SlotStatus::SlotProcessed));
+        buffer.push(entry(2));
+        buffer.push(entry(3));
+
+        assert_eq!(
+            summary(buffer.push(slot(2, None, SlotStatus::SlotDead))),
+            [(2, Some(RollbackReason::Dead))]
+        );
+        // Late updates of a dead slot are rolled back right away.
+        assert_eq!(
+            summary(buffer.push(entry(2))),
+            [(2, Some(RollbackReason::Dead))]
+        );
+        buffer.push(slot(4, Some(1), SlotStatus::SlotProcessed));
+        assert_eq!(
+            summary(buffer.push(slot(4, None, SlotStatus::SlotFinalized))),
+            [(3, Some(RollbackReason::Abandoned))]
+        );
+    }
+
+    #[test]
+    fn expires_slots_without_slot_updates() {
+        let mut buffer = CommitmentBuffer::new(SlotStatus::SlotConfirmed).max_pending_slots(2);
+        buffer.push(entry(10));
+        buffer.push(entry(11));
+        buffer.push(entry(12));
+        assert_eq!(buffer.pending_updates(), 3);
+
+        assert_eq!(
+            summary(buffer.push(entry(13))),
+            [(10, Some(RollbackReason::Expired))]
+        );
+        assert_eq!(buffer.pending_updates(), 3);
+    }
+
+    #[cfg(feature = "metrics")]
+    #[test]
+    fn reports_queue_depth() {
+        use futures::{FutureExt, StreamExt};
+
+        let registry = crate::metrics::prometheus::Registry::new();
+        let metrics = crate::metrics::GeyserMetrics::register(&registry).unwrap();
+        let depth = || {
+            crate::metrics::encode(&registry)
+                .lines()
+                .find_map(|line| line.strip_prefix("geyser_queue_depth ").map(str::to_owned))
+        };
+
+        let (sender, receiver) = futures::channel::mpsc::unbounded::<Result<_, ()>>();
+        let mut stream =
+            CommitmentStream::new(receiver, SlotStatus::SlotConfirmed).with_metrics(metrics);
+        for update in [
+            slot(1, Some(0), SlotStatus::SlotProcessed),
+            entry(1),
+            entry(1),
+        ] {
+            sender.unbounded_send(Ok(update)).unwrap();
+        }
+
+        assert!(stream.next().now_or_never().is_some());
+        assert!(stream.next().now_or_never().is_none());
+        assert_eq!(depth().as_deref(), Some("2"));
+
+        sender
+            .unbounded_send(Ok(slot(1, Some(0), SlotStatus::SlotConfirmed)))
+            .unwrap();
+        assert!(stream.next().now_or_never().is_some());
+        assert_eq!(depth().as_deref(), Some("0"));
+    }
+}
@@ -0,0 +1,669 @@
+//! Conversions between the `solana_storage` protos and Solana SDK types.
+
+use solana_account_decoder_client_types::token::UiTokenAmount;
+use solana_hash::Hash;
+use solana_message::{
+    compiled_instruction::CompiledInstruction,
+    legacy,
+    v0::{self, LoadedAddresses, MessageAddressTableLookup},
+    MessageHeader, VersionedMessage,
+};
+use solana_pubkey::Pubkey;
+use solana_reward_info::RewardType;
+use solana_signature::Signature;
+use solana_transaction::versioned::VersionedTransaction;
+use solana_transaction_context::TransactionReturnData;
+use solana_transaction_error::TransactionError;
+use solana_transaction_status_client_types::{
+    InnerInstruction, InnerInstructions, Reward, TransactionStatusMeta, TransactionTokenBalance,
+};
+
+use crate::proto::solana_storage;
+
+#[derive(Debug, thiserror::Error)]
+pub enum ConversionError {
+    #[error("invalid pubkey length {0}")]
+    InvalidPubkey(usize),
+    #[error("invalid signature length {0}")]
+    InvalidSignature(usize),
+    #[error("invalid blockhash length {0}")]
+    InvalidBlockhash(usize),
+    #[error("{0} does not fit into a u8")]
+    IndexOutOfRange(&'static str),
+    #[error("missing {0}")]
+    MissingField(&'static str),
+    #[error("invalid commission `{0}`")]
+    InvalidCommission(String),
+    #[error("failed to (de)serialize transaction error: {0}")]
+    TransactionError(#[from] bincode::Error),
+}
+
+fn pubkey(bytes: &[u8]) -> Result<Pubkey, ConversionError> {
+    <[u8; 32]>::try_from(bytes)
+        .map(Pubkey::new_from_array)
+        .map_err(|_| ConversionError::InvalidPubkey(bytes.len()))
+}
+
+pub(crate) fn pubkeys(keys: Vec<Vec<u8>>) -> Result<Vec<Pubkey>, ConversionError> {
+    keys.iter().map(|key| pubkey(key)).collect()
+}
+
+fn index(value: u32, field: &'static str) -> Result<u8, ConversionError> {
+    u8::try_from(value).map_err(|_| ConversionError::IndexOutOfRange(field))
+}
+
+impl TryFrom<solana_storage::Transaction> for VersionedTransaction {
+    type Error = ConversionError;
+
+    fn try_from(transaction: solana_storage::Transaction) -> Result<Self, Self::Error> {
+        let signatures = transaction
+            .signatures
+            .iter()
+            .map(|signature| {
+                <[u8; 64]>::try_from(signature.as_slice())
+                    .map(Signature::from)
+                    .map_err(|_| ConversionError::InvalidSignature(signature.len()))
+            })
+            .collect::<Result<_, _>>()?;
+        let message = transaction
+            .message
+            .ok_or(ConversionError::MissingField("message"))?
+            .try_into()?;
+
+        Ok(Self {
+            signatures,
+            message,
+        })
+    }
+}
+
+impl TryFrom<solana_storage::Message> for VersionedMessage {
+    type Error = ConversionError;
+
+    fn try_from(message: solana_storage::Message) -> Result<Self, Self::Error> {
+        let header = message
+            .header
+            .ok_or(ConversionError::MissingField("message header"))?;
+        let header = MessageHeader {
+            num_required_signatures: index(
+                header.num_required_signatures,
+                "num_required_signatures",
+            )?,
+            num_readonly_signed_accounts: index(
+                header.num_readonly_signed_accounts,
+                "num_readonly_signed_accounts",
+            )?,
+            num_readonly_unsigned_accounts: index(
+                header.num_readonly_unsigned_accounts,
+                "num_readonly_unsigned_accounts",
+            )?,
+        };
+        let account_keys = pubkeys(message.account_keys)?;
+        let recent_blockhash = <[u8; 32]>::try_from(message.recent_blockhash.as_slice())
+            .map(Hash::new_from_array)
+            .map_err(|_| ConversionError::InvalidBlockhash(message.recent_blockhash.len()))?;
+        let instructions = message
+            .instructions
+            .into_iter()
+            .map(CompiledInstruction::try_from)
+            .collect::<Result<_, _>>()?;
+
+        if !message.versioned {
+            return Ok(Self::Legacy(legacy::Message {
+                header,
+                account_keys,
+                recent_blockhash,
+                instructions,
+            }));
+        }
+
+        let address_table_lookups = message
+            .address_table_lookups
+            .into_iter()
+            .map(|lookup| {
+                Ok(MessageAddressTableLookup {
+                    account_key: pubkey(&lookup.account_key)?,
+                    writable_indexes: lookup.writable_indexes,
+                    readonly_indexes: lookup.readonly_indexes,
+                })
+            })
+            .collect::<Result<_, ConversionError>>()?;
+
+        Ok(Self::V0(v0::Message {
+            header,
+            account_keys,
+            recent_blockhash,
+            instructions,
+            address_table_lookups,
+        }))
+    }
+}
+
+impl TryFrom<solana_storage::CompiledInstruction> for CompiledInstruction {
+    type Error = ConversionError;
+
+    fn try_from(instruction: solana_storage::CompiledInstruction) -> Result<Self, Self::Error> {
+        Ok(Self {
+            program_id_index: index(instruction.program_id_index, "program_id_index")?,
+            accounts: instruction.accounts,
+            data: instruction.data,
+        })
+    }
+}
+
+impl TryFrom<solana_storage::TransactionError> for TransactionError {
+    type Error = ConversionError;
+
+    /// Decodes the bincode serialized error.
+    fn try_from(error: solana_storage::TransactionError) -> Result<Self, Self::Error> {
+        Ok(bincode::deserialize(&error.err)?)
+    }
+}
+
+impl TryFrom<solana_storage::TransactionStatusMeta> for TransactionStatusMeta {
+    type Error = ConversionError;
+
+    fn try_from(meta: solana_storage::TransactionStatusMeta) -> Result<Self, Self::Error> {
+        let status = match meta.err {
+            Some(error) => Err(error.try_into()?),
+            None => Ok(()),
+        };
+        let inner_instructions = match meta.inner_instructions_none {
+            true => None,
+            false => Some(
+                meta.inner_instructions
+                    .into_iter()
+                    .map(inner_instructions)
+                    .collect::<Result<_, _>>()?,
+            ),
+        };
+        let log_messages = (!meta.log_messages_none).then_some(meta.log_messages);
+        let return_data = match meta.return_data_none {
+            true => None,
+            false => meta
+                .return_data
+                .map(|return_data| {
+                    Ok::<_, ConversionError>(TransactionReturnData {
+                        program_id: pubkey(&return_data.program_id)?,
+                        data: return_data.data,
+                    })
+                })
+                .transpose()?,
+        };
+
+        Ok(Self {
+            status,
+            fee: meta.fee,
+            pre_balances: meta.pre_balances,
+            post_balances: meta.post_balances,
+            inner_instructions,
+            log_messages,
+            pre_token_balances: token_balances(meta.pre_token_balances)?,
+            post_token_balances: token_balances(meta.post_token_balances)?,
+            rewards: non_empty(meta.rewards)
+                .map(|rewards| rewards.into_iter().map(Reward::try_from).collect())
+                .transpose()?,
+            loaded_addresses: LoadedAddresses {
+                writable: pubkeys(meta.loaded_writable_addresses)?,
+                readonly: pubkeys(meta.loaded_readonly_addresses)?,
+            },
+            return_data,
+            compute_units_consumed: meta.compute_units_consumed,
+            cost_units: meta.cost_units,
+        })
+    }
+}
+
+pub(crate) fn inner_instructions(
+    inner: solana_storage::InnerInstructions,
+) -> Result<InnerInstructions, ConversionError> {
+    Ok(InnerInstructions {
+        index: index(inner.index, "inner instructions index")?,
+        instructions: inner
+            .instructions
+            .into_iter()
+            .map(|instruction| {
+                Ok(InnerInstruction {
+                    instruction: CompiledInstruction {
+                        program_id_index: index(instruction.program_id_index, "program_id_index")?,
+                        accounts: instruction.accounts,
+                        data: instruction.data,
+                    },
+                    stack_height: instruction.stack_height,
+                })
+            })
+            .collect::<Result<_, ConversionError>>()?,
+    })
+}
+
+/// The proto has no `_none` flag for token balances and rewards, so an empty
+/// list stands for `None` in both directions.
+fn non_empty<T>(items: Vec<T>) -> Option<Vec<T>> {
+    (!items.is_empty()).then_some(items)
+}
+
+fn token_balances(
+    balances: Vec<solana_storage::TokenBalance>,
+) -> Result<Option<Vec<TransactionTokenBalance>>, ConversionError> {
+    let Some(balances) = non_empty(balances) else {
+        return Ok(None);
+    };
+
+    balances
+        .into_iter()
+        .map(|balance| {
+            let amount = balance.ui_token_amount.unwrap_or_default();
+            Ok(TransactionTokenBalance {
+                account_index: index(balance.account_index, "token balance account_index")?,
+                mint: balance.mint,
+                ui_token_amount: UiTokenAmount {
+                    ui_amount: (amount.ui_amount != 0.0).then_some(amount.ui_amount),
+                    decimals: index(amount.decimals, "token decimals")?,
+                    amount: amount.amount,
+                    ui_amount_string: amount.ui_amount_string,
+                },
+                owner: balance.owner,
+                program_id: balance.program_id,
+            })
+        })
+        .collect::<Result<_, _>>()
+        .map(Some)
+}
+
+impl TryFrom<solana_storage::Reward> for Reward {
+    type Error = ConversionError;
+
+    fn try_from(reward: solana_storage::Reward) -> Result<Self, Self::Error> {
+        let reward_type = match reward.reward_type() {
+            solana_storage::RewardType::Unspecified => None,
+            solana_storage::RewardType::Fee => Some(RewardType::Fee),
+            solana_storage::RewardType::Rent => Some(RewardType::Rent),
+            solana_storage::RewardType::Staking => Some(RewardType::Staking),
+            solana_storage::RewardType::Voting => Some(RewardType::Voting),
+        };
+        let commission = match reward.commission.as_str() {
+            "" => None,
+            commission => Some(
+                commission
+                    .parse()
+                    .map_err(|_| ConversionError::InvalidCommission(reward.commission.clone()))?,
+            ),
+        };
+
+        Ok(Self {
+            pubkey: reward.pubkey,
+            lamports: reward.lamports,
+            post_balance: reward.post_balance,
+            reward_type,
+            commission,
+        })
+    }
+}
+
+impl From<VersionedTransaction> for solana_storage::Transaction {
+    fn from(transaction: VersionedTransaction) -> Self {
+        Self {
+            signatures: transaction
+                .signatures
+                .into_iter()
+                .map(|signature| signature.as_ref().to_vec())
+                .collect(),
+            message: Some(transaction.message.into()),
+        }
+    }
+}
+
+impl From<VersionedMessage> for solana_storage::Message {
+    fn from(message: VersionedMessage) -> Self {
+        let (header, account_keys, recent_blockhash, instructions, lookups) = match message {
+            VersionedMessage::Legacy(message) => (
+                message.header,
+                message.account_keys,
+                message.recent_blockhash,
+                message.instructions,
+                None,
+            ),
+            VersionedMessage::V0(message) => (
+                message.header,
+                message.account_keys,
+                message.recent_blockhash,
+                message.instructions,
+                Some(message.address_table_lookups),
+            ),
+        };
+
+        Self {
+            header: Some(solana_storage::MessageHeader {
+                num_required_signatures: header.num_required_signatures.into(),
+                num_readonly_signed_accounts: header.num_readonly_signed_accounts.into(),
+                num_readonly_unsigned_accounts: header.num_readonly_unsigned_accounts.into(),
+            }),
+            account_keys: account_keys
+                .iter()
+                .map(|key| key.to_bytes().to_vec())
+                .collect(),
+            recent_blockhash: recent_blockhash.to_bytes().to_vec(),
+            instructions: instructions.into_iter().map(Into::into).collect(),
+            versioned: lookups.is_some(),
+            address_table_lookups: lookups
+                .unwrap_or_default()
+                .into_iter()
+                .map(|lookup| solana_storage::MessageAddressTableLookup {
+                    account_key: lookup.account_key.to_bytes().to_vec(),
+                    writable_indexes: lookup.writable_indexes,
+                    readonly_indexes: lookup.readonly_indexes,
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<CompiledInstruction> for solana_storage::CompiledInstruction {
+    fn from(instruction: CompiledInstruction) -> Self {
+        Self {
+            program_id_index: instruction.program_id_index.into(),
+            accounts: instruction.accounts,
+            data: instruction.data,
+        }
+    }
+}
+
+impl TryFrom<TransactionError> for solana_storage::TransactionError {
+    type Error = ConversionError;
+
+    fn try_from(error: TransactionError) -> Result<Self, Self::Error> {
+        Ok(Self {
+            err: bincode::serialize(&error)?,
+        })
+    }
+}
+
+impl TryFrom<TransactionStatusMeta> for solana_storage::TransactionStatusMeta {
+    type Error = ConversionError;
+
+    fn try_from(meta: TransactionStatusMeta) -> Result<Self, Self::Error> {
+        let err = match meta.status {
+            Ok(()) => None,
+            Err(error) => Some(error.try_into()?),
+        };
+
+        Ok(Self {
+            err,
+            fee: meta.fee,
+            pre_balances: meta.pre_balances,
+            post_balances: meta.post_balances,
+            inner_instructions_none: meta.inner_instructions.is_none(),
+            inner_instructions: meta
+                .inner_instructions
+                .unwrap_or_default()
+                .into_iter()
+                .map(|inner| solana_storage::InnerInstructions {
+                    index: inner.index.into(),
+                    instructions: inner
+                        .instructions
+                        .into_iter()
+                        .map(|inner| solana_storage::InnerInstruction {
+                            program_id_index: inner.instruction.program_id_index.into(),
+                            accounts: inner.instruction.accounts,
+                            data: inner.instruction.data,
+                            stack_height: inner.stack_height,
+                        })
+                        .collect(),
+                })
+                .collect(),
+            log_messages_none: meta.log_messages.is_none(),
+            log_messages: meta.log_messages.unwrap_or_default(),
+            pre_token_balances: proto_token_balances(meta.pre_token_balances.unwrap_or_default()),
+            post_token_balances: proto_token_balances(meta.post_token_balances.unwrap_or_default()),
+            rewards: meta
+                .rewards
+                .unwrap_or_default()
+                .into_iter()
+                .map(Into::into)
+                .collect(),
+            loaded_writable_addresses: meta
+                .loaded_addresses
+                .writable
+                .iter()
+                .map(|key| key.to_bytes().to_vec())
+                .collect(),
+            loaded_readonly_addresses: meta
+                .loaded_addresses
+                .readonly
+                .iter()
+                .map(|key| key.to_bytes().to_vec())
+                .collect(),
+            return_data_none: meta.return_data.is_none(),
+            return_data: meta
+                .return_data
+                .map(|return_data| solana_storage::ReturnData {
+                    program_id: return_data.program_id.to_bytes().to_vec(),
+                    data: return_data.data,
+                }),
+            compute_units_consumed: meta.compute_units_consumed,
+            cost_units: meta.cost_units,
+        })
+    }
+}
+
+fn proto_token_balances(
+    balances: Vec<TransactionTokenBalance>,
+) -> Vec<solana_storage::TokenBalance> {
+    balances
+        .into_iter()
+        .map(|balance| solana_storage::TokenBalance {
+            account_index: balance.account_index.into(),
+            mint: balance.mint,
+            ui_token_amount: Some(solana_storage::UiTokenAmount {
+                ui_amount: balance.ui_token_amount.ui_amount.unwrap_or_default(),
+                decimals: balance.ui_token_amount.decimals.into(),
+                amount: balance.ui_token_amount.amount,
+                ui_amount_string: balance.ui_token_amount.ui_amount_string,
+            }),
+            owner: balance.owner,
+            program_id: balance.program_id,
+        })
+        .collect()
+}
+
+impl From<Reward> for solana_storage::Reward {
+    fn from(reward: Reward) -> Self {
+        let reward_type = match reward.reward_type {
+            None => solana_storage::RewardType::Unspecified,
+            Some(RewardType::Fee) => solana_storage::RewardType::Fee,
+            Some(RewardType::Rent) => solana_storage::RewardType::Rent,
+            Some(RewardType::Staking) => solana_storage::RewardType::Staking,
+            Some(RewardType::Voting) => solana_storage::RewardType::Voting,
+        };
+
+        Self {
+            pubkey: reward.pubkey,
+            lamports: reward.lamports,
+            post_balance: reward.post_balance,
+            reward_type: reward_type as i32,
+            commission: reward
+                .commission
+                .map(|commission| commission.to_string())
+                .unwrap_or_default(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(byte: u8) -> Pubkey {
+        Pubkey::new_from_array([byte; 32])
+    }
+
+    fn legacy_transaction() -> VersionedTransaction {
+        VersionedTransaction {
+            signatures: vec![Signature::from([1; 64])],
+            message: VersionedMessage::Legacy(legacy::Message {
+                header: MessageHeader {
+                    num_required_signatures: 1,
+                    num_readonly_signed_accounts: 0,
+                    num_readonly_unsigned_accounts: 1,
+                },
+                account_keys: vec![key(1), key(2), key(3)],
+                recent_blockhash: Hash::new_from_array([4; 32]),
+                instructions: vec![CompiledInstruction {
+                    program_id_index: 2,
+                    accounts: vec![0, 1],
+                    data: vec![2, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0, 0],
+                }],
+            }),
+        }
+    }
+
+    fn full_meta() -> TransactionStatusMeta {
+        TransactionStatusMeta {
+            status: Err(TransactionError::InsufficientFundsForFee),
+            fee: 5000,
+            pre_balances: vec![10, 20],
+            post_balances: vec![5, 25],
+            inner_instructions: Some(vec![InnerInstructions {
+                index: 0,
+                instructions: vec![InnerInstruction {
+                    instruction: CompiledInstruction {
+                        program_id_index: 2,
+                        accounts: vec![1],
+                        data: vec![3],
+                    },
+                    stack_height: Some(2),
+                }],
+            }]),
+            log_messages: Some(vec!["Program log: hello".to_string()]),
+            pre_token_balances: Some(vec![TransactionTokenBalance {
+                account_index: 1,
+                mint: key(5).to_string(),
+                ui_token_amount: UiTokenAmount {
+                    ui_amount: Some(1.5),
+                    decimals: 6,
+                    amount: "1500000".to_string(),
+                    ui_amount_string: "1.5".to_string(),
+                },
+                owner: key(1).to_string(),
+                program_id: key(6).to_string(),
+            }]),
+            post_token_balances: Some(vec![]),
+            rewards: Some(vec![Reward {
+                pubkey: key(7).to_string(),
+                lamports: -10,
+                post_balance: 90,
+                reward_type: Some(RewardType::Rent),
+                commission: Some(5),
+            }]),
+            loaded_addresses: LoadedAddresses {
+                writable: vec![key(8)],
+                readonly: vec![key(9)],
+            },
+            return_data: Some(TransactionReturnData {
+                program_id: key(2),
+                data: vec![1, 2, 3],
+            }),
+            compute_units_consumed: Some(1200),
+            cost_units: Some(1500),
+        }
+    }
+
+    fn round_trip_meta(meta: TransactionStatusMeta) -> TransactionStatusMeta {
+        let proto = solana_storage::TransactionStatusMeta::try_from(meta).unwrap();
+        TransactionStatusMeta::try_from(proto).unwrap()
+    }
+
+    #[test]
+    fn round_trips_legacy_transaction() {
+        let transaction = legacy_transaction();
+        let proto = solana_storage::Transaction::from(transaction.clone());
+
+        assert!(!proto.message.as_ref().unwrap().versioned);
+        assert_eq!(VersionedTransaction::try_from(proto).unwrap(), transaction);
+    }
+
+    #[test]
+    fn round_trips_v0_transaction() {
+        let VersionedMessage::Legacy(message) = legacy_transaction().message else {
+            unreachable!();
+        };
+        let transaction = VersionedTransaction {
+            signatures: vec![Signature::from([1; 64])],
+            message: VersionedMessage::V0(v0::Message {
+                header: message.header,
+                account_keys: message.account_keys,
+                recent_blockhash: message.recent_blockhash,
+                instructions: message.instructions,
+                address_table_lookups: vec![MessageAddressTableLookup {
+                    account_key: key(10),
+                    writable_indexes: vec![0, 3],
+                    readonly_indexes: vec![1],
+                }],
+            }),
+        };
+        let proto = solana_storage::Transaction::from(transaction.clone());
+
+        assert!(proto.message.as_ref().unwrap().versioned);
+        assert_eq!(VersionedTransaction::try_from(proto).unwrap(), transaction);
+    }
+
+    #[test]
+    fn round_trips_meta() {
+        let meta = full_meta();
+        let round_tripped = round_trip_meta(meta.clone());
+
+        // An empty list comes back as `None`, everything else is kept.
+        assert_eq!(round_tripped.post_token_balances, None);
+        assert_eq!(
+            TransactionStatusMeta {
+                post_token_balances: None,
+                ..meta
+            },
+            round_tripped
+        );
+    }
+
+    #[test]
+    fn keeps_absent_meta_fields() {
+        let meta = TransactionStatusMeta {
+            status: Ok(()),
+            inner_instructions: None,
+            log_messages: None,
+            pre_token_balances: None,
+            post_token_balances: None,
+            rewards: None,
+            return_data: None,
+            compute_units_consumed: None,
+            cost_units: None,
+            ..full_meta()
+        };
+
+        assert_eq!(round_trip_meta(meta.clone()), meta);
+    }
+
+    #[test]
+    fn rejects_invalid_proto() {
+        let mut proto = solana_storage::Transaction::from(legacy_transaction());
+        proto.message.as_mut().unwrap().account_keys[0].pop();
+        assert!(matches!(
+            VersionedTransaction::try_from(proto),
+            Err(ConversionError::InvalidPubkey(31))
+        ));
+
+        let mut proto = solana_storage::Transaction::from(legacy_transaction());
+        proto.message.as_mut().unwrap().instructions[0].program_id_index = 256;
+        assert!(matches!(
+            VersionedTransaction::try_from(proto),
+            Err(ConversionError::IndexOutOfRange("program_id_index"))
+        ));
+
+        let proto = solana_storage::Transaction {
+            message: None,
+            ..Default::default()
+        };
+        assert!(matches!(
+            VersionedTransaction::try_from(proto),
+            Err(ConversionError::MissingField("message"))
+        ));
+    }
+}
@@ -0,0 +1,401 @@
+use futures::{
+    future::{self, BoxFuture},
+    FutureExt,
+};
+use std::{
+    cmp::Reverse,
+    time::{Duration, Instant},
+};
+use tonic::Status;
+
+use crate::{
+    error::GeyserClientError,
+    proto::geyser::{CommitmentLevel, SubscribeRequest, SubscribeUpdate},
+    reconnect::{ReconnectConfig, ResilientSubscription},
+    GeyserClient,
+};
+
+#[derive(Debug, Clone)]
+pub struct FailoverConfig {
+    /// Reconnect behaviour of the active endpoint before switching away from it.
+    pub reconnect: ReconnectConfig,
+    /// How often every endpoint is probed with `ping` and `get_slot`.
+    pub health_check_interval: Duration,
+    /// Probes taking longer than this count as failed.
+    pub probe_timeout: Duration,
+    /// Switch when the active stream delivers neither updates nor pongs for this long.
+    pub stall_timeout: Option<Duration>,
+    /// Switch when the active endpoint is this many slots behind the newest one.
+    pub max_slot_lag: u64,
+    /// Probes in a row the active endpoint has to lag on or fail before
+    /// switching, so that a single slow or failed probe does not move the stream.
+    pub lag_probes: u32,
+    /// Commitment used for the `get_slot` probe.
+    pub commitment: CommitmentLevel,
+}
+
+impl Default for FailoverConfig {
+    fn default() -> Self {
+        Self {
+            reconnect: ReconnectConfig {
+                max_attempts: Some(3),
+                ..Default::default()
+            },
+            health_check_interval: Duration::from_secs(5),
+            probe_timeout: Duration::from_secs(2),
+            stall_timeout: Some(Duration::from_secs(30)),
+            max_slot_lag: 20,
+            lag_probes: 3,
+            commitment: CommitmentLevel::Processed,
+        }
+    }
+}
+
+/// Latest probe results of one endpoint.
+#[derive(Debug, Clone, Default)]
+pub struct EndpointHealth {
+    /// Round-trip time of the last `ping` probe.
+    pub rtt: Option<Duration>,
+    /// Slot reported by the last `get_slot` probe.
+    pub slot: Option<u64>,
+    /// Probes or streams that failed in a row.
+    pub failures: u32,
+    pub last_checked: Option<Instant>,
+}
+
+impl EndpointHealth {
+    pub fn is_healthy(&self) -> bool {
+        self.failures == 0
+    }
+
+    /// Orders endpoints by fewest failures, then newest slot, then lowest RTT.
+    fn rank(&self) -> (Reverse<u32>, Option<u64>, Reverse<Duration>) {
+        (
+            Reverse(self.failures),
+            self.slot,
+            Reverse(self.rtt.unwrap_or(Duration::MAX)),
+        )
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwitchReason {
+    /// The active stream ended or failed after exhausting its reconnects.
+    Failed,
+    /// The active stream went quiet for longer than `stall_timeout`.
+    Stalled,
+    /// The active endpoint was more than `max_slot_lag` slots behind on
+    /// `lag_probes` probes in a row.
+    Lagging,
+    /// The health probe of the active endpoint failed on `lag_probes` probes
+    /// in a row while its stream delivered neither updates nor pongs.
+    Unhealthy,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Switchover {
+    pub from: usize,
+    pub to: usize,
+    pub reason: SwitchReason,
+}
+
+type ProbeResult = Result<(Duration, u64), GeyserClientError>;
+
+/// A `Subscribe` stream over a pool of endpoints with one active upstream.
+///
+/// Every endpoint is probed periodically with `ping` and `get_slot`. The
+/// subscription switches to the healthiest standby when the active endpoint
+/// fails, stalls or lags behind the others, and resumes from the last seen
+/// slot so consumers see a single stream. It stays put when no standby is
+/// healthier than the active endpoint. Updates around the switch may be
+/// delivered twice.
+pub struct FailoverSubscription {
+    clients: Vec<GeyserClient>,
+    config: FailoverConfig,
+    request: SubscribeRequest,
+    active: usize,
+    subscription: ResilientSubscription,
+    health: Vec<EndpointHealth>,
+    probe: BoxFuture<'static, Vec<ProbeResult>>,
+    last_slot: Option<u64>,
+    last_activity: Instant,
+    last_pongs: u64,
+    /// Whether the active stream delivered updates since the last probe round.
+    delivered: bool,
+    probe_pongs: u64,
+    lagging_probes: u32,
+    failed_in_row: usize,
+    switchovers: u64,
+    last_switch: Option<Switchover>,
+    unrecoverable_slots: u64,
+}
+
+impl FailoverSubscription {
+    /// Starts on the first client, fails with [`GeyserClientError::NoEndpoints`]
+    /// when `clients` is empty.
+    pub fn new(
+        clients: Vec<GeyserClient>,
+        request: SubscribeRequest,
+        config: FailoverConfig,
+    ) -> Result<Self, GeyserClientError> {
+        if clients.is_empty() {
+            return Err(GeyserClientError::NoEndpoints);
+        }
+
+        let subscription = ResilientSubscription::new(
+            clients[0].clone(),
+            request.clone(),
+            config.reconnect.clone(),
+        );
+        let probe = probe_all(clients.clone(), &config, Duration::ZERO);
+
+        Ok(Self {
+            health: vec![EndpointHealth::default(); clients.len()],
+            clients,
+            config,
+            request,
+            active: 0,
+            subscription,
+            probe,
+            last_slot: None,
+            last_activity: Instant::now(),
+            last_pongs: 0,
+            delivered: false,
+            probe_pongs: 0,
+            lagging_probes: 0,
+            failed_in_row: 0,
+            switchovers: 0,
+            last_switch: None,
+            unrecoverable_slots: 0,
+        })
+    }
+
+    /// Index of the endpoint currently streaming.
+    pub fn active(&self) -> usize {
+        self.active
+    }
+
+    /// Health of every endpoint, indexed like the clients passed to [`FailoverSubscription::new`].
+    pub fn health(&self) -> &[EndpointHealth] {
+        &self.health
+    }
+
+    /// Highest slot seen on any update so far.
+    pub fn last_slot(&self) -> Option<u64> {
+        self.last_slot
+    }
+
+    pub fn switchovers(&self) -> u64 {
+        self.switchovers
+    }
+
+    pub fn last_switch(&self) -> Option<Switchover> {
+        self.last_switch
+    }
+
+    /// Slots that could not be replayed across reconnects and switchovers.
+    pub fn unrecoverable_slots(&self) -> u64 {
+        self.unrecoverable_slots + self.subscription.unrecoverable_slots()
+    }
+
+    /// Replaces the subscription filters on the active endpoint and every later one.
+    pub fn update(&mut self, request: SubscribeRequest) {
+        self.request = request.clone();
+        self.subscription.update(request);
+    }
+
+    /// Returns the next update, switching endpoints as needed.
+    ///
+    /// Returns the last error once every endpoint failed in a row without
+    /// delivering an update in between, or when no standby is healthier than
+    /// the failed endpoint.
+    ///
+    /// Cancel-safe, like [`ResilientSubscription::message`].
+    pub async fn message(&mut self) -> Result<Option<SubscribeUpdate>, GeyserClientError> {
+        loop {
+            let stall_deadline = self
+                .config
+                .stall_timeout
+                .map(|timeout| self.last_activity + timeout);
+            let stall = async move {
+                match stall_deadline {
+                    Some(deadline) => tokio::time::sleep_until(deadline.into()).await,
+                    None => future::pending().await,
+                }
+            };
+
+            tokio::select! {
+                result = self.subscription.message() => match result {
+                    Ok(Some(update)) => {
+                        self.last_activity = Instant::now();
+                        self.delivered = true;
+                        self.failed_in_row = 0;
+                        self.health[self.active].failures = 0;
+                        if let Some(slot) = update.slot() {
+                            self.last_slot = Some(self.last_slot.map_or(slot, |x| x.max(slot)));
+                        }
+                        return Ok(Some(update));
+                    }
+                    result => {
+                        self.health[self.active].failures += 1;
+                        self.failed_in_row += 1;
+                        if self.failed_in_row >= self.clients.len()
+                            || !self.switch(SwitchReason::Failed)
+                        {
+                            return result;
+                        }
+                    }
+                },
+                results = &mut self.probe => {
+                    self.probe = probe_all(
+                        self.clients.clone(),
+                        &self.config,
+                        self.config.health_check_interval,
+                    );
+                    if let Some(reason) = self.record_probes(results) {
+                        self.switch(reason);
+                    }
+                },
+                _ = stall => {
+                    // Pongs show the connection is alive even when the filters match nothing.
+                    let pongs = self.subscription.keepalive_stats().pongs_received();
+                    self.last_activity = Instant::now();
+                    if pongs != self.last_pongs {
+                        self.last_pongs = pongs;
+                    } else {
+                        self.health[self.active].failures += 1;
+                        self.switch(SwitchReason::Stalled);
+                    }
+                },
+            }
+        }
+    }
+
+    /// Stores probe results and returns why the active endpoint should be left, if at all.
+    fn record_probes(&mut self, results: Vec<ProbeResult>) -> Option<SwitchReason> {
+        let now = Instant::now();
+        for (health, result) in self.health.iter_mut().zip(results) {
+            health.last_checked = Some(now);
+            match result {
+                Ok((rtt, slot)) => {
+                    health.rtt = Some(rtt);
+                    health.slot = Some(slot);
+                    health.failures = 0;
+                }
+                Err(_) => health.failures += 1,
+            }
+        }
+
+        // Updates or pongs since the last round show the stream is alive,
+        // whatever the probes say.
+        let pongs = self.subscription.keepalive_stats().pongs_received();
+        let delivering = std::mem::take(&mut self.delivered) || pongs != self.probe_pongs;
+        self.probe_pongs = pongs;
+
+        let active = &self.health[self.active];
+        if !active.is_healthy() {
+            let failing = active.failures >= self.config.lag_probes.max(1);
+            return (failing && !delivering).then_some(SwitchReason::Unhealthy);
+        }
+
+        let newest = self
+            .health
+            .iter()
+            .filter(|health| health.is_healthy())
+            .filter_map(|health| health.slot)
+            .max()?;
+        let active_slot = active.slot.unwrap_or_default();
+        if active_slot.saturating_add(self.config.max_slot_lag) >= newest {
+            self.lagging_probes = 0;
+            return None;
+        }
+
+        self.lagging_probes += 1;
+        (self.lagging_probes >= self.config.lag_probes).then_some(SwitchReason::Lagging)
+    }
+
+    /// Moves the stream to the standby, returns `false` when there is none.
+    fn switch(&mut self, reason: SwitchReason) -> bool {
+        let Some(to) = self.standby() else {
+            return false;
+        };
+
+        let subscription = ResilientSubscription::new(
+            self.clients[to].clone(),
+            self.request.clone(),
+            self.config.reconnect.clone(),
+        )
+        .resume_from(self.last_slot);
+        let previous = std::mem::replace(&mut self.subscription, subscription);
+        self.unrecoverable_slots += previous.unrecoverable_slots();
+
+        self.last_switch = Some(Switchover {
+            from: self.active,
+            to,
+            reason,
+        });
+        self.active = to;
+        self.switchovers += 1;
+        self.last_activity = Instant::now();
+        self.last_pongs = 0;
+        self.delivered = false;
+        self.probe_pongs = 0;
+        self.lagging_probes = 0;
+
+        true
+    }
+
+    /// Picks the standby with the fewest failures, the newest slot and the
+    /// lowest RTT, as long as it ranks above the active endpoint.
+    fn standby(&self) -> Option<usize> {
+        let active = self.health[self.active].rank();
+
+        self.health
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| *index != self.active)
+            .map(|(index, health)| (index, health.rank()))
+            .filter(|(_, rank)| *rank > active)
+            .max_by_key(|(_, rank)| *rank)
+            .map(|(index, _)| index)
+    }
+}
+
+fn probe_all(
+    clients: Vec<GeyserClient>,
+    config: &FailoverConfig,
+    delay: Duration,
+) -> BoxFuture<'static, Vec<ProbeResult>> {
+    let timeout = config.probe_timeout;
+    let commitment = config.commitment;
+
+    async move {
+        tokio::time::sleep(delay).await;
+        future::join_all(
+            clients
+                .into_iter()
+                .map(|client| probe(client, commitment, timeout)),
+        )
+        .await
+    }
+    .boxed()
+}
+
+async fn probe(
+    mut client: GeyserClient,
+    commitment: CommitmentLevel,
+    timeout: Duration,
+) -> ProbeResult {
+    let probe = async {
+        let started = Instant::now();
+        client.ping(1).await?;
+        let rtt = started.elapsed();
+        let slot = client.get_slot(Some(commitment)).await?.slot;
+
+        Ok((rtt, slot))
+    };
+
+    tokio::time::timeout(timeout, probe)
+        .await
+        .map_err(|_| Status::deadline_exceeded("health probe timed out"))?
+}
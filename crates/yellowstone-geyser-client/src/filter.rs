@@ -0,0 +1,577 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::collections::HashSet;
+
+use crate::{
+    proto::geyser::{
+        subscribe_request_filter_accounts_filter::Filter as AccountsFilter,
+        subscribe_request_filter_accounts_filter_lamports::Cmp as LamportsCmp,
+        subscribe_request_filter_accounts_filter_memcmp::Data as MemcmpData,
+        subscribe_update::UpdateOneof, CommitmentLevel, SlotStatus, SubscribeRequest,
+        SubscribeRequestFilterAccounts, SubscribeRequestFilterSlots,
+        SubscribeRequestFilterTransactions, SubscribeUpdate, SubscribeUpdateAccountInfo,
+        SubscribeUpdateSlot, SubscribeUpdateTransactionInfo, SubscribeUpdateTransactionStatus,
+    },
+    request_builder::{RequestValidationError, PUBKEY_LEN},
+    spl::{ACCOUNT_LEN, ACCOUNT_STATE_OFFSET, ACCOUNT_TYPE_ACCOUNT, ACCOUNT_TYPE_OFFSET},
+};
+
+/// Evaluates the filters of a `SubscribeRequest` locally.
+///
+/// Matches the server side semantics: every condition that is set must hold,
+/// and a list condition holds if any of its entries matches. Block, block meta
+/// and entry filters always match, their contents are not filtered.
+///
+/// Transaction status updates carry no account keys, so a `transactions_status`
+/// filter with `account_include`, `account_exclude` or `account_required` set
+/// never matches them locally.
+#[derive(Debug, Clone)]
+pub struct FilterMatcher {
+    commitment: SlotStatus,
+    accounts: Vec<(String, AccountsMatcher)>,
+    slots: Vec<(String, SubscribeRequestFilterSlots)>,
+    transactions: Vec<(String, TransactionsMatcher)>,
+    transactions_status: Vec<(String, TransactionsMatcher)>,
+    blocks: Vec<String>,
+    blocks_meta: Vec<String>,
+    entry: Vec<String>,
+}
+
+impl FilterMatcher {
+    pub fn new(request: &SubscribeRequest) -> Result<Self, RequestValidationError> {
+        let commitment = match request.commitment() {
+            CommitmentLevel::Processed => SlotStatus::SlotProcessed,
+            CommitmentLevel::Confirmed => SlotStatus::SlotConfirmed,
+            CommitmentLevel::Finalized => SlotStatus::SlotFinalized,
+        };
+
+        let mut matcher = Self {
+            commitment,
+            accounts: request
+                .accounts
+                .iter()
+                .map(|(name, filter)| Ok((name.clone(), AccountsMatcher::new(name, filter)?)))
+                .collect::<Result<_, _>>()?,
+            slots: request
+                .slots
+                .iter()
+                .map(|(name, filter)| (name.clone(), *filter))
+                .collect(),
+            transactions: request
+                .transactions
+                .iter()
+                .map(|(name, filter)| Ok((name.clone(), TransactionsMatcher::new(name, filter)?)))
+                .collect::<Result<_, _>>()?,
+            transactions_status: request
+                .transactions_status
+                .iter()
+                .map(|(name, filter)| Ok((name.clone(), TransactionsMatcher::new(name, filter)?)))
+                .collect::<Result<_, _>>()?,
+            blocks: request.blocks.keys().cloned().collect(),
+            blocks_meta: request.blocks_meta.keys().cloned().collect(),
+            entry: request.entry.keys().cloned().collect(),
+        };
+        // The request maps have no order, sort so matches come out the same every time.
+        matcher.accounts.sort_by(|(a, _), (b, _)| a.cmp(b));
+        matcher.slots.sort_by(|(a, _), (b, _)| a.cmp(b));
+        matcher.transactions.sort_by(|(a, _), (b, _)| a.cmp(b));
+        matcher
+            .transactions_status
+            .sort_by(|(a, _), (b, _)| a.cmp(b));
+        matcher.blocks.sort();
+        matcher.blocks_meta.sort();
+        matcher.entry.sort();
+
+        Ok(matcher)
+    }
+
+    /// Names of the filters matching `update` in name order, empty for pings
+    /// and pongs.
+    pub fn matches(&self, update: &SubscribeUpdate) -> Vec<String> {
+        let Some(update) = update.update_oneof.as_ref() else {
+            return Vec::new();
+        };
+
+        match update {
+            UpdateOneof::Account(account) => match account.account.as_ref() {
+                Some(info) => names(&self.accounts, |filter| filter.matches(info)),
+                None => Vec::new(),
+            },
+            UpdateOneof::Slot(slot) => names(&self.slots, |filter| self.matches_slot(filter, slot)),
+            UpdateOneof::Transaction(transaction) => match transaction.transaction.as_ref() {
+                Some(info) => names(&self.transactions, |filter| filter.matches(info)),
+                None => Vec::new(),
+            },
+            UpdateOneof::TransactionStatus(status) => names(&self.transactions_status, |filter| {
+                filter.matches_status(status)
+            }),
+            UpdateOneof::Block(_) => self.blocks.clone(),
+            UpdateOneof::BlockMeta(_) => self.blocks_meta.clone(),
+            UpdateOneof::Entry(_) => self.entry.clone(),
+            UpdateOneof::Ping(_) | UpdateOneof::Pong(_) => Vec::new(),
+        }
+    }
+
+    /// Replaces `update.filters` with the matching filter names and returns
+    /// whether any filter matched.
+    pub fn apply(&self, update: &mut SubscribeUpdate) -> bool {
+        update.filters = self.matches(update);
+        !update.filters.is_empty()
+    }
+
+    fn matches_slot(
+        &self,
+        filter: &SubscribeRequestFilterSlots,
+        slot: &SubscribeUpdateSlot,
+    ) -> bool {
+        let status = slot.status();
+        if filter.filter_by_commitment() && status != self.commitment {
+            return false;
+        }
+        filter.interslot_updates()
+            || matches!(
+                status,
+                SlotStatus::SlotProcessed | SlotStatus::SlotConfirmed | SlotStatus::SlotFinalized
+            )
+    }
+}
+
+fn names<T>(filters: &[(String, T)], matches: impl Fn(&T) -> bool) -> Vec<String> {
+    filters
+        .iter()
+        .filter(|(_, filter)| matches(filter))
+        .map(|(name, _)| name.clone())
+        .collect()
+}
+
+#[derive(Debug, Clone)]
+struct AccountsMatcher {
+    account: HashSet<Vec<u8>>,
+    owner: HashSet<Vec<u8>>,
+    conditions: Vec<AccountCondition>,
+    nonempty_txn_signature: Option<bool>,
+}
+
+#[derive(Debug, Clone)]
+enum AccountCondition {
+    Memcmp { offset: u64, bytes: Vec<u8> },
+    Datasize(u64),
+    TokenAccountState,
+    Lamports(LamportsCmp),
+}
+
+impl AccountsMatcher {
+    fn new(
+        name: &str,
+        filter: &SubscribeRequestFilterAccounts,
+    ) -> Result<Self, RequestValidationError> {
+        let invalid = |reason: &str| RequestValidationError::InvalidFilter {
+            filter: name.to_string(),
+            reason: reason.to_string(),
+        };
+
+        let conditions = filter
+            .filters
+            .iter()
+            .map(|filter| match filter.filter.as_ref() {
+                Some(AccountsFilter::Memcmp(memcmp)) => {
+                    let bytes = match memcmp.data.as_ref() {
+                        Some(MemcmpData::Bytes(bytes)) => bytes.clone(),
+                        Some(MemcmpData::Base58(data)) => bs58::decode(data)
+                            .into_vec()
+                            .map_err(|_| invalid("memcmp data is not valid base58"))?,
+                        Some(MemcmpData::Base64(data)) => STANDARD
+                            .decode(data)
+                            .map_err(|_| invalid("memcmp data is not valid base64"))?,
+                        None => return Err(invalid("memcmp without data")),
+                    };
+                    Ok(AccountCondition::Memcmp {
+                        offset: memcmp.offset,
+                        bytes,
+                    })
+                }
+                Some(AccountsFilter::Datasize(size)) => Ok(AccountCondition::Datasize(*size)),
+                Some(AccountsFilter::TokenAccountState(_)) => {
+                    Ok(AccountCondition::TokenAccountState)
+                }
+                Some(AccountsFilter::Lamports(lamports)) => lamports
+                    .cmp
+                    .map(AccountCondition::Lamports)
+                    .ok_or_else(|| invalid("lamports filter without a comparison")),
+                None => Err(invalid("accounts filter without a condition")),
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(Self {
+            account: decode_pubkeys(name, &filter.account)?,
+            owner: decode_pubkeys(name, &filter.owner)?,
+            conditions,
+            nonempty_txn_signature: filter.nonempty_txn_signature,
+        })
+    }
+
+    fn matches(&self, info: &SubscribeUpdateAccountInfo) -> bool {
+        if !self.account.is_empty() && !self.account.contains(&info.pubkey) {
+            return false;
+        }
+        if !self.owner.is_empty() && !self.owner.contains(&info.owner) {
+            return false;
+        }
+        if self
+            .nonempty_txn_signature
+            .is_some_and(|nonempty| nonempty != info.txn_signature.is_some())
+        {
+            return false;
+        }
+
+        self.conditions.iter().all(|condition| match condition {
+            // Offsets beyond the address space can never be inside the data.
+            AccountCondition::Memcmp { offset, bytes } => usize::try_from(*offset)
+                .ok()
+                .and_then(|offset| info.data.get(offset..offset.saturating_add(bytes.len())))
+                .is_some_and(|data| data == bytes.as_slice()),
+            AccountCondition::Datasize(size) => info.data.len() as u64 == *size,
+            AccountCondition::TokenAccountState => is_token_account(&info.data),
+            AccountCondition::Lamports(cmp) => match *cmp {
+                LamportsCmp::Eq(value) => info.lamports == value,
+                LamportsCmp::Ne(value) => info.lamports != value,
+                LamportsCmp::Lt(value) => info.lamports < value,
+                LamportsCmp::Gt(value) => info.lamports > value,
+            },
+        })
+    }
+}
+
+/// An initialized SPL Token or Token-2022 account.
+fn is_token_account(data: &[u8]) -> bool {
+    let initialized = data
+        .get(ACCOUNT_STATE_OFFSET)
+        .is_some_and(|state| *state != 0);
+
+    match data.len() {
+        ACCOUNT_LEN => initialized,
+        len if len > ACCOUNT_TYPE_OFFSET => {
+            initialized && data[ACCOUNT_TYPE_OFFSET] == ACCOUNT_TYPE_ACCOUNT
+        }
+        _ => false,
+    }
+}
+
+#[derive(Debug, Clone)]
+struct TransactionsMatcher {
+    vote: Option<bool>,
+    failed: Option<bool>,
+    signature: Option<Vec<u8>>,
+    account_include: HashSet<Vec<u8>>,
+    account_exclude: HashSet<Vec<u8>>,
+    account_required: HashSet<Vec<u8>>,
+}
+
+impl TransactionsMatcher {
+    fn new(
+        name: &str,
+        filter: &SubscribeRequestFilterTransactions,
+    ) -> Result<Self, RequestValidationError> {
+        let signature = filter
+            .signature
+            .as_ref()
+            .map(|signature| {
+                bs58::decode(signature).into_vec().map_err(|_| {
+                    RequestValidationError::InvalidSignature {
+                        filter: name.to_string(),
+                        value: signature.clone(),
+                    }
+                })
+            })
+            .transpose()?;
+
+        Ok(Self {
+            vote: filter.vote,
+            failed: filter.failed,
+            signature,
+            account_include: decode_pubkeys(name, &filter.account_include)?,
+            account_exclude: decode_pubkeys(name, &filter.account_exclude)?,
+            account_required: decode_pubkeys(name, &filter.account_required)?,
+        })
+    }
+
+    fn matches(&self, info: &SubscribeUpdateTransactionInfo) -> bool {
+        let failed = info.meta.as_ref().is_some_and(|meta| meta.err.is_some());
+        if !self.matches_flags(info.is_vote, failed, &info.signature) {
+            return false;
+        }
+
+        let message = info
+            .transaction
+            .as_ref()
+            .and_then(|transaction| transaction.message.as_ref());
+        let keys = message
+            .into_iter()
+            .flat_map(|message| &message.account_keys)
+            .chain(info.meta.iter().flat_map(|meta| {
+                meta.loaded_writable_addresses
+                    .iter()
+                    .chain(&meta.loaded_readonly_addresses)
+            }))
+            .collect::<HashSet<_>>();
+
+        (self.account_include.is_empty()
+            || self.account_include.iter().any(|key| keys.contains(key)))
+            && !self.account_exclude.iter().any(|key| keys.contains(key))
+            && self.account_required.iter().all(|key| keys.contains(key))
+    }
+
+    /// Status updates carry no account keys, so filters with account
+    /// conditions cannot be evaluated and never match.
+    fn matches_status(&self, status: &SubscribeUpdateTransactionStatus) -> bool {
+        self.account_include.is_empty()
+            && self.account_exclude.is_empty()
+            && self.account_required.is_empty()
+            && self.matches_flags(status.is_vote, status.err.is_some(), &status.signature)
+    }
+
+    fn matches_flags(&self, is_vote: bool, failed: bool, signature: &[u8]) -> bool {
+        self.vote.is_none_or(|vote| vote == is_vote)
+            && self.failed.is_none_or(|expected| expected == failed)
+            && self
+                .signature
+                .as_ref()
+                .is_none_or(|expected| expected == signature)
+    }
+}
+
+fn decode_pubkeys(
+    name: &str,
+    pubkeys: &[String],
+) -> Result<HashSet<Vec<u8>>, RequestValidationError> {
+    pubkeys
+        .iter()
+        .map(|pubkey| {
+            bs58::decode(pubkey)
+                .into_vec()
+                .ok()
+                .filter(|bytes| bytes.len() == PUBKEY_LEN)
+                .ok_or_else(|| RequestValidationError::InvalidPubkey {
+                    filter: name.to_string(),
+                    value: pubkey.clone(),
+                })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        proto::{
+            geyser::{SubscribeUpdateAccount, SubscribeUpdateTransaction},
+            solana_storage::{Message, Transaction, TransactionError, TransactionStatusMeta},
+        },
+        request_builder::SubscribeRequestBuilder,
+    };
+
+    fn pubkey(byte: u8) -> String {
+        bs58::encode([byte; PUBKEY_LEN]).into_string()
+    }
+
+    fn matcher(build: impl FnOnce(&mut SubscribeRequestBuilder)) -> FilterMatcher {
+        let mut builder = SubscribeRequestBuilder::new();
+        build(&mut builder);
+        FilterMatcher::new(&builder.build().unwrap()).unwrap()
+    }
+
+    fn account(data: Vec<u8>) -> SubscribeUpdate {
+        SubscribeUpdate {
+            update_oneof: Some(UpdateOneof::Account(SubscribeUpdateAccount {
+                account: Some(SubscribeUpdateAccountInfo {
+                    pubkey: vec![1; PUBKEY_LEN],
+                    owner: vec![2; PUBKEY_LEN],
+                    data,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+    }
+
+    fn transaction(is_vote: bool, failed: bool, keys: &[u8]) -> SubscribeUpdate {
+        SubscribeUpdate {
+            update_oneof: Some(UpdateOneof::Transaction(SubscribeUpdateTransaction {
+                transaction: Some(SubscribeUpdateTransactionInfo {
+                    is_vote,
+                    transaction: Some(Transaction {
+                        message: Some(Message {
+                            account_keys: keys.iter().map(|key| vec![*key; PUBKEY_LEN]).collect(),
+                            ..Default::default()
+                        }),
+                        ..Default::default()
+                    }),
+                    meta: Some(TransactionStatusMeta {
+                        err: failed.then(TransactionError::default),
+                        ..Default::default()
+                    }),
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+    }
+
+    fn status(is_vote: bool, failed: bool) -> SubscribeUpdate {
+        SubscribeUpdate {
+            update_oneof: Some(UpdateOneof::TransactionStatus(
+                SubscribeUpdateTransactionStatus {
+                    is_vote,
+                    err: failed.then(TransactionError::default),
+                    ..Default::default()
+                },
+            )),
+            ..Default::default()
+        }
+    }
+
+    fn token_account(state: u8, extra: Option<u8>) -> Vec<u8> {
+        let mut data = vec![0; ACCOUNT_LEN];
+        data[ACCOUNT_STATE_OFFSET] = state;
+        data.extend(extra);
+        data
+    }
+
+    #[test]
+    fn matches_memcmp() {
+        let matcher = matcher(|builder| {
+            builder.account("accounts").memcmp(2, [7, 8]);
+        });
+
+        assert_eq!(matcher.matches(&account(vec![0, 0, 7, 8])), ["accounts"]);
+        assert!(matcher.matches(&account(vec![0, 0, 7, 9])).is_empty());
+        // Data ending inside the compared range does not match.
+        assert!(matcher.matches(&account(vec![0, 0, 7])).is_empty());
+    }
+
+    #[test]
+    fn never_matches_memcmp_past_the_address_space() {
+        let matcher = matcher(|builder| {
+            builder.account("accounts").memcmp(u64::MAX, [7, 8]);
+        });
+
+        assert!(matcher.matches(&account(vec![7, 8])).is_empty());
+    }
+
+    #[test]
+    fn matches_datasize() {
+        let matcher = matcher(|builder| {
+            builder.account("accounts").datasize(4);
+        });
+
+        assert_eq!(matcher.matches(&account(vec![0; 4])), ["accounts"]);
+        assert!(matcher.matches(&account(vec![0; 5])).is_empty());
+    }
+
+    #[test]
+    fn matches_token_account_state() {
+        let matcher = matcher(|builder| {
+            builder.account("accounts").token_account_state();
+        });
+
+        assert_eq!(
+            matcher.matches(&account(token_account(1, None))),
+            ["accounts"]
+        );
+        assert_eq!(
+            matcher.matches(&account(token_account(1, Some(ACCOUNT_TYPE_ACCOUNT)))),
+            ["accounts"]
+        );
+        // Uninitialized, a Token-2022 mint and a short account.
+        assert!(matcher.matches(&account(token_account(0, None))).is_empty());
+        assert!(matcher
+            .matches(&account(token_account(1, Some(1))))
+            .is_empty());
+        assert!(matcher.matches(&account(vec![1; 100])).is_empty());
+    }
+
+    #[test]
+    fn matches_all_account_conditions() {
+        let matcher = matcher(|builder| {
+            builder
+                .account("accounts")
+                .pubkey(pubkey(1))
+                .owner(pubkey(2))
+                .datasize(2);
+            builder.account("other").owner(pubkey(3));
+        });
+
+        assert_eq!(matcher.matches(&account(vec![0; 2])), ["accounts"]);
+        assert!(matcher.matches(&account(vec![0; 3])).is_empty());
+    }
+
+    #[test]
+    fn matches_transaction_flags() {
+        let matcher = matcher(|builder| {
+            builder.transactions("votes").vote(true);
+            builder.transactions("failed").vote(false).failed(true);
+            builder.transactions("all");
+        });
+
+        assert_eq!(
+            matcher.matches(&transaction(true, false, &[])),
+            ["all", "votes"]
+        );
+        assert_eq!(
+            matcher.matches(&transaction(false, true, &[])),
+            ["all", "failed"]
+        );
+        assert_eq!(matcher.matches(&transaction(false, false, &[])), ["all"]);
+    }
+
+    #[test]
+    fn matches_transaction_accounts() {
+        let matcher = matcher(|builder| {
+            builder
+                .transactions("transactions")
+                .account_include([pubkey(1), pubkey(2)])
+                .account_exclude([pubkey(3)])
+                .account_required([pubkey(4)]);
+        });
+
+        assert_eq!(
+            matcher.matches(&transaction(false, false, &[2, 4])),
+            ["transactions"]
+        );
+        assert!(matcher.matches(&transaction(false, false, &[2])).is_empty());
+        assert!(matcher
+            .matches(&transaction(false, false, &[2, 3, 4]))
+            .is_empty());
+        assert!(matcher.matches(&transaction(false, false, &[4])).is_empty());
+    }
+
+    #[test]
+    fn matches_status_flags() {
+        let matcher = matcher(|builder| {
+            builder.transactions_status("votes").vote(true);
+            builder.transactions_status("failed").failed(true);
+        });
+
+        assert_eq!(matcher.matches(&status(true, false)), ["votes"]);
+        assert_eq!(matcher.matches(&status(false, true)), ["failed"]);
+        assert!(matcher.matches(&status(false, false)).is_empty());
+    }
+
+    #[test]
+    fn status_never_matches_account_conditions() {
+        let matcher = matcher(|builder| {
+            builder
+                .transactions_status("include")
+                .account_include([pubkey(1)]);
+            builder
+                .transactions_status("exclude")
+                .account_exclude([pubkey(1)]);
+            builder
+                .transactions_status("required")
+                .account_required([pubkey(1)]);
+        });
+
+        assert!(matcher.matches(&status(false, false)).is_empty());
+    }
+}
@@ -0,0 +1,158 @@
+//! Instruction decoding for transaction updates with [`solana_instruction_decoder`].
+
+use solana_message::{v0::LoadedAddresses, VersionedMessage};
+
+use crate::{
+    convert::{inner_instructions, pubkeys, ConversionError},
+    proto::geyser::SubscribeUpdateTransactionInfo,
+};
+
+pub use solana_instruction_decoder::{
+    decode_transaction, DecodedInstruction, InstructionDecodeError, ParsedInstruction,
+    TransactionInstruction,
+};
+
+impl SubscribeUpdateTransactionInfo {
+    /// Decodes every top-level instruction together with the inner
+    /// instructions it invoked.
+    ///
+    /// Accounts loaded from address lookup tables and the inner instructions
+    /// are taken from `meta`, the rest of the meta is not converted.
+    pub fn decode_instructions(&self) -> Result<Vec<TransactionInstruction>, ConversionError> {
+        let message = self
+            .transaction
+            .as_ref()
+            .and_then(|transaction| transaction.message.clone())
+            .ok_or(ConversionError::MissingField("message"))?;
+        let message = VersionedMessage::try_from(message)?;
+
+        let (loaded_addresses, inner) = match self.meta.as_ref() {
+            Some(meta) => {
+                let loaded_addresses = LoadedAddresses {
+                    writable: pubkeys(meta.loaded_writable_addresses.clone())?,
+                    readonly: pubkeys(meta.loaded_readonly_addresses.clone())?,
+                };
+                let inner = match meta.inner_instructions_none {
+                    true => None,
+                    false => Some(
+                        meta.inner_instructions
+                            .iter()
+                            .cloned()
+                            .map(inner_instructions)
+                            .collect::<Result<Vec<_>, _>>()?,
+                    ),
+                };
+                (Some(loaded_addresses), inner)
+            }
+            None => (None, None),
+        };
+
+        Ok(decode_transaction(
+            &message,
+            loaded_addresses.as_ref(),
+            inner.as_deref(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::solana_storage::{
+        CompiledInstruction, InnerInstruction, InnerInstructions, Message, MessageHeader,
+        Transaction, TransactionStatusMeta,
+    };
+    use solana_instruction_decoder::{system, SystemInstruction};
+    use solana_pubkey::Pubkey;
+
+    fn transfer(lamports: u64) -> Vec<u8> {
+        [2u32.to_le_bytes().as_slice(), &lamports.to_le_bytes()].concat()
+    }
+
+    fn info(meta: Option<TransactionStatusMeta>) -> SubscribeUpdateTransactionInfo {
+        SubscribeUpdateTransactionInfo {
+            transaction: Some(Transaction {
+                signatures: vec![vec![1; 64]],
+                message: Some(Message {
+                    header: Some(MessageHeader {
+                        num_required_signatures: 1,
+                        num_readonly_signed_accounts: 0,
+                        num_readonly_unsigned_accounts: 1,
+                    }),
+                    account_keys: vec![vec![1; 32], system::ID.to_bytes().to_vec()],
+                    recent_blockhash: vec![0; 32],
+                    instructions: vec![CompiledInstruction {
+                        program_id_index: 1,
+                        // The destination comes from a lookup table.
+                        accounts: vec![0, 2],
+                        data: transfer(5),
+                    }],
+                    versioned: true,
+                    address_table_lookups: Vec::new(),
+                }),
+            }),
+            meta,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn decodes_with_loaded_addresses_and_inner_instructions() {
+        let meta = TransactionStatusMeta {
+            loaded_writable_addresses: vec![vec![3; 32]],
+            inner_instructions: vec![InnerInstructions {
+                index: 0,
+                instructions: vec![InnerInstruction {
+                    program_id_index: 1,
+                    accounts: vec![2, 0],
+                    data: transfer(1),
+                    stack_height: Some(2),
+                }],
+            }],
+            ..Default::default()
+        };
+
+        let instructions = info(Some(meta)).decode_instructions().unwrap();
+        assert_eq!(instructions.len(), 1);
+        let instruction = instructions[0].instruction.as_ref().unwrap();
+        assert_eq!(instruction.program_id, system::ID);
+        assert_eq!(
+            instruction.parsed,
+            Some(ParsedInstruction::System(SystemInstruction::Transfer {
+                source: Pubkey::new_from_array([1; 32]),
+                destination: Pubkey::new_from_array([3; 32]),
+                lamports: 5,
+            }))
+        );
+        let inner = instructions[0].inner_instructions[0].as_ref().unwrap();
+        assert_eq!(inner.stack_height, Some(2));
+        assert_eq!(
+            inner.parsed,
+            Some(ParsedInstruction::System(SystemInstruction::Transfer {
+                source: Pubkey::new_from_array([3; 32]),
+                destination: Pubkey::new_from_array([1; 32]),
+                lamports: 1,
+            }))
+        );
+    }
+
+    #[test]
+    fn fails_to_resolve_lookups_without_meta() {
+        let instructions = info(None).decode_instructions().unwrap();
+
+        assert_eq!(
+            instructions[0].instruction,
+            Err(InstructionDecodeError::AccountIndexOutOfRange(2))
+        );
+    }
+
+    #[test]
+    fn requires_message() {
+        let info = SubscribeUpdateTransactionInfo::default();
+
+        assert!(matches!(
+            info.decode_instructions(),
+            Err(ConversionError::MissingField("message"))
+        ));
+    }
+}
@@ -0,0 +1,295 @@
+use futures::Stream;
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    sync::{Arc, Mutex},
+    task::{ready, Context, Poll},
+    time::{Duration, Instant},
+};
+use tokio::time::{interval_at, Interval, MissedTickBehavior};
+use tonic::{Status, Streaming};
+
+use crate::{
+    error::GeyserClientError,
+    latency::{LatencyStats, TimestampedUpdate},
+    proto::geyser::{
+        subscribe_update::UpdateOneof, SubscribeRequest, SubscribeRequestPing, SubscribeUpdate,
+    },
+    SubscribeRequestSender,
+};
+
+/// Pings older than this many outstanding requests are considered lost.
+const MAX_PENDING_PINGS: usize = 64;
+
+#[derive(Debug, Clone)]
+pub struct KeepaliveConfig {
+    /// Answer server `Ping` updates with a `SubscribeRequestPing`.
+    pub respond_to_pings: bool,
+    /// Interval for client initiated pings, `None` disables them.
+    pub ping_interval: Option<Duration>,
+    /// Pass `Ping` and `Pong` updates through to the caller.
+    pub forward_pings: bool,
+}
+
+impl Default for KeepaliveConfig {
+    fn default() -> Self {
+        Self {
+            respond_to_pings: true,
+            ping_interval: Some(Duration::from_secs(10)),
+            forward_pings: false,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct KeepaliveState {
+    last_rtt: Option<Duration>,
+    pings_sent: u64,
+    pongs_received: u64,
+}
+
+/// Round-trip statistics of a keepalive stream, shared with the stream itself.
+#[derive(Debug, Clone, Default)]
+pub struct KeepaliveStats {
+    state: Arc<Mutex<KeepaliveState>>,
+}
+
+impl KeepaliveStats {
+    /// Round-trip time of the most recently answered ping.
+    pub fn last_rtt(&self) -> Option<Duration> {
+        self.state.lock().unwrap().last_rtt
+    }
+
+    pub fn pings_sent(&self) -> u64 {
+        self.state.lock().unwrap().pings_sent
+    }
+
+    pub fn pongs_received(&self) -> u64 {
+        self.state.lock().unwrap().pongs_received
+    }
+}
+
+/// A `Subscribe` stream that answers server pings and measures round-trip time.
+///
+/// Every ping sent by the stream carries a unique id, and the matching
+/// `SubscribeUpdatePong` is used to update [`KeepaliveStats`]. Updates are
+/// stamped with their receive time as they come off the wire and recorded in
+/// [`LatencyStats`].
+pub struct KeepaliveStream {
+    sender: SubscribeRequestSender,
+    stream: Streaming<SubscribeUpdate>,
+    config: KeepaliveConfig,
+    interval: Option<Interval>,
+    next_id: i32,
+    pending: VecDeque<(i32, Instant)>,
+    stats: KeepaliveStats,
+    latency_stats: LatencyStats,
+    #[cfg(feature = "metrics")]
+    metrics: Option<crate::metrics::GeyserMetrics>,
+    #[cfg(feature = "tracing")]
+    span: tracing::Span,
+}
+
+impl KeepaliveStream {
+    pub fn new(
+        sender: SubscribeRequestSender,
+        stream: Streaming<SubscribeUpdate>,
+        config: KeepaliveConfig,
+    ) -> Self {
+        Self::with_stats(sender, stream, config, KeepaliveStats::default())
+    }
+
+    pub(crate) fn with_stats(
+        sender: SubscribeRequestSender,
+        stream: Streaming<SubscribeUpdate>,
+        config: KeepaliveConfig,
+        stats: KeepaliveStats,
+    ) -> Self {
+        let interval = config.ping_interval.map(|period| {
+            let mut interval = interval_at(tokio::time::Instant::now() + period, period);
+            interval.set_missed_tick_behavior(MissedTickBehavior::Delay);
+            interval
+        });
+
+        Self {
+            sender,
+            stream,
+            config,
+            interval,
+            next_id: 1,
+            pending: VecDeque::new(),
+            stats,
+            latency_stats: LatencyStats::default(),
+            #[cfg(feature = "metrics")]
+            metrics: None,
+            #[cfg(feature = "tracing")]
+            span: tracing::Span::none(),
+        }
+    }
+
+    /// Records latency into `stats` instead of a fresh [`LatencyStats`], e.g.
+    /// to keep one set of histograms across several streams.
+    pub fn with_latency_stats(mut self, stats: LatencyStats) -> Self {
+        self.latency_stats = stats;
+        self
+    }
+
+    #[cfg(feature = "metrics")]
+    pub(crate) fn with_metrics(mut self, metrics: Option<crate::metrics::GeyserMetrics>) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    #[cfg(feature = "tracing")]
+    pub(crate) fn with_span(mut self, span: tracing::Span) -> Self {
+        self.span = span;
+        self
+    }
+
+    pub fn sender(&self) -> &SubscribeRequestSender {
+        &self.sender
+    }
+
+    pub fn stats(&self) -> KeepaliveStats {
+        self.stats.clone()
+    }
+
+    /// Latency and slot lag of the updates returned so far.
+    pub fn latency_stats(&self) -> LatencyStats {
+        self.latency_stats.clone()
+    }
+
+    /// Returns the next update that is not consumed by the keepalive logic.
+    pub async fn message(&mut self) -> Result<Option<SubscribeUpdate>, GeyserClientError> {
+        let message = self.timestamped_message().await?;
+
+        Ok(message.map(|message| message.update))
+    }
+
+    /// Like [`Self::message`], with the local time the update was received at.
+    pub async fn timestamped_message(
+        &mut self,
+    ) -> Result<Option<TimestampedUpdate>, GeyserClientError> {
+        let message = futures::future::poll_fn(|cx| self.poll_timestamped(cx)).await;
+
+        Ok(message.transpose()?)
+    }
+
+    fn send_ping(&mut self) {
+        let id = self.next_id;
+        self.next_id = self.next_id.wrapping_add(1).max(1);
+
+        let request = SubscribeRequest {
+            ping: Some(SubscribeRequestPing { id }),
+            ..Default::default()
+        };
+        if self.sender.send(request).is_err() {
+            return;
+        }
+
+        if self.pending.len() == MAX_PENDING_PINGS {
+            self.pending.pop_front();
+        }
+        self.pending.push_back((id, Instant::now()));
+        self.stats.state.lock().unwrap().pings_sent += 1;
+    }
+
+    fn record_pong(&mut self, id: i32) {
+        let Some(position) = self.pending.iter().position(|(x, _)| *x == id) else {
+            return;
+        };
+        let (_, sent_at) = self.pending[position];
+        // Pings sent before this one were either answered out of order or lost.
+        self.pending.drain(..=position);
+
+        let rtt = sent_at.elapsed();
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = self.metrics.as_ref() {
+            metrics.observe_ping_rtt(rtt);
+        }
+
+        let mut state = self.stats.state.lock().unwrap();
+        state.last_rtt = Some(rtt);
+        state.pongs_received += 1;
+    }
+}
+
+impl KeepaliveStream {
+    fn poll_timestamped(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<TimestampedUpdate, Status>>> {
+        loop {
+            if let Some(interval) = self.interval.as_mut() {
+                let mut due = false;
+                while interval.poll_tick(cx).is_ready() {
+                    due = true;
+                }
+                if due {
+                    self.send_ping();
+                }
+            }
+
+            let update = match ready!(Pin::new(&mut self.stream).poll_next(cx)) {
+                Some(Ok(update)) => update,
+                other => {
+                    #[cfg(feature = "metrics")]
+                    if let (Some(metrics), Some(Err(status))) = (self.metrics.as_ref(), &other) {
+                        metrics.observe_error(status.code());
+                    }
+                    #[cfg(feature = "tracing")]
+                    match &other {
+                        Some(Err(status)) => tracing::warn!(
+                            parent: &self.span,
+                            code = ?status.code(),
+                            error = status.message(),
+                            "stream failed"
+                        ),
+                        _ => tracing::info!(parent: &self.span, "stream closed by server"),
+                    }
+                    return Poll::Ready(match other {
+                        Some(Err(status)) => Some(Err(status)),
+                        _ => None,
+                    });
+                }
+            };
+            let update = TimestampedUpdate::new(update);
+            #[cfg(feature = "metrics")]
+            if let Some(metrics) = self.metrics.as_ref() {
+                metrics.observe_update(&update.update);
+            }
+
+            match update.update.update_oneof.as_ref() {
+                Some(UpdateOneof::Ping(_)) => {
+                    if self.config.respond_to_pings {
+                        self.send_ping();
+                    }
+                }
+                Some(UpdateOneof::Pong(pong)) => {
+                    self.record_pong(pong.id);
+                }
+                _ => {
+                    self.latency_stats.record(&update);
+                    return Poll::Ready(Some(Ok(update)));
+                }
+            }
+
+            if self.config.forward_pings {
+                self.latency_stats.record(&update);
+                return Poll::Ready(Some(Ok(update)));
+            }
+        }
+    }
+}
+
+impl Stream for KeepaliveStream {
+    type Item = Result<SubscribeUpdate, Status>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Poll::Ready(match ready!(self.poll_timestamped(cx)) {
+            Some(Ok(message)) => Some(Ok(message.update)),
+            Some(Err(status)) => Some(Err(status)),
+            None => None,
+        })
+    }
+}
@@ -0,0 +1,380 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, Mutex},
+    time::{Duration, SystemTime},
+};
+
+use crate::proto::geyser::{subscribe_update::UpdateOneof, SubscribeUpdate};
+
+/// Values below this are counted exactly, larger ones in log-scaled buckets.
+const LINEAR_BUCKETS: u64 = 16;
+/// Buckets per power of two, bounding the relative error to 1/8.
+const SUB_BUCKETS: u64 = 8;
+const BUCKETS: usize = (LINEAR_BUCKETS + (64 - 4) * SUB_BUCKETS) as usize;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum UpdateKind {
+    Account,
+    Slot,
+    Transaction,
+    TransactionStatus,
+    Block,
+    BlockMeta,
+    Entry,
+    Ping,
+    Pong,
+}
+
+impl UpdateKind {
+    pub const ALL: [Self; 9] = [
+        Self::Account,
+        Self::Slot,
+        Self::Transaction,
+        Self::TransactionStatus,
+        Self::Block,
+        Self::BlockMeta,
+        Self::Entry,
+        Self::Ping,
+        Self::Pong,
+    ];
+
+    pub fn of(update: &SubscribeUpdate) -> Option<Self> {
+        Some(match update.update_oneof.as_ref()? {
+            UpdateOneof::Account(_) => Self::Account,
+            UpdateOneof::Slot(_) => Self::Slot,
+            UpdateOneof::Transaction(_) => Self::Transaction,
+            UpdateOneof::TransactionStatus(_) => Self::TransactionStatus,
+            UpdateOneof::Block(_) => Self::Block,
+            UpdateOneof::BlockMeta(_) => Self::BlockMeta,
+            UpdateOneof::Entry(_) => Self::Entry,
+            UpdateOneof::Ping(_) => Self::Ping,
+            UpdateOneof::Pong(_) => Self::Pong,
+        })
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Account => "account",
+            Self::Slot => "slot",
+            Self::Transaction => "transaction",
+            Self::TransactionStatus => "transaction_status",
+            Self::Block => "block",
+            Self::BlockMeta => "block_meta",
+            Self::Entry => "entry",
+            Self::Ping => "ping",
+            Self::Pong => "pong",
+        }
+    }
+}
+
+/// Histogram of `u64` values with log-scaled buckets.
+///
+/// Percentiles are reported as the upper bound of their bucket, at most
+/// 12.5% above the exact value.
+#[derive(Debug, Clone)]
+pub struct Histogram {
+    buckets: Box<[u64; BUCKETS]>,
+    count: u64,
+    sum: u128,
+    min: u64,
+    max: u64,
+}
+
+impl Default for Histogram {
+    fn default() -> Self {
+        Self {
+            buckets: Box::new([0; BUCKETS]),
+            count: 0,
+            sum: 0,
+            min: u64::MAX,
+            max: 0,
+        }
+    }
+}
+
+impl Histogram {
+    pub fn record(&mut self, value: u64) {
+        self.buckets[bucket(value)] += 1;
+        self.count += 1;
+        self.sum += u128::from(value);
+        self.min = self.min.min(value);
+        self.max = self.max.max(value);
+    }
+
+    pub fn count(&self) -> u64 {
+        self.count
+    }
+
+    pub fn min(&self) -> Option<u64> {
+        (self.count > 0).then_some(self.min)
+    }
+
+    pub fn max(&self) -> Option<u64> {
+        (self.count > 0).then_some(self.max)
+    }
+
+    pub fn mean(&self) -> Option<f64> {
+        (self.count > 0).then(|| self.sum as f64 / self.count as f64)
+    }
+
+    /// Value below which `quantile` of the recorded values fall, `quantile` in `0.0..=1.0`.
+    pub fn percentile(&self, quantile: f64) -> Option<u64> {
+        if self.count == 0 {
+            return None;
+        }
+
+        let rank = ((quantile.clamp(0.0, 1.0) * self.count as f64).ceil() as u64).max(1);
+        let mut seen = 0;
+        for (index, count) in self.buckets.iter().enumerate() {
+            seen += count;
+            if seen >= rank {
+                return Some(bucket_upper_bound(index).clamp(self.min, self.max));
+            }
+        }
+        Some(self.max)
+    }
+}
+
+fn bucket(value: u64) -> usize {
+    if value < LINEAR_BUCKETS {
+        return value as usize;
+    }
+    let exponent = u64::from(63 - value.leading_zeros());
+    let sub_bucket = (value >> (exponent - 3)) & (SUB_BUCKETS - 1);
+    (LINEAR_BUCKETS + (exponent - 4) * SUB_BUCKETS + sub_bucket) as usize
+}
+
+fn bucket_upper_bound(index: usize) -> u64 {
+    let index = index as u64;
+    if index < LINEAR_BUCKETS {
+        return index;
+    }
+    let exponent = (index - LINEAR_BUCKETS) / SUB_BUCKETS + 4;
+    let sub_bucket = (index - LINEAR_BUCKETS) % SUB_BUCKETS;
+    let lower = (SUB_BUCKETS + sub_bucket) << (exponent - 3);
+    lower.saturating_add((1 << (exponent - 3)) - 1)
+}
+
+/// An update with the local time it was received at.
+#[derive(Debug, Clone)]
+pub struct TimestampedUpdate {
+    pub update: SubscribeUpdate,
+    pub received_at: SystemTime,
+}
+
+impl TimestampedUpdate {
+    pub fn new(update: SubscribeUpdate) -> Self {
+        Self {
+            update,
+            received_at: SystemTime::now(),
+        }
+    }
+
+    /// Time between the server creating the update and receiving it, zero if
+    /// the server clock is ahead.
+    pub fn latency(&self) -> Option<Duration> {
+        let created_at = created_at(&self.update)?;
+        Some(
+            self.received_at
+                .duration_since(created_at)
+                .unwrap_or_default(),
+        )
+    }
+}
+
+/// `created_at` of the update as a `SystemTime`.
+pub fn created_at(update: &SubscribeUpdate) -> Option<SystemTime> {
+    let created_at = update.created_at.as_ref()?;
+    let since_epoch = Duration::new(
+        u64::try_from(created_at.seconds).ok()?,
+        u32::try_from(created_at.nanos).ok()?,
+    );
+    SystemTime::UNIX_EPOCH.checked_add(since_epoch)
+}
+
+#[derive(Debug, Default)]
+struct LatencyState {
+    latency: HashMap<UpdateKind, Histogram>,
+    slot_lag: HashMap<UpdateKind, Histogram>,
+    tip: Option<u64>,
+}
+
+/// Per update type latency and slot lag of a subscription.
+///
+/// Latency is receive time minus `created_at` in microseconds, it is only
+/// meaningful with synchronized clocks. Slot lag is how many slots an update
+/// trails the highest slot known, from the stream itself or [`Self::set_tip`].
+#[derive(Debug, Clone, Default)]
+pub struct LatencyStats {
+    state: Arc<Mutex<LatencyState>>,
+}
+
+impl LatencyStats {
+    pub fn record(&self, update: &TimestampedUpdate) {
+        let Some(kind) = UpdateKind::of(&update.update) else {
+            return;
+        };
+        let mut state = self.state.lock().unwrap();
+
+        if let Some(latency) = update.latency() {
+            let micros = u64::try_from(latency.as_micros()).unwrap_or(u64::MAX);
+            state.latency.entry(kind).or_default().record(micros);
+        }
+        if let Some(slot) = update.update.slot() {
+            let tip = state.tip.map_or(slot, |tip| tip.max(slot));
+            state.tip = Some(tip);
+            state.slot_lag.entry(kind).or_default().record(tip - slot);
+        }
+    }
+
+    /// Raises the known tip, e.g. from `get_slot` or another provider.
+    pub fn set_tip(&self, slot: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.tip = Some(state.tip.map_or(slot, |tip| tip.max(slot)));
+    }
+
+    pub fn tip(&self) -> Option<u64> {
+        self.state.lock().unwrap().tip
+    }
+
+    /// Latency histogram of `kind` in microseconds.
+    pub fn latency(&self, kind: UpdateKind) -> Histogram {
+        let state = self.state.lock().unwrap();
+        state.latency.get(&kind).cloned().unwrap_or_default()
+    }
+
+    /// Slot lag histogram of `kind` in slots.
+    pub fn slot_lag(&self, kind: UpdateKind) -> Histogram {
+        let state = self.state.lock().unwrap();
+        state.slot_lag.get(&kind).cloned().unwrap_or_default()
+    }
+
+    /// Clears the histograms, the tip is kept.
+    pub fn reset(&self) {
+        let mut state = self.state.lock().unwrap();
+        state.latency.clear();
+        state.slot_lag.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::geyser::SubscribeUpdateSlot;
+
+    fn slot_update(slot: u64) -> TimestampedUpdate {
+        TimestampedUpdate::new(SubscribeUpdate {
+            update_oneof: Some(UpdateOneof::Slot(SubscribeUpdateSlot {
+                slot,
+                ..Default::default()
+            })),
+            ..Default::default()
+        })
+    }
+
+    #[test]
+    fn counts_small_values_exactly() {
+        for value in 0..LINEAR_BUCKETS {
+            assert_eq!(bucket(value), value as usize);
+            assert_eq!(bucket_upper_bound(bucket(value)), value);
+        }
+    }
+
+    #[test]
+    fn buckets_are_contiguous() {
+        assert_eq!(bucket(16), 16);
+        assert_eq!(bucket(17), 16);
+        assert_eq!(bucket(18), 17);
+        assert_eq!(bucket(31), 23);
+        assert_eq!(bucket(32), 24);
+        assert_eq!(bucket(u64::MAX), BUCKETS - 1);
+        assert_eq!(bucket_upper_bound(BUCKETS - 1), u64::MAX);
+
+        for index in LINEAR_BUCKETS as usize..BUCKETS - 1 {
+            let upper = bucket_upper_bound(index);
+            assert_eq!(bucket(upper), index);
+            assert_eq!(bucket(upper + 1), index + 1);
+        }
+    }
+
+    #[test]
+    fn bounds_relative_error() {
+        for value in [16, 100, 1_000, 12_345, 1 << 40, u64::MAX / 3] {
+            let upper = bucket_upper_bound(bucket(value));
+            assert!(upper >= value);
+            assert!((upper - value) as f64 <= value as f64 / SUB_BUCKETS as f64);
+        }
+    }
+
+    #[test]
+    fn reports_percentiles() {
+        let mut histogram = Histogram::default();
+        assert_eq!(histogram.percentile(0.5), None);
+        assert_eq!(histogram.mean(), None);
+
+        for value in 1..=10 {
+            histogram.record(value);
+        }
+        assert_eq!(histogram.count(), 10);
+        assert_eq!(histogram.min(), Some(1));
+        assert_eq!(histogram.max(), Some(10));
+        assert_eq!(histogram.mean(), Some(5.5));
+        assert_eq!(histogram.percentile(0.0), Some(1));
+        assert_eq!(histogram.percentile(0.5), Some(5));
+        assert_eq!(histogram.percentile(0.9), Some(9));
+        assert_eq!(histogram.percentile(1.0), Some(10));
+        assert_eq!(histogram.percentile(2.0), Some(10));
+    }
+
+    #[test]
+    fn clamps_percentiles_to_recorded_range() {
+        let mut histogram = Histogram::default();
+        histogram.record(1_000);
+        histogram.record(1_001);
+
+        // Both fall in the 960..=1023 bucket.
+        assert_eq!(histogram.percentile(0.5), Some(1_001));
+        assert_eq!(histogram.percentile(0.0), Some(1_001));
+
+        histogram.record(5_000);
+        assert_eq!(histogram.percentile(0.5), Some(1_023));
+        assert_eq!(histogram.percentile(0.99), Some(5_000));
+    }
+
+    #[test]
+    fn records_slot_lag_behind_tip() {
+        let stats = LatencyStats::default();
+        stats.record(&slot_update(10));
+        stats.record(&slot_update(12));
+        stats.record(&slot_update(11));
+        stats.set_tip(20);
+        stats.record(&slot_update(15));
+
+        assert_eq!(stats.tip(), Some(20));
+        let lag = stats.slot_lag(UpdateKind::Slot);
+        assert_eq!(lag.count(), 4);
+        assert_eq!([lag.min(), lag.max()], [Some(0), Some(5)]);
+        assert_eq!(stats.latency(UpdateKind::Slot).count(), 0);
+
+        stats.reset();
+        assert_eq!(stats.slot_lag(UpdateKind::Slot).count(), 0);
+        assert_eq!(stats.tip(), Some(20));
+    }
+
+    #[test]
+    fn measures_latency_from_created_at() {
+        let mut update = slot_update(1);
+        let created_at = update.received_at - Duration::from_millis(250);
+        let since_epoch = created_at.duration_since(SystemTime::UNIX_EPOCH).unwrap();
+        update.update.created_at = Some(prost_types::Timestamp {
+            seconds: since_epoch.as_secs() as i64,
+            nanos: since_epoch.subsec_nanos() as i32,
+        });
+
+        assert_eq!(update.latency(), Some(Duration::from_millis(250)));
+
+        let stats = LatencyStats::default();
+        stats.record(&update);
+        assert_eq!(stats.latency(UpdateKind::Slot).max(), Some(250_000));
+    }
+}
@@ -1,32 +1,70 @@
-use futures::stream;
-use std::error::Error;
+use futures::{channel::mpsc, stream, Stream};
+use grpc_transport::auth::{AuthInterceptor, AuthProvider};
+use std::sync::Arc;
 use tonic::{
     codec::CompressionEncoding,
-    metadata::{AsciiMetadataValue, MetadataValue},
+    metadata::{AsciiMetadataValue, MetadataMap, MetadataValue},
     service::{interceptor::InterceptedService, Interceptor},
-    transport::{Channel, ClientTlsConfig},
+    transport::{Channel, Endpoint},
     Request, Status, Streaming,
 };
 
+use crate::error::GeyserClientError;
+use crate::keepalive::{KeepaliveConfig, KeepaliveStream};
 use crate::proto::geyser::{
     CommitmentLevel, GetBlockHeightRequest, GetBlockHeightResponse, GetLatestBlockhashRequest,
     GetLatestBlockhashResponse, GetSlotRequest, GetSlotResponse, GetVersionRequest,
     GetVersionResponse, IsBlockhashValidRequest, IsBlockhashValidResponse, PingRequest,
-    PongResponse, SubscribeRequest, SubscribeUpdate,
+    PongResponse, SubscribeReplayInfoRequest, SubscribeReplayInfoResponse, SubscribeRequest,
+    SubscribeUpdate,
 };
 
+pub mod account_cache;
+pub mod block_assembler;
+pub mod commitment;
+#[cfg(feature = "sdk")]
+pub mod convert;
+pub mod error;
+pub mod failover;
+pub mod filter;
+#[cfg(feature = "instructions")]
+pub mod instructions;
+pub mod keepalive;
+pub mod latency;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+#[cfg(feature = "mock")]
+pub mod mock;
 pub mod proto;
+pub mod racing;
+pub mod reconnect;
+#[cfg(feature = "recording")]
+pub mod recording;
+pub mod request_builder;
+pub mod slot_tracker;
+pub mod spl;
+#[cfg(feature = "token")]
+pub mod token;
 
-impl Interceptor for InterceptorXToken {
-    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+#[cfg(feature = "tracing")]
+pub use grpc_transport::logging;
+pub use grpc_transport::{auth, tls::TlsConfig, TransportConfig, TransportConfigBuilder};
+
+impl AuthProvider for InterceptorXToken {
+    fn apply(&self, metadata: &mut MetadataMap) -> Result<(), Status> {
         if let Some(x_token) = self.x_token.clone() {
-            request.metadata_mut().insert("x-token", x_token);
+            metadata.insert("x-token", x_token);
         }
         if self.x_request_snapshot {
-            request
-                .metadata_mut()
-                .insert("x-request-snapshot", MetadataValue::from_static("true"));
+            metadata.insert("x-request-snapshot", MetadataValue::from_static("true"));
         }
+        Ok(())
+    }
+}
+
+impl Interceptor for InterceptorXToken {
+    fn call(&mut self, mut request: Request<()>) -> Result<Request<()>, Status> {
+        self.apply(request.metadata_mut())?;
         Ok(request)
     }
 }
@@ -41,40 +79,139 @@ pub struct InterceptorXToken {
 pub struct GeyserClientConfig {
     pub x_token: Option<AsciiMetadataValue>,
     pub x_request_snapshot: bool,
+    /// Extra providers run on every request after `x_token` is applied.
+    pub auth: Vec<Arc<dyn AuthProvider>>,
     pub send_compressed: Option<CompressionEncoding>,
     pub accept_compressed: Option<CompressionEncoding>,
     pub max_decoding_message_size: Option<usize>,
     pub max_encoding_message_size: Option<usize>,
+    pub keepalive: KeepaliveConfig,
+    pub transport: TransportConfig,
+    pub tls: TlsConfig,
+    /// Instruments the streams opened by the client.
+    pub metrics: Option<MetricsHandle>,
+}
+
+/// Metrics for [`GeyserClientConfig::metrics`], created from a
+/// [`crate::metrics::GeyserMetrics`] with the `metrics` feature.
+///
+/// The field exists either way so configs can be built the same with and
+/// without the feature. Without it no handle can be created.
+#[derive(Debug, Clone)]
+pub struct MetricsHandle {
+    #[cfg(feature = "metrics")]
+    metrics: crate::metrics::GeyserMetrics,
+    #[cfg(not(feature = "metrics"))]
+    _never: std::convert::Infallible,
+}
+
+#[cfg(feature = "metrics")]
+impl From<crate::metrics::GeyserMetrics> for MetricsHandle {
+    fn from(metrics: crate::metrics::GeyserMetrics) -> Self {
+        Self { metrics }
+    }
+}
+
+/// Handle for pushing new `SubscribeRequest`s over an open `Subscribe` stream.
+///
+/// Each request replaces the filters of the subscription. Dropping every clone
+/// of the sender closes the request side of the stream.
+#[derive(Debug, Clone)]
+pub struct SubscribeRequestSender {
+    sender: mpsc::UnboundedSender<SubscribeRequest>,
+}
+
+impl SubscribeRequestSender {
+    pub fn send(&self, request: SubscribeRequest) -> Result<(), GeyserClientError> {
+        self.sender
+            .unbounded_send(request)
+            .map_err(|_| GeyserClientError::SubscriptionClosed)
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.sender.is_closed()
+    }
+}
+
+/// Outcome of clamping a requested `from_slot` to the server's replay history.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FromSlotReplay {
+    /// Slot to use as `SubscribeRequest.from_slot`.
+    pub from_slot: u64,
+    /// Number of requested slots older than the first replayable slot.
+    pub unrecoverable_slots: u64,
 }
 
 #[derive(Clone)]
 pub struct GeyserClient {
     client: crate::proto::geyser::geyser_client::GeyserClient<
-        InterceptedService<Channel, InterceptorXToken>,
+        InterceptedService<Channel, AuthInterceptor>,
     >,
+    endpoint: Arc<str>,
+    keepalive: KeepaliveConfig,
+    #[cfg(feature = "metrics")]
+    metrics: Option<crate::metrics::GeyserMetrics>,
 }
 
 impl GeyserClient {
+    /// Creates a client whose channel connects on first use.
     pub fn new(
         endpoint_url: impl AsRef<str>,
         config: Option<GeyserClientConfig>,
-    ) -> Result<Self, Box<dyn Error>> {
+    ) -> Result<Self, GeyserClientError> {
         let config = config.unwrap_or_default();
+        let channel = Self::endpoint(&endpoint_url, &config)?.connect_lazy();
 
-        let tls_config = ClientTlsConfig::new()
-            .with_native_roots()
-            .with_webpki_roots();
-        let channel = Channel::from_shared(endpoint_url.as_ref().to_string())
-            .unwrap()
-            .tls_config(tls_config)?
-            .connect_lazy();
+        Ok(Self::with_channel(channel, endpoint_url, config))
+    }
+
+    /// Creates a client and connects right away, failing if the endpoint is unreachable.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "geyser_connect",
+            skip_all,
+            fields(endpoint = endpoint_url.as_ref()),
+            err
+        )
+    )]
+    pub async fn connect(
+        endpoint_url: impl AsRef<str>,
+        config: Option<GeyserClientConfig>,
+    ) -> Result<Self, GeyserClientError> {
+        let config = config.unwrap_or_default();
+        let channel = Self::endpoint(&endpoint_url, &config)?
+            .connect()
+            .await
+            .map_err(GeyserClientError::Transport)?;
+
+        Ok(Self::with_channel(channel, endpoint_url, config))
+    }
+
+    fn endpoint(
+        endpoint_url: &impl AsRef<str>,
+        config: &GeyserClientConfig,
+    ) -> Result<Endpoint, GeyserClientError> {
+        let endpoint = Channel::from_shared(endpoint_url.as_ref().to_string())?;
+        let endpoint = config.tls.apply(endpoint).map_err(GeyserClientError::Tls)?;
+
+        Ok(config.transport.apply(endpoint))
+    }
+
+    fn with_channel(
+        channel: Channel,
+        endpoint_url: impl AsRef<str>,
+        config: GeyserClientConfig,
+    ) -> Self {
+        let mut providers: Vec<Arc<dyn AuthProvider>> = vec![Arc::new(InterceptorXToken {
+            x_token: config.x_token,
+            x_request_snapshot: config.x_request_snapshot,
+        })];
+        providers.extend(config.auth);
 
         let mut client = crate::proto::geyser::geyser_client::GeyserClient::with_interceptor(
             channel,
-            InterceptorXToken {
-                x_token: config.x_token,
-                x_request_snapshot: config.x_request_snapshot,
-            },
+            AuthInterceptor::new(providers),
         );
 
         if let Some(encoding) = config.send_compressed {
@@ -90,25 +227,157 @@ impl GeyserClient {
             client = client.max_encoding_message_size(limit);
         }
 
-        Ok(Self { client })
+        Self {
+            client,
+            endpoint: endpoint_url.as_ref().into(),
+            keepalive: config.keepalive,
+            #[cfg(feature = "metrics")]
+            metrics: config.metrics.map(|handle| handle.metrics),
+        }
     }
 
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "geyser_subscribe",
+            skip_all,
+            fields(
+                endpoint = %self.endpoint,
+                filters = ?request.filter_names(),
+                from_slot = request.from_slot,
+            ),
+            err
+        )
+    )]
     pub async fn subscribe(
         &mut self,
         request: SubscribeRequest,
-    ) -> Result<Streaming<SubscribeUpdate>, Box<dyn Error>> {
+    ) -> Result<Streaming<SubscribeUpdate>, GeyserClientError> {
         let request = Request::new(stream::once(async move { request }));
 
         let response = self
             .client
             .subscribe(request)
             .await
-            .map_err(|e| Box::new(e))?;
+            .inspect_err(|status| self.observe_error(status))?;
+
+        Ok(response.into_inner())
+    }
+
+    /// Opens a `Subscribe` stream that stays open for filter updates.
+    ///
+    /// Unlike [`GeyserClient::subscribe`], the request side of the stream is kept
+    /// alive until the returned [`SubscribeRequestSender`] is dropped, so new
+    /// `SubscribeRequest`s can be sent without reconnecting.
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "geyser_subscribe",
+            skip_all,
+            fields(
+                endpoint = %self.endpoint,
+                filters = ?request.filter_names(),
+                from_slot = request.from_slot,
+            ),
+            err
+        )
+    )]
+    pub async fn subscribe_with_sender(
+        &mut self,
+        request: SubscribeRequest,
+    ) -> Result<(SubscribeRequestSender, Streaming<SubscribeUpdate>), GeyserClientError> {
+        let (sender, receiver) = mpsc::unbounded();
+        let sender = SubscribeRequestSender { sender };
+        sender.send(request)?;
+
+        let response = self
+            .client
+            .subscribe(Request::new(receiver))
+            .await
+            .inspect_err(|status| self.observe_error(status))?;
+
+        Ok((sender, response.into_inner()))
+    }
+
+    /// Opens a `Subscribe` stream that answers server pings and sends periodic
+    /// client pings according to the client's [`KeepaliveConfig`].
+    pub async fn subscribe_with_keepalive(
+        &mut self,
+        request: SubscribeRequest,
+    ) -> Result<KeepaliveStream, GeyserClientError> {
+        #[cfg(feature = "tracing")]
+        let span = self.stream_span(&request);
+        let (sender, stream) = self.subscribe_with_sender(request).await?;
+
+        let stream = KeepaliveStream::new(sender, stream, self.keepalive.clone());
+        #[cfg(feature = "metrics")]
+        let stream = stream.with_metrics(self.metrics.clone());
+        #[cfg(feature = "tracing")]
+        let stream = stream.with_span(span);
+
+        Ok(stream)
+    }
+
+    /// The URL the client was created with.
+    pub fn endpoint_url(&self) -> &str {
+        &self.endpoint
+    }
+
+    pub fn keepalive_config(&self) -> &KeepaliveConfig {
+        &self.keepalive
+    }
+
+    #[cfg(feature = "metrics")]
+    pub fn metrics(&self) -> Option<&crate::metrics::GeyserMetrics> {
+        self.metrics.as_ref()
+    }
+
+    /// Span for the events of a stream opened with `request`.
+    #[cfg(feature = "tracing")]
+    pub(crate) fn stream_span(&self, request: &SubscribeRequest) -> tracing::Span {
+        tracing::info_span!(
+            "geyser_stream",
+            endpoint = %self.endpoint,
+            filters = ?request.filter_names(),
+        )
+    }
+
+    #[cfg_attr(not(feature = "metrics"), allow(unused_variables))]
+    fn observe_error(&self, status: &Status) {
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = self.metrics.as_ref() {
+            metrics.observe_error(status.code());
+        }
+    }
+
+    pub async fn subscribe_replay_info(
+        &mut self,
+    ) -> Result<SubscribeReplayInfoResponse, GeyserClientError> {
+        let request = tonic::Request::new(SubscribeReplayInfoRequest {});
+        let response = self.client.subscribe_replay_info(request).await?;
 
         Ok(response.into_inner())
     }
 
-    pub async fn ping(&mut self, count: i32) -> Result<PongResponse, Box<dyn Error>> {
+    /// Clamps `from_slot` to the oldest slot the server can replay.
+    ///
+    /// Returns `None` if the server keeps no replay history, in which case
+    /// `from_slot` must not be set on the request.
+    pub async fn clamp_from_slot(
+        &mut self,
+        from_slot: u64,
+    ) -> Result<Option<FromSlotReplay>, GeyserClientError> {
+        let response = self.subscribe_replay_info().await?;
+
+        Ok(response
+            .first_available
+            .map(|first_available| FromSlotReplay {
+                from_slot: from_slot.max(first_available),
+                unrecoverable_slots: first_available.saturating_sub(from_slot),
+            }))
+    }
+
+    pub async fn ping(&mut self, count: i32) -> Result<PongResponse, GeyserClientError> {
         let message = PingRequest { count };
         let request = tonic::Request::new(message);
         let response = self.client.ping(request).await?;
@@ -119,7 +388,7 @@ impl GeyserClient {
     pub async fn get_latest_blockhash(
         &mut self,
         commitment: Option<CommitmentLevel>,
-    ) -> Result<GetLatestBlockhashResponse, Box<dyn Error>> {
+    ) -> Result<GetLatestBlockhashResponse, GeyserClientError> {
         let request = tonic::Request::new(GetLatestBlockhashRequest {
             commitment: commitment.map(|value| value as i32),
         });
@@ -131,7 +400,7 @@ impl GeyserClient {
     pub async fn get_block_height(
         &mut self,
         commitment: Option<CommitmentLevel>,
-    ) -> Result<GetBlockHeightResponse, Box<dyn Error>> {
+    ) -> Result<GetBlockHeightResponse, GeyserClientError> {
         let request = tonic::Request::new(GetBlockHeightRequest {
             commitment: commitment.map(|value| value as i32),
         });
@@ -143,7 +412,7 @@ impl GeyserClient {
     pub async fn get_slot(
         &mut self,
         commitment: Option<CommitmentLevel>,
-    ) -> Result<GetSlotResponse, Box<dyn Error>> {
+    ) -> Result<GetSlotResponse, GeyserClientError> {
         let request = tonic::Request::new(GetSlotRequest {
             commitment: commitment.map(|value| value as i32),
         });
@@ -156,7 +425,7 @@ impl GeyserClient {
         &mut self,
         blockhash: String,
         commitment: Option<CommitmentLevel>,
-    ) -> Result<IsBlockhashValidResponse, Box<dyn Error>> {
+    ) -> Result<IsBlockhashValidResponse, GeyserClientError> {
         let request = tonic::Request::new(IsBlockhashValidRequest {
             blockhash,
             commitment: commitment.map(|value| value as i32),
@@ -166,10 +435,37 @@ impl GeyserClient {
         Ok(response.into_inner())
     }
 
-    pub async fn get_version(&mut self) -> Result<GetVersionResponse, Box<dyn Error>> {
+    pub async fn get_version(&mut self) -> Result<GetVersionResponse, GeyserClientError> {
         let request = tonic::Request::new(GetVersionRequest {});
         let response = self.client.get_version(request).await?;
 
         Ok(response.into_inner())
     }
 }
+
+/// Opens `Subscribe` streams, implemented by [`GeyserClient`] and, with the
+/// `recording` feature, by [`recording::ReplayClient`].
+///
+/// Code written against the trait runs the same on a live server and on a
+/// recording.
+#[tonic::async_trait]
+pub trait Subscribe: Send {
+    type Stream: Stream<Item = Result<SubscribeUpdate, Status>> + Send + Unpin + 'static;
+
+    async fn subscribe(
+        &mut self,
+        request: SubscribeRequest,
+    ) -> Result<Self::Stream, GeyserClientError>;
+}
+
+#[tonic::async_trait]
+impl Subscribe for GeyserClient {
+    type Stream = Streaming<SubscribeUpdate>;
+
+    async fn subscribe(
+        &mut self,
+        request: SubscribeRequest,
+    ) -> Result<Self::Stream, GeyserClientError> {
+        GeyserClient::subscribe(self, request).await
+    }
+}
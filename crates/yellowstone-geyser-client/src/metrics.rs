@@ -0,0 +1,135 @@
+use std::{collections::HashMap, time::Duration};
+
+use grpc_transport::metrics::{
+    code_label,
+    prometheus::{
+        exponential_buckets, Histogram, HistogramOpts, IntCounter, IntCounterVec, IntGauge, Opts,
+        Registry, Result,
+    },
+};
+use prost::Message;
+use tonic::Code;
+
+use crate::{latency::UpdateKind, proto::geyser::SubscribeUpdate};
+
+pub use grpc_transport::metrics::{encode, prometheus, MetricsServer};
+
+/// Prometheus metrics of `Subscribe` streams.
+///
+/// Clones share the same metrics. Streams opened with
+/// [`crate::GeyserClient::subscribe_with_keepalive`] and
+/// [`crate::reconnect::ResilientSubscription`] are instrumented, a plain
+/// [`crate::GeyserClient::subscribe`] stream only counts failures to open it.
+/// An update matching several filters is counted once for each of them. The
+/// queue depth is reported by a [`crate::commitment::CommitmentStream`]
+/// given the metrics with [`crate::commitment::CommitmentStream::with_metrics`].
+#[derive(Debug, Clone)]
+pub struct GeyserMetrics {
+    updates: IntCounterVec,
+    update_bytes: IntCounterVec,
+    reconnects: IntCounter,
+    stream_errors: IntCounterVec,
+    ping_rtt: Histogram,
+    queue_depth: IntGauge,
+}
+
+impl GeyserMetrics {
+    /// Creates the metrics and registers them with `registry`.
+    pub fn register(registry: &Registry) -> Result<Self> {
+        Self::register_with_labels(registry, HashMap::new())
+    }
+
+    /// Like [`Self::register`], with `labels` set on every metric, e.g. to tell
+    /// several endpoints apart in one registry.
+    pub fn register_with_labels(
+        registry: &Registry,
+        labels: HashMap<String, String>,
+    ) -> Result<Self> {
+        let opts = |name: &str, help: &str| Opts::new(name, help).const_labels(labels.clone());
+
+        let metrics = Self {
+            updates: IntCounterVec::new(
+                opts(
+                    "geyser_updates_total",
+                    "Updates received, by type and filter name.",
+                ),
+                &["kind", "filter"],
+            )?,
+            update_bytes: IntCounterVec::new(
+                opts(
+                    "geyser_update_bytes_total",
+                    "Encoded size of the updates received, by type.",
+                ),
+                &["kind"],
+            )?,
+            reconnects: IntCounter::with_opts(opts(
+                "geyser_reconnects_total",
+                "Subscriptions re-established after the first connect.",
+            ))?,
+            stream_errors: IntCounterVec::new(
+                opts(
+                    "geyser_stream_errors_total",
+                    "Failures to open or read a stream, by gRPC code.",
+                ),
+                &["code"],
+            )?,
+            ping_rtt: Histogram::with_opts(
+                HistogramOpts::from(opts(
+                    "geyser_ping_rtt_seconds",
+                    "Round-trip time of keepalive pings.",
+                ))
+                .buckets(exponential_buckets(0.001, 2.0, 14)?),
+            )?,
+            queue_depth: IntGauge::with_opts(opts(
+                "geyser_queue_depth",
+                "Updates held back until their slot reaches the commitment.",
+            ))?,
+        };
+
+        registry.register(Box::new(metrics.updates.clone()))?;
+        registry.register(Box::new(metrics.update_bytes.clone()))?;
+        registry.register(Box::new(metrics.reconnects.clone()))?;
+        registry.register(Box::new(metrics.stream_errors.clone()))?;
+        registry.register(Box::new(metrics.ping_rtt.clone()))?;
+        registry.register(Box::new(metrics.queue_depth.clone()))?;
+
+        Ok(metrics)
+    }
+
+    pub(crate) fn observe_update(&self, update: &SubscribeUpdate) {
+        let Some(kind) = UpdateKind::of(update) else {
+            return;
+        };
+
+        for filter in update.filters.iter() {
+            self.updates
+                .with_label_values(&[kind.as_str(), filter.as_str()])
+                .inc();
+        }
+        if update.filters.is_empty() {
+            self.updates.with_label_values(&[kind.as_str(), ""]).inc();
+        }
+        self.update_bytes
+            .with_label_values(&[kind.as_str()])
+            .inc_by(update.encoded_len() as u64);
+    }
+
+    pub(crate) fn observe_reconnect(&self) {
+        self.reconnects.inc();
+    }
+
+    pub(crate) fn observe_error(&self, code: Code) {
+        self.stream_errors
+            .with_label_values(&[code_label(code).as_str()])
+            .inc();
+    }
+
+    pub(crate) fn observe_ping_rtt(&self, rtt: Duration) {
+        self.ping_rtt.observe(rtt.as_secs_f64());
+    }
+
+    pub(crate) fn observe_queue_depth(&self, depth: usize) {
+        self.queue_depth
+            .set(i64::try_from(depth).unwrap_or(i64::MAX));
+    }
+}
@@ -0,0 +1,572 @@
+use futures::{
+    channel::{mpsc, oneshot},
+    Stream, StreamExt,
+};
+use http_body::Frame;
+use prost::{bytes::BufMut, Message};
+use std::{
+    collections::{HashMap, VecDeque},
+    convert::Infallible,
+    io,
+    net::SocketAddr,
+    pin::Pin,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    task::{ready, Context, Poll},
+    time::Duration,
+};
+use tokio::task::JoinHandle;
+use tonic::{
+    body::Body,
+    codec::{Codec, EncodeBuf, Encoder, ProstCodec},
+    codegen::{http, BoxFuture, Bytes, Service, StdError},
+    server::{Grpc, NamedService, StreamingService},
+    transport::{server::TcpIncoming, Server},
+    Code, Request, Response, Status, Streaming,
+};
+
+use crate::proto::geyser::{
+    geyser_server::{Geyser, GeyserServer, SERVICE_NAME},
+    subscribe_update::UpdateOneof,
+    GetBlockHeightRequest, GetBlockHeightResponse, GetLatestBlockhashRequest,
+    GetLatestBlockhashResponse, GetSlotRequest, GetSlotResponse, GetVersionRequest,
+    GetVersionResponse, IsBlockhashValidRequest, IsBlockhashValidResponse, PingRequest,
+    PongResponse, SubscribeReplayInfoRequest, SubscribeReplayInfoResponse, SubscribeRequest,
+    SubscribeUpdate, SubscribeUpdatePong,
+};
+
+const SUBSCRIBE_PATH: &str = "/geyser.Geyser/Subscribe";
+
+/// One step of a scripted `Subscribe` stream.
+#[derive(Debug, Clone)]
+#[allow(clippy::large_enum_variant)]
+pub enum MockEvent {
+    Update(SubscribeUpdate),
+    /// Sends bytes that do not decode as a `SubscribeUpdate` as one message.
+    Malformed(Vec<u8>),
+    Delay(Duration),
+    /// Ends the stream with this status.
+    Status(Code, String),
+    /// Ends the stream with an `OK` status.
+    End,
+    /// Resets the HTTP/2 stream, so the client sees no status at all.
+    ///
+    /// Updates still buffered on the server are dropped with the stream,
+    /// precede it with a delay to get them delivered first.
+    Reset,
+}
+
+impl From<SubscribeUpdate> for MockEvent {
+    fn from(update: SubscribeUpdate) -> Self {
+        Self::Update(update)
+    }
+}
+
+/// Events replaying a recording, with the gaps between updates as delays.
+#[cfg(feature = "recording")]
+pub fn recorded_events(path: impl AsRef<std::path::Path>) -> io::Result<Vec<MockEvent>> {
+    let mut events = Vec::new();
+    let mut last_received_at = None;
+    for record in crate::recording::RecordReader::<SubscribeUpdate>::open(path)? {
+        let record = record?;
+        if let Some(gap) = last_received_at
+            .and_then(|last| record.received_at.duration_since(last).ok())
+            .filter(|gap| !gap.is_zero())
+        {
+            events.push(MockEvent::Delay(gap));
+        }
+        last_received_at = Some(record.received_at);
+        events.push(MockEvent::Update(record.message));
+    }
+
+    Ok(events)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum MockRpc {
+    Subscribe,
+    SubscribeReplayInfo,
+    Ping,
+    GetLatestBlockhash,
+    GetBlockHeight,
+    GetSlot,
+    IsBlockhashValid,
+    GetVersion,
+}
+
+#[derive(Debug, Default)]
+struct MockState {
+    subscriptions: VecDeque<Vec<MockEvent>>,
+    failures: HashMap<MockRpc, VecDeque<Status>>,
+    requests: Vec<SubscribeRequest>,
+    subscribes: u64,
+    slot: u64,
+    block_height: u64,
+    blockhash: String,
+    last_valid_block_height: u64,
+    version: String,
+    first_available: Option<u64>,
+}
+
+/// An in-process Geyser server answering from scripted state.
+///
+/// Every `Subscribe` call plays the next script queued with
+/// [`Self::push_subscription`], and stays open without updates once no
+/// script is left. Client pings are answered with pongs and every other
+/// `SubscribeRequest` is kept for [`Self::requests`]. The unary RPCs answer
+/// from the values set on the mock. Clones share the same state.
+///
+/// Malformed messages and resets need the server started with
+/// [`Self::serve`]. Mounted as a plain [`GeyserServer`], a malformed message
+/// fails the stream with `Internal` and a reset ends it normally.
+#[derive(Debug, Clone, Default)]
+pub struct MockGeyser {
+    state: Arc<Mutex<MockState>>,
+}
+
+impl MockGeyser {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues the events of a future `Subscribe` stream.
+    pub fn push_subscription(&self, events: impl IntoIterator<Item = MockEvent>) {
+        let mut state = self.state.lock().unwrap();
+        state.subscriptions.push_back(events.into_iter().collect());
+    }
+
+    /// Fails the next call of `rpc` with `status` instead of answering it.
+    pub fn fail_next(&self, rpc: MockRpc, status: Status) {
+        let mut state = self.state.lock().unwrap();
+        state.failures.entry(rpc).or_default().push_back(status);
+    }
+
+    pub fn set_slot(&self, slot: u64) {
+        self.state.lock().unwrap().slot = slot;
+    }
+
+    pub fn set_block_height(&self, block_height: u64) {
+        self.state.lock().unwrap().block_height = block_height;
+    }
+
+    /// Sets the latest blockhash, the only one `IsBlockhashValid` accepts.
+    pub fn set_latest_blockhash(&self, blockhash: impl Into<String>, last_valid_block_height: u64) {
+        let mut state = self.state.lock().unwrap();
+        state.blockhash = blockhash.into();
+        state.last_valid_block_height = last_valid_block_height;
+    }
+
+    pub fn set_version(&self, version: impl Into<String>) {
+        self.state.lock().unwrap().version = version.into();
+    }
+
+    /// Sets the oldest replayable slot, `None` for a server without replay.
+    pub fn set_first_available(&self, slot: Option<u64>) {
+        self.state.lock().unwrap().first_available = slot;
+    }
+
+    /// `SubscribeRequest`s received on all streams so far, pings excluded.
+    pub fn requests(&self) -> Vec<SubscribeRequest> {
+        self.state.lock().unwrap().requests.clone()
+    }
+
+    /// Number of `Subscribe` calls accepted so far.
+    pub fn subscribes(&self) -> u64 {
+        self.state.lock().unwrap().subscribes
+    }
+
+    /// Serves the mock on a free local port until the server is dropped.
+    pub async fn serve(self) -> io::Result<MockServer> {
+        let incoming = TcpIncoming::bind(SocketAddr::from(([127, 0, 0, 1], 0)))?;
+        let local_addr = incoming.local_addr()?;
+        let (shutdown, signal) = oneshot::channel::<()>();
+
+        let service = MockService {
+            server: GeyserServer::new(self.clone()),
+            mock: self,
+        };
+        let handle = tokio::spawn(async move {
+            let _ = Server::builder()
+                .add_service(service)
+                .serve_with_incoming_shutdown(incoming, async {
+                    let _ = signal.await;
+                })
+                .await;
+        });
+
+        Ok(MockServer {
+            local_addr,
+            shutdown: Some(shutdown),
+            handle,
+        })
+    }
+
+    // `Status` is what the generated service traits return.
+    #[allow(clippy::result_large_err)]
+    fn check(&self, rpc: MockRpc) -> Result<(), Status> {
+        let mut state = self.state.lock().unwrap();
+        match state.failures.get_mut(&rpc).and_then(VecDeque::pop_front) {
+            Some(status) => Err(status),
+            None => Ok(()),
+        }
+    }
+
+    /// Starts playing the next script, with client requests read in the background.
+    #[allow(clippy::result_large_err)]
+    fn open(&self, requests: Streaming<SubscribeRequest>) -> Result<FrameStream, Status> {
+        self.check(MockRpc::Subscribe)?;
+        let events = {
+            let mut state = self.state.lock().unwrap();
+            state.subscribes += 1;
+            state.subscriptions.pop_front().unwrap_or_default()
+        };
+
+        let (sender, receiver) = mpsc::unbounded();
+        tokio::spawn(play(events, sender.clone()));
+        tokio::spawn(read_requests(self.clone(), requests, sender));
+
+        Ok(FrameStream {
+            receiver,
+            reset: Arc::new(AtomicBool::new(false)),
+        })
+    }
+}
+
+/// A running [`MockGeyser`], shut down on drop.
+pub struct MockServer {
+    local_addr: SocketAddr,
+    shutdown: Option<oneshot::Sender<()>>,
+    handle: JoinHandle<()>,
+}
+
+impl MockServer {
+    pub fn local_addr(&self) -> SocketAddr {
+        self.local_addr
+    }
+
+    /// URL to pass to [`crate::GeyserClient::new`].
+    pub fn endpoint(&self) -> String {
+        format!("http://{}", self.local_addr)
+    }
+
+    /// Stops accepting connections and waits for open streams to end.
+    pub async fn shutdown(mut self) {
+        if let Some(shutdown) = self.shutdown.take() {
+            let _ = shutdown.send(());
+        }
+        let _ = (&mut self.handle).await;
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.handle.abort();
+    }
+}
+
+#[allow(clippy::large_enum_variant)]
+enum MockFrame {
+    Update(SubscribeUpdate),
+    Malformed(Vec<u8>),
+    Reset,
+}
+
+async fn play(events: Vec<MockEvent>, sender: mpsc::UnboundedSender<Result<MockFrame, Status>>) {
+    for event in events {
+        let frame = match event {
+            MockEvent::Update(update) => Ok(MockFrame::Update(update)),
+            MockEvent::Malformed(bytes) => Ok(MockFrame::Malformed(bytes)),
+            MockEvent::Delay(delay) => {
+                tokio::time::sleep(delay).await;
+                continue;
+            }
+            MockEvent::Status(code, message) => Err(Status::new(code, message)),
+            MockEvent::Reset => Ok(MockFrame::Reset),
+            MockEvent::End => {
+                sender.close_channel();
+                return;
+            }
+        };
+
+        let last = !matches!(frame, Ok(MockFrame::Update(_) | MockFrame::Malformed(_)));
+        if sender.unbounded_send(frame).is_err() {
+            return;
+        }
+        if last {
+            sender.close_channel();
+            return;
+        }
+    }
+}
+
+async fn read_requests(
+    mock: MockGeyser,
+    mut requests: Streaming<SubscribeRequest>,
+    sender: mpsc::UnboundedSender<Result<MockFrame, Status>>,
+) {
+    while let Ok(Some(request)) = requests.message().await {
+        let Some(ping) = request.ping else {
+            mock.state.lock().unwrap().requests.push(request);
+            continue;
+        };
+
+        let pong = SubscribeUpdate {
+            update_oneof: Some(UpdateOneof::Pong(SubscribeUpdatePong { id: ping.id })),
+            ..Default::default()
+        };
+        if sender.unbounded_send(Ok(MockFrame::Update(pong))).is_err() {
+            return;
+        }
+    }
+}
+
+struct FrameStream {
+    receiver: mpsc::UnboundedReceiver<Result<MockFrame, Status>>,
+    /// Set once the script resets the stream, for [`ResetBody`].
+    reset: Arc<AtomicBool>,
+}
+
+impl Stream for FrameStream {
+    type Item = Result<MockFrame, Status>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        match ready!(self.receiver.poll_next_unpin(cx)) {
+            Some(Ok(MockFrame::Reset)) => {
+                self.reset.store(true, Ordering::Release);
+                self.receiver.close();
+                Poll::Ready(None)
+            }
+            frame => Poll::Ready(frame),
+        }
+    }
+}
+
+#[tonic::async_trait]
+impl Geyser for MockGeyser {
+    type SubscribeStream =
+        Pin<Box<dyn Stream<Item = Result<SubscribeUpdate, Status>> + Send + 'static>>;
+
+    #[allow(clippy::result_large_err)]
+    async fn subscribe(
+        &self,
+        request: Request<Streaming<SubscribeRequest>>,
+    ) -> Result<Response<Self::SubscribeStream>, Status> {
+        let stream = self.open(request.into_inner())?.map(|frame| match frame {
+            Ok(MockFrame::Update(update)) => Ok(update),
+            Ok(MockFrame::Malformed(_)) => Err(Status::internal(
+                "malformed messages need a server started with MockGeyser::serve",
+            )),
+            Ok(MockFrame::Reset) => unreachable!("FrameStream ends on reset"),
+            Err(status) => Err(status),
+        });
+
+        Ok(Response::new(Box::pin(stream)))
+    }
+
+    async fn subscribe_replay_info(
+        &self,
+        _request: Request<SubscribeReplayInfoRequest>,
+    ) -> Result<Response<SubscribeReplayInfoResponse>, Status> {
+        self.check(MockRpc::SubscribeReplayInfo)?;
+        let state = self.state.lock().unwrap();
+
+        Ok(Response::new(SubscribeReplayInfoResponse {
+            first_available: state.first_available,
+        }))
+    }
+
+    async fn ping(&self, request: Request<PingRequest>) -> Result<Response<PongResponse>, Status> {
+        self.check(MockRpc::Ping)?;
+
+        Ok(Response::new(PongResponse {
+            count: request.into_inner().count,
+        }))
+    }
+
+    async fn get_latest_blockhash(
+        &self,
+        _request: Request<GetLatestBlockhashRequest>,
+    ) -> Result<Response<GetLatestBlockhashResponse>, Status> {
+        self.check(MockRpc::GetLatestBlockhash)?;
+        let state = self.state.lock().unwrap();
+
+        Ok(Response::new(GetLatestBlockhashResponse {
+            slot: state.slot,
+            blockhash: state.blockhash.clone(),
+            last_valid_block_height: state.last_valid_block_height,
+        }))
+    }
+
+    async fn get_block_height(
+        &self,
+        _request: Request<GetBlockHeightRequest>,
+    ) -> Result<Response<GetBlockHeightResponse>, Status> {
+        self.check(MockRpc::GetBlockHeight)?;
+        let state = self.state.lock().unwrap();
+
+        Ok(Response::new(GetBlockHeightResponse {
+            block_height: state.block_height,
+        }))
+    }
+
+    async fn get_slot(
+        &self,
+        _request: Request<GetSlotRequest>,
+    ) -> Result<Response<GetSlotResponse>, Status> {
+        self.check(MockRpc::GetSlot)?;
+        let state = self.state.lock().unwrap();
+
+        Ok(Response::new(GetSlotResponse { slot: state.slot }))
+    }
+
+    async fn is_blockhash_valid(
+        &self,
+        request: Request<IsBlockhashValidRequest>,
+    ) -> Result<Response<IsBlockhashValidResponse>, Status> {
+        self.check(MockRpc::IsBlockhashValid)?;
+        let state = self.state.lock().unwrap();
+
+        Ok(Response::new(IsBlockhashValidResponse {
+            slot: state.slot,
+            valid: request.into_inner().blockhash == state.blockhash,
+        }))
+    }
+
+    async fn get_version(
+        &self,
+        _request: Request<GetVersionRequest>,
+    ) -> Result<Response<GetVersionResponse>, Status> {
+        self.check(MockRpc::GetVersion)?;
+        let state = self.state.lock().unwrap();
+
+        Ok(Response::new(GetVersionResponse {
+            version: state.version.clone(),
+        }))
+    }
+}
+
+/// Serves `Subscribe` itself so that scripts can send malformed messages and
+/// resets, everything else is handled by the generated [`GeyserServer`].
+#[derive(Clone)]
+struct MockService {
+    mock: MockGeyser,
+    server: GeyserServer<MockGeyser>,
+}
+
+impl NamedService for MockService {
+    const NAME: &'static str = SERVICE_NAME;
+}
+
+impl<B> Service<http::Request<B>> for MockService
+where
+    B: http_body::Body + Send + 'static,
+    B::Error: Into<StdError> + Send + 'static,
+{
+    type Response = http::Response<Body>;
+    type Error = Infallible;
+    type Future = BoxFuture<Self::Response, Self::Error>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, request: http::Request<B>) -> Self::Future {
+        if request.uri().path() != SUBSCRIBE_PATH {
+            return self.server.call(request);
+        }
+
+        let reset = Arc::new(AtomicBool::new(false));
+        let method = SubscribeService {
+            mock: self.mock.clone(),
+            reset: reset.clone(),
+        };
+        Box::pin(async move {
+            let response = Grpc::new(MockCodec).streaming(method, request).await;
+            Ok(response.map(|inner| Body::new(ResetBody { inner, reset })))
+        })
+    }
+}
+
+struct SubscribeService {
+    mock: MockGeyser,
+    reset: Arc<AtomicBool>,
+}
+
+impl StreamingService<SubscribeRequest> for SubscribeService {
+    type Response = MockFrame;
+    type ResponseStream = FrameStream;
+    type Future = std::future::Ready<Result<Response<FrameStream>, Status>>;
+
+    fn call(&mut self, request: Request<Streaming<SubscribeRequest>>) -> Self::Future {
+        let stream = self.mock.open(request.into_inner()).map(|mut stream| {
+            stream.reset = self.reset.clone();
+            Response::new(stream)
+        });
+
+        std::future::ready(stream)
+    }
+}
+
+/// Encodes frames as is, so malformed ones reach the client.
+struct MockCodec;
+
+impl Codec for MockCodec {
+    type Encode = MockFrame;
+    type Decode = SubscribeRequest;
+    type Encoder = MockEncoder;
+    type Decoder = <ProstCodec<SubscribeUpdate, SubscribeRequest> as Codec>::Decoder;
+
+    fn encoder(&mut self) -> Self::Encoder {
+        MockEncoder
+    }
+
+    fn decoder(&mut self) -> Self::Decoder {
+        ProstCodec::<SubscribeUpdate, SubscribeRequest>::default().decoder()
+    }
+}
+
+struct MockEncoder;
+
+impl Encoder for MockEncoder {
+    type Item = MockFrame;
+    type Error = Status;
+
+    fn encode(&mut self, item: Self::Item, dst: &mut EncodeBuf<'_>) -> Result<(), Self::Error> {
+        match item {
+            MockFrame::Update(update) => update
+                .encode(dst)
+                .map_err(|error| Status::internal(error.to_string())),
+            MockFrame::Malformed(bytes) => {
+                dst.put_slice(&bytes);
+                Ok(())
+            }
+            MockFrame::Reset => Err(Status::internal("reset frames are not encoded")),
+        }
+    }
+}
+
+/// Turns the trailers of a reset stream into a body error, on which the
+/// HTTP/2 stream is reset instead of being ended with a status.
+struct ResetBody {
+    inner: Body,
+    reset: Arc<AtomicBool>,
+}
+
+impl http_body::Body for ResetBody {
+    type Data = Bytes;
+    type Error = Status;
+
+    fn poll_frame(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<Frame<Self::Data>, Self::Error>>> {
+        match ready!(Pin::new(&mut self.inner).poll_frame(cx)) {
+            Some(Ok(frame)) if frame.is_trailers() && self.reset.load(Ordering::Acquire) => {
+                Poll::Ready(Some(Err(Status::internal("stream reset by mock"))))
+            }
+            frame => Poll::Ready(frame),
+        }
+    }
+}
@@ -1,6 +1,25 @@
 pub mod geyser {
+    #![allow(clippy::large_enum_variant)]
     tonic::include_proto!("geyser");
 }
 pub mod solana_storage {
     tonic::include_proto!("solana_storage");
 }
+
+impl geyser::SubscribeUpdate {
+    /// Returns the slot this update belongs to, if the update carries one.
+    pub fn slot(&self) -> Option<u64> {
+        use geyser::subscribe_update::UpdateOneof;
+
+        match self.update_oneof.as_ref()? {
+            UpdateOneof::Account(account) => Some(account.slot),
+            UpdateOneof::Slot(slot) => Some(slot.slot),
+            UpdateOneof::Transaction(transaction) => Some(transaction.slot),
+            UpdateOneof::TransactionStatus(status) => Some(status.slot),
+            UpdateOneof::Block(block) => Some(block.slot),
+            UpdateOneof::BlockMeta(block_meta) => Some(block_meta.slot),
+            UpdateOneof::Entry(entry) => Some(entry.slot),
+            UpdateOneof::Ping(_) | UpdateOneof::Pong(_) => None,
+        }
+    }
+}
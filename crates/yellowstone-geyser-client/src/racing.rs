@@ -0,0 +1,378 @@
+use futures::{
+    stream::{self, BoxStream, SelectAll},
+    StreamExt,
+};
+use std::{
+    collections::{BTreeMap, HashMap},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    error::GeyserClientError,
+    latency::LatencyStats,
+    proto::geyser::{subscribe_update::UpdateOneof, SubscribeRequest, SubscribeUpdate},
+    reconnect::{ReconnectConfig, ResilientSubscription},
+    GeyserClient,
+};
+
+#[derive(Debug, Clone)]
+pub struct RacingConfig {
+    /// Reconnect behaviour of every endpoint.
+    pub reconnect: ReconnectConfig,
+    /// How many slots behind the newest slot an update is remembered for de-duplication.
+    pub dedup_window_slots: u64,
+}
+
+impl Default for RacingConfig {
+    fn default() -> Self {
+        Self {
+            reconnect: ReconnectConfig::default(),
+            dedup_window_slots: 150,
+        }
+    }
+}
+
+/// Identity of an update, shared by copies of it from different endpoints.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum UpdateKey {
+    Account { pubkey: Vec<u8>, write_version: u64 },
+    Slot { slot: u64, status: i32 },
+    Transaction { signature: Vec<u8> },
+    TransactionStatus { signature: Vec<u8> },
+    Block { slot: u64 },
+    BlockMeta { slot: u64 },
+    Entry { slot: u64, index: u64 },
+}
+
+impl UpdateKey {
+    /// Returns `None` for updates that only concern a single connection, like pings.
+    pub fn of(update: &SubscribeUpdate) -> Option<Self> {
+        match update.update_oneof.as_ref()? {
+            UpdateOneof::Account(account) => {
+                let info = account.account.as_ref()?;
+                Some(Self::Account {
+                    pubkey: info.pubkey.clone(),
+                    write_version: info.write_version,
+                })
+            }
+            UpdateOneof::Slot(slot) => Some(Self::Slot {
+                slot: slot.slot,
+                status: slot.status,
+            }),
+            UpdateOneof::Transaction(transaction) => Some(Self::Transaction {
+                signature: transaction.transaction.as_ref()?.signature.clone(),
+            }),
+            UpdateOneof::TransactionStatus(status) => Some(Self::TransactionStatus {
+                signature: status.signature.clone(),
+            }),
+            UpdateOneof::Block(block) => Some(Self::Block { slot: block.slot }),
+            UpdateOneof::BlockMeta(block_meta) => Some(Self::BlockMeta {
+                slot: block_meta.slot,
+            }),
+            UpdateOneof::Entry(entry) => Some(Self::Entry {
+                slot: entry.slot,
+                index: entry.index,
+            }),
+            UpdateOneof::Ping(_) | UpdateOneof::Pong(_) => None,
+        }
+    }
+}
+
+/// An update together with the index of the endpoint that delivered it first.
+#[derive(Debug, Clone)]
+pub struct RacedUpdate {
+    pub endpoint: usize,
+    pub update: SubscribeUpdate,
+}
+
+#[derive(Debug, thiserror::Error)]
+#[error("endpoint {endpoint} failed: {error}")]
+pub struct EndpointError {
+    pub endpoint: usize,
+    #[source]
+    pub error: GeyserClientError,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct EndpointRaceStats {
+    /// Updates this endpoint delivered first.
+    pub wins: u64,
+    /// Updates this endpoint delivered after another endpoint already had.
+    pub duplicates: u64,
+    /// Sum of the delays behind the winner over all duplicates.
+    pub total_lag: Duration,
+    /// Updates older than the de-duplication window. They can no longer be
+    /// checked against earlier copies and are passed through.
+    pub late: u64,
+}
+
+impl EndpointRaceStats {
+    /// Average delay behind the winning endpoint when this endpoint lost.
+    pub fn average_lag(&self) -> Option<Duration> {
+        (self.duplicates > 0).then(|| self.total_lag.div_f64(self.duplicates as f64))
+    }
+}
+
+/// Opens the same `SubscribeRequest` against several endpoints and yields each
+/// update once, from whichever endpoint delivered it first.
+///
+/// Each endpoint runs a [`ResilientSubscription`], so a failing endpoint
+/// reconnects on its own while the others keep delivering. Updates more than
+/// `dedup_window_slots` behind the newest slot are not de-duplicated, they are
+/// yielded and counted in [`EndpointRaceStats::late`].
+pub struct RacingSubscription {
+    streams: SelectAll<BoxStream<'static, (usize, Result<SubscribeUpdate, GeyserClientError>)>>,
+    dedup_window_slots: u64,
+    seen: HashMap<UpdateKey, Instant>,
+    seen_by_slot: BTreeMap<u64, Vec<UpdateKey>>,
+    stats: Vec<EndpointRaceStats>,
+    latency_stats: Vec<LatencyStats>,
+}
+
+impl RacingSubscription {
+    pub fn new(
+        clients: Vec<GeyserClient>,
+        request: SubscribeRequest,
+        config: RacingConfig,
+    ) -> Self {
+        let stats = vec![EndpointRaceStats::default(); clients.len()];
+        let mut latency_stats = Vec::with_capacity(clients.len());
+        let streams =
+            stream::select_all(clients.into_iter().enumerate().map(|(endpoint, client)| {
+                let subscription =
+                    ResilientSubscription::new(client, request.clone(), config.reconnect.clone());
+                latency_stats.push(subscription.latency_stats());
+
+                stream::unfold(Some(subscription), move |subscription| async move {
+                    let mut subscription = subscription?;
+                    match subscription.message().await {
+                        Ok(Some(update)) => Some(((endpoint, Ok(update)), Some(subscription))),
+                        Ok(None) => None,
+                        // The subscription gave up, report the error once and end the stream.
+                        Err(error) => Some(((endpoint, Err(error)), None)),
+                    }
+                })
+                .boxed()
+            }));
+
+        Self {
+            streams,
+            dedup_window_slots: config.dedup_window_slots,
+            seen: HashMap::new(),
+            seen_by_slot: BTreeMap::new(),
+            stats,
+            latency_stats,
+        }
+    }
+
+    /// Per endpoint statistics, indexed like the clients passed to [`RacingSubscription::new`].
+    pub fn stats(&self) -> &[EndpointRaceStats] {
+        &self.stats
+    }
+
+    /// Per endpoint latency, counting every update an endpoint delivered,
+    /// duplicates included.
+    pub fn latency_stats(&self) -> &[LatencyStats] {
+        &self.latency_stats
+    }
+
+    /// Returns the next update not seen before, or `None` once every endpoint has ended.
+    pub async fn message(&mut self) -> Option<Result<RacedUpdate, EndpointError>> {
+        loop {
+            let (endpoint, update) = match self.streams.next().await? {
+                (endpoint, Ok(update)) => (endpoint, update),
+                (endpoint, Err(error)) => return Some(Err(EndpointError { endpoint, error })),
+            };
+
+            if self.is_duplicate(endpoint, &update) {
+                continue;
+            }
+
+            return Some(Ok(RacedUpdate { endpoint, update }));
+        }
+    }
+
+    fn is_duplicate(&mut self, endpoint: usize, update: &SubscribeUpdate) -> bool {
+        let (Some(key), Some(slot)) = (UpdateKey::of(update), update.slot()) else {
+            return false;
+        };
+
+        if let Some(received_at) = self.seen.get(&key) {
+            let stats = &mut self.stats[endpoint];
+            stats.duplicates += 1;
+            stats.total_lag += received_at.elapsed();
+            return true;
+        }
+
+        // Older than the window, it may be a copy of a pruned update or something
+        // only this endpoint saw, like a late finalized status. Pass it on rather
+        // than risk dropping real data.
+        let newest_slot = self.seen_by_slot.keys().next_back().copied();
+        if newest_slot.is_some_and(|newest| slot.saturating_add(self.dedup_window_slots) < newest) {
+            self.stats[endpoint].late += 1;
+            return false;
+        }
+
+        self.stats[endpoint].wins += 1;
+        self.seen.insert(key.clone(), Instant::now());
+        self.seen_by_slot.entry(slot).or_default().push(key);
+        self.prune();
+
+        false
+    }
+
+    fn prune(&mut self) {
+        let Some(newest) = self.seen_by_slot.keys().next_back().copied() else {
+            return;
+        };
+        let oldest_kept = newest.saturating_sub(self.dedup_window_slots);
+
+        while let Some(entry) = self.seen_by_slot.first_entry() {
+            if *entry.key() >= oldest_kept {
+                break;
+            }
+            for key in entry.remove() {
+                self.seen.remove(&key);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::proto::geyser::{
+        SubscribeUpdateAccount, SubscribeUpdateAccountInfo, SubscribeUpdatePing,
+        SubscribeUpdateSlot, SubscribeUpdateTransactionStatus,
+    };
+
+    fn slot(slot: u64, status: i32) -> SubscribeUpdate {
+        SubscribeUpdate {
+            update_oneof: Some(UpdateOneof::Slot(SubscribeUpdateSlot {
+                slot,
+                status,
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+    }
+
+    fn account(slot: u64, pubkey: u8, write_version: u64) -> SubscribeUpdate {
+        SubscribeUpdate {
+            update_oneof: Some(UpdateOneof::Account(SubscribeUpdateAccount {
+                slot,
+                account: Some(SubscribeUpdateAccountInfo {
+                    pubkey: vec![pubkey; 32],
+                    write_version,
+                    ..Default::default()
+                }),
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+    }
+
+    fn racing(endpoints: usize, dedup_window_slots: u64) -> RacingSubscription {
+        let config = RacingConfig {
+            dedup_window_slots,
+            ..Default::default()
+        };
+        let mut racing = RacingSubscription::new(Vec::new(), SubscribeRequest::default(), config);
+        racing.stats = vec![EndpointRaceStats::default(); endpoints];
+        racing
+    }
+
+    #[test]
+    fn keys_updates_by_identity() {
+        assert_eq!(
+            UpdateKey::of(&account(5, 1, 7)),
+            Some(UpdateKey::Account {
+                pubkey: vec![1; 32],
+                write_version: 7,
+            })
+        );
+        assert_eq!(
+            UpdateKey::of(&slot(5, 2)),
+            Some(UpdateKey::Slot { slot: 5, status: 2 })
+        );
+        let status = SubscribeUpdate {
+            update_oneof: Some(UpdateOneof::TransactionStatus(
+                SubscribeUpdateTransactionStatus {
+                    slot: 5,
+                    signature: vec![3; 64],
+                    ..Default::default()
+                },
+            )),
+            ..Default::default()
+        };
+        assert_eq!(
+            UpdateKey::of(&status),
+            Some(UpdateKey::TransactionStatus {
+                signature: vec![3; 64],
+            })
+        );
+
+        let ping = SubscribeUpdate {
+            update_oneof: Some(UpdateOneof::Ping(SubscribeUpdatePing {})),
+            ..Default::default()
+        };
+        assert_eq!(UpdateKey::of(&ping), None);
+        let account_without_info = SubscribeUpdate {
+            update_oneof: Some(UpdateOneof::Account(SubscribeUpdateAccount::default())),
+            ..Default::default()
+        };
+        assert_eq!(UpdateKey::of(&account_without_info), None);
+        assert_eq!(UpdateKey::of(&SubscribeUpdate::default()), None);
+    }
+
+    #[test]
+    fn drops_copies_from_other_endpoints() {
+        let mut racing = racing(2, 150);
+
+        assert!(!racing.is_duplicate(0, &account(5, 1, 7)));
+        assert!(racing.is_duplicate(1, &account(5, 1, 7)));
+        assert!(!racing.is_duplicate(1, &account(5, 1, 8)));
+        assert!(!racing.is_duplicate(1, &slot(5, 1)));
+        assert!(!racing.is_duplicate(0, &slot(5, 2)));
+        assert!(racing.is_duplicate(0, &slot(5, 1)));
+
+        let stats = racing.stats();
+        assert_eq!([stats[0].wins, stats[1].wins], [2, 2]);
+        assert_eq!([stats[0].duplicates, stats[1].duplicates], [1, 1]);
+        assert!(stats[0].average_lag().is_some());
+        assert_eq!([stats[0].late, stats[1].late], [0, 0]);
+    }
+
+    #[test]
+    fn prunes_updates_outside_window() {
+        let mut racing = racing(2, 10);
+        racing.is_duplicate(0, &account(100, 1, 1));
+        racing.is_duplicate(0, &account(105, 1, 2));
+        racing.is_duplicate(0, &account(111, 1, 3));
+
+        assert_eq!(
+            racing.seen_by_slot.keys().copied().collect::<Vec<_>>(),
+            [105, 111]
+        );
+        assert_eq!(racing.seen.len(), 2);
+
+        // Still inside the window, the copy is recognised.
+        assert!(racing.is_duplicate(1, &account(105, 1, 2)));
+        // Pruned, but no longer than the window behind, so it counts as a win again.
+        assert!(!racing.is_duplicate(1, &account(101, 1, 1)));
+        assert_eq!(racing.stats()[1].wins, 1);
+    }
+
+    #[test]
+    fn passes_late_updates_through() {
+        let mut racing = racing(2, 10);
+        racing.is_duplicate(0, &account(100, 1, 1));
+        racing.is_duplicate(0, &account(120, 1, 2));
+
+        assert!(!racing.is_duplicate(1, &account(100, 1, 1)));
+        assert!(!racing.is_duplicate(1, &account(100, 1, 1)));
+        assert_eq!(racing.stats()[1].late, 2);
+        assert_eq!(racing.stats()[1].wins, 0);
+        assert!(!racing.seen_by_slot.contains_key(&100));
+    }
+}
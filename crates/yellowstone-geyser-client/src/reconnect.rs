@@ -0,0 +1,293 @@
+use std::{pin::Pin, time::Duration};
+use tokio::time::Sleep;
+use tonic::Code;
+
+use crate::{
+    error::GeyserClientError,
+    keepalive::{KeepaliveStats, KeepaliveStream},
+    latency::{LatencyStats, TimestampedUpdate},
+    proto::geyser::{SubscribeRequest, SubscribeUpdate},
+    GeyserClient,
+};
+
+#[derive(Debug, Clone)]
+pub struct ReconnectConfig {
+    /// Delay before the first reconnect attempt.
+    pub initial_backoff: Duration,
+    /// Upper bound for the delay between attempts.
+    pub max_backoff: Duration,
+    /// Factor applied to the delay after every failed attempt.
+    pub backoff_multiplier: f64,
+    /// Number of consecutive failed attempts before giving up, `None` retries forever.
+    pub max_attempts: Option<u32>,
+}
+
+impl Default for ReconnectConfig {
+    fn default() -> Self {
+        Self {
+            initial_backoff: Duration::from_millis(100),
+            max_backoff: Duration::from_secs(10),
+            backoff_multiplier: 2.0,
+            max_attempts: None,
+        }
+    }
+}
+
+impl ReconnectConfig {
+    /// A config that never reconnects, so the subscription behaves like a plain stream.
+    pub fn disabled() -> Self {
+        Self {
+            max_attempts: Some(0),
+            ..Default::default()
+        }
+    }
+
+    fn backoff(&self, attempt: u32) -> Duration {
+        let factor = self.backoff_multiplier.max(1.0).powi(attempt as i32);
+
+        self.initial_backoff.mul_f64(factor).min(self.max_backoff)
+    }
+}
+
+/// A `Subscribe` stream that reconnects with backoff when the connection drops.
+///
+/// The highest slot seen so far is tracked and written into
+/// `SubscribeRequest.from_slot` on reconnect, so the server replays anything
+/// missed while disconnected. Updates for that slot may be delivered twice.
+/// The resume slot is clamped to the server's replay history first, see
+/// [`GeyserClient::clamp_from_slot`].
+///
+/// Pings are handled by a [`KeepaliveStream`] using the client's keepalive config.
+pub struct ResilientSubscription {
+    client: GeyserClient,
+    config: ReconnectConfig,
+    request: SubscribeRequest,
+    stream: Option<KeepaliveStream>,
+    /// Delay before the next reconnect, kept here so it survives cancellation.
+    backoff: Option<Pin<Box<Sleep>>>,
+    keepalive_stats: KeepaliveStats,
+    latency_stats: LatencyStats,
+    last_slot: Option<u64>,
+    attempt: u32,
+    connects: u64,
+    unrecoverable_slots: u64,
+}
+
+impl ResilientSubscription {
+    pub fn new(client: GeyserClient, request: SubscribeRequest, config: ReconnectConfig) -> Self {
+        Self {
+            client,
+            config,
+            request,
+            stream: None,
+            backoff: None,
+            keepalive_stats: KeepaliveStats::default(),
+            latency_stats: LatencyStats::default(),
+            last_slot: None,
+            attempt: 0,
+            connects: 0,
+            unrecoverable_slots: 0,
+        }
+    }
+
+    /// Resumes from `slot` on the first connect, as if it had already been seen.
+    pub fn resume_from(mut self, slot: Option<u64>) -> Self {
+        self.last_slot = slot;
+        self
+    }
+
+    /// Highest slot seen on any update so far.
+    pub fn last_slot(&self) -> Option<u64> {
+        self.last_slot
+    }
+
+    /// Ping statistics, kept across reconnects.
+    pub fn keepalive_stats(&self) -> KeepaliveStats {
+        self.keepalive_stats.clone()
+    }
+
+    /// Latency and slot lag of received updates, kept across reconnects.
+    pub fn latency_stats(&self) -> LatencyStats {
+        self.latency_stats.clone()
+    }
+
+    /// Number of times the stream was re-established after the first connect.
+    pub fn reconnects(&self) -> u64 {
+        self.connects.saturating_sub(1)
+    }
+
+    /// Slots that were missed across reconnects because the server could no
+    /// longer replay them.
+    pub fn unrecoverable_slots(&self) -> u64 {
+        self.unrecoverable_slots
+    }
+
+    /// Replaces the subscription filters.
+    ///
+    /// The request is sent over the live stream if there is one and is used
+    /// for every reconnect from now on.
+    pub fn update(&mut self, request: SubscribeRequest) {
+        self.request = request.clone();
+
+        if let Some(stream) = self.stream.as_ref() {
+            if stream.sender().send(request).is_err() {
+                self.disconnect();
+            }
+        }
+    }
+
+    /// Returns the next update, reconnecting as needed.
+    ///
+    /// Returns `Ok(None)` when the server ends the stream and no attempts are
+    /// left, and the last error when reconnecting fails too many times in a row.
+    ///
+    /// Cancel-safe: dropping the future loses no update, and a pending backoff
+    /// carries on where it stopped on the next call.
+    pub async fn message(&mut self) -> Result<Option<SubscribeUpdate>, GeyserClientError> {
+        let update = self.timestamped_message().await?;
+        Ok(update.map(|update| update.update))
+    }
+
+    /// Like [`Self::message`], with the local time the update was received at.
+    pub async fn timestamped_message(
+        &mut self,
+    ) -> Result<Option<TimestampedUpdate>, GeyserClientError> {
+        loop {
+            if let Some(backoff) = self.backoff.as_mut() {
+                backoff.await;
+                self.backoff = None;
+            }
+
+            let error = match self.stream.as_mut() {
+                Some(stream) => match stream.timestamped_message().await {
+                    Ok(Some(update)) => {
+                        self.attempt = 0;
+                        if let Some(slot) = update.update.slot() {
+                            self.last_slot = Some(self.last_slot.map_or(slot, |x| x.max(slot)));
+                        }
+                        return Ok(Some(update));
+                    }
+                    Ok(None) => GeyserClientError::StreamClosed,
+                    Err(error) => error,
+                },
+                None => match self.connect().await {
+                    Ok(()) => continue,
+                    Err(error) => error,
+                },
+            };
+
+            if let Some(result) = self.disconnect_with(error) {
+                return result;
+            }
+
+            // Counted before sleeping, so a cancelled backoff still counts as an attempt.
+            self.backoff = Some(Box::pin(tokio::time::sleep(
+                self.config.backoff(self.attempt),
+            )));
+            self.attempt += 1;
+        }
+    }
+
+    async fn connect(&mut self) -> Result<(), GeyserClientError> {
+        let mut request = self.request.clone();
+        request.ping = None;
+        if let Some(last_slot) = self.last_slot {
+            match self.client.clamp_from_slot(last_slot).await {
+                Ok(replay) => {
+                    request.from_slot = replay.map(|replay| replay.from_slot);
+                    self.unrecoverable_slots +=
+                        replay.map_or(0, |replay| replay.unrecoverable_slots);
+                }
+                // Older servers have no `SubscribeReplayInfo`, try the slot as is.
+                Err(error) if error.code() == Some(Code::Unimplemented) => {
+                    request.from_slot = Some(last_slot);
+                }
+                Err(error) => return Err(error),
+            }
+        }
+
+        #[cfg(feature = "tracing")]
+        let (span, from_slot) = (self.client.stream_span(&request), request.from_slot);
+        let (sender, stream) = self.client.subscribe_with_sender(request).await?;
+        let stream = KeepaliveStream::with_stats(
+            sender,
+            stream,
+            self.client.keepalive_config().clone(),
+            self.keepalive_stats.clone(),
+        )
+        .with_latency_stats(self.latency_stats.clone());
+        #[cfg(feature = "metrics")]
+        let stream = stream.with_metrics(self.client.metrics().cloned());
+        #[cfg(feature = "tracing")]
+        let stream = {
+            tracing::info!(
+                parent: &span,
+                from_slot,
+                reconnects = self.connects,
+                unrecoverable_slots = self.unrecoverable_slots,
+                "subscribed"
+            );
+            stream.with_span(span)
+        };
+        self.stream = Some(stream);
+        self.connects += 1;
+
+        #[cfg(feature = "metrics")]
+        if let Some(metrics) = self.client.metrics().filter(|_| self.connects > 1) {
+            metrics.observe_reconnect();
+        }
+
+        Ok(())
+    }
+
+    /// Drops the current stream and decides whether to retry, returning the
+    /// final result when the subscription has to give up.
+    fn disconnect_with(
+        &mut self,
+        error: GeyserClientError,
+    ) -> Option<Result<Option<TimestampedUpdate>, GeyserClientError>> {
+        self.disconnect();
+
+        let exhausted = self
+            .config
+            .max_attempts
+            .is_some_and(|max_attempts| self.attempt >= max_attempts);
+        #[cfg(feature = "tracing")]
+        if !exhausted && is_retryable(&error) {
+            tracing::warn!(
+                endpoint = self.client.endpoint_url(),
+                attempt = self.attempt + 1,
+                backoff_ms = self.config.backoff(self.attempt).as_millis() as u64,
+                last_slot = self.last_slot,
+                %error,
+                "subscription disconnected, reconnecting"
+            );
+        } else {
+            tracing::error!(
+                endpoint = self.client.endpoint_url(),
+                attempts = self.attempt,
+                last_slot = self.last_slot,
+                %error,
+                "subscription disconnected, giving up"
+            );
+        }
+        match error {
+            GeyserClientError::StreamClosed if exhausted => Some(Ok(None)),
+            error if exhausted || !is_retryable(&error) => Some(Err(error)),
+            _ => None,
+        }
+    }
+
+    fn disconnect(&mut self) {
+        self.stream = None;
+    }
+}
+
+fn is_retryable(error: &GeyserClientError) -> bool {
+    match error {
+        GeyserClientError::InvalidEndpoint(_)
+        | GeyserClientError::Tls(_)
+        | GeyserClientError::Unauthenticated(_) => false,
+        error => error.code() != Some(Code::InvalidArgument),
+    }
+}
@@ -0,0 +1,236 @@
+use futures::Stream;
+use std::{
+    path::{Path, PathBuf},
+    pin::Pin,
+    task::{ready, Context, Poll},
+};
+use tonic::Status;
+
+use crate::{
+    error::GeyserClientError,
+    filter::FilterMatcher,
+    latency::TimestampedUpdate,
+    proto::geyser::{SubscribeRequest, SubscribeUpdate},
+    Subscribe,
+};
+
+pub use grpc_transport::recording::{
+    Compression, RecordReader, Recorded, Recorder, RecordingStream, Replay, ReplaySpeed,
+};
+
+/// Serves `Subscribe` streams from a recording instead of a server.
+///
+/// Every subscription replays the recording from its start, so consumers
+/// generic over [`Subscribe`] can run against a capture by swapping the
+/// client. Record one by wrapping a live stream in a [`RecordingStream`].
+#[derive(Debug, Clone)]
+pub struct ReplayClient {
+    path: PathBuf,
+    speed: ReplaySpeed,
+}
+
+impl ReplayClient {
+    pub fn new(path: impl Into<PathBuf>, speed: ReplaySpeed) -> Self {
+        Self {
+            path: path.into(),
+            speed,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Opens the recording and applies the filters of `request` to it.
+    ///
+    /// Updates are matched the way [`FilterMatcher`] does and get their
+    /// `filters` rewritten, updates older than `from_slot` are skipped. A
+    /// request without filters replays every update unchanged.
+    pub async fn subscribe(
+        &mut self,
+        request: SubscribeRequest,
+    ) -> Result<ReplayStream, GeyserClientError> {
+        let matcher = if request.filter_names().is_empty() {
+            None
+        } else {
+            let matcher = FilterMatcher::new(&request)
+                .map_err(|error| Status::invalid_argument(error.to_string()))?;
+            Some(matcher)
+        };
+        let replay = Replay::open(&self.path, self.speed).map_err(GeyserClientError::Recording)?;
+
+        Ok(ReplayStream {
+            replay,
+            matcher,
+            from_slot: request.from_slot,
+        })
+    }
+}
+
+#[tonic::async_trait]
+impl Subscribe for ReplayClient {
+    type Stream = ReplayStream;
+
+    async fn subscribe(
+        &mut self,
+        request: SubscribeRequest,
+    ) -> Result<Self::Stream, GeyserClientError> {
+        ReplayClient::subscribe(self, request).await
+    }
+}
+
+/// A `Subscribe` stream replayed from a recording.
+pub struct ReplayStream {
+    replay: Replay<SubscribeUpdate>,
+    matcher: Option<FilterMatcher>,
+    from_slot: Option<u64>,
+}
+
+impl ReplayStream {
+    /// Returns the next update, `None` at the end of the recording.
+    pub async fn message(&mut self) -> Result<Option<SubscribeUpdate>, GeyserClientError> {
+        let update = self.timestamped_message().await?;
+        Ok(update.map(|update| update.update))
+    }
+
+    /// Like [`Self::message`], with the time the update was originally received at.
+    pub async fn timestamped_message(
+        &mut self,
+    ) -> Result<Option<TimestampedUpdate>, GeyserClientError> {
+        let update = futures::future::poll_fn(|cx| self.poll_timestamped(cx)).await;
+
+        Ok(update.transpose()?)
+    }
+
+    fn poll_timestamped(
+        &mut self,
+        cx: &mut Context<'_>,
+    ) -> Poll<Option<Result<TimestampedUpdate, Status>>> {
+        loop {
+            let record = match ready!(Pin::new(&mut self.replay).poll_next(cx)) {
+                Some(Ok(record)) => record,
+                Some(Err(error)) => {
+                    return Poll::Ready(Some(Err(Status::data_loss(format!(
+                        "failed to read recording: {error}"
+                    )))))
+                }
+                None => return Poll::Ready(None),
+            };
+
+            let Recorded {
+                message: mut update,
+                received_at,
+            } = record;
+            if self
+                .from_slot
+                .zip(update.slot())
+                .is_some_and(|(from_slot, slot)| slot < from_slot)
+            {
+                continue;
+            }
+            if let Some(matcher) = self.matcher.as_ref() {
+                if !matcher.apply(&mut update) {
+                    continue;
+                }
+            }
+
+            return Poll::Ready(Some(Ok(TimestampedUpdate {
+                update,
+                received_at,
+            })));
+        }
+    }
+}
+
+impl Stream for ReplayStream {
+    type Item = Result<SubscribeUpdate, Status>;
+
+    fn poll_next(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        Poll::Ready(match ready!(self.poll_timestamped(cx)) {
+            Some(Ok(update)) => Some(Ok(update.update)),
+            Some(Err(status)) => Some(Err(status)),
+            None => None,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        proto::geyser::{subscribe_update::UpdateOneof, SubscribeUpdateEntry, SubscribeUpdateSlot},
+        request_builder::SubscribeRequestBuilder,
+    };
+    use futures::{executor::block_on, StreamExt};
+    use std::time::SystemTime;
+
+    fn slot(slot: u64) -> SubscribeUpdate {
+        SubscribeUpdate {
+            update_oneof: Some(UpdateOneof::Slot(SubscribeUpdateSlot {
+                slot,
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+    }
+
+    fn entry(slot: u64) -> SubscribeUpdate {
+        SubscribeUpdate {
+            update_oneof: Some(UpdateOneof::Entry(SubscribeUpdateEntry {
+                slot,
+                ..Default::default()
+            })),
+            ..Default::default()
+        }
+    }
+
+    /// Slots and filter names of every update, whatever serves the stream.
+    async fn collect(
+        client: &mut impl Subscribe,
+        request: SubscribeRequest,
+    ) -> Vec<(Option<u64>, Vec<String>)> {
+        let stream = client.subscribe(request).await.unwrap();
+        stream
+            .map(|update| {
+                let update = update.unwrap();
+                (update.slot(), update.filters)
+            })
+            .collect()
+            .await
+    }
+
+    #[test]
+    fn replays_through_subscribe() {
+        let path = std::env::temp_dir().join(format!("geyser-replay-{}", std::process::id()));
+        let mut recorder = Recorder::create(&path, Compression::None).unwrap();
+        for update in [slot(1), slot(2), entry(2), slot(3)] {
+            recorder.record(&update, SystemTime::now()).unwrap();
+        }
+        recorder.finish().unwrap();
+
+        let mut client = ReplayClient::new(&path, ReplaySpeed::Unlimited);
+        let everything = block_on(collect(&mut client, SubscribeRequest::default()));
+        let mut builder = SubscribeRequestBuilder::new();
+        builder.slots("slots");
+        builder.from_slot(2);
+        let slots = block_on(collect(&mut client, builder.build().unwrap()));
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            everything,
+            [
+                (Some(1), vec![]),
+                (Some(2), vec![]),
+                (Some(2), vec![]),
+                (Some(3), vec![]),
+            ]
+        );
+        assert_eq!(
+            slots,
+            [
+                (Some(2), vec!["slots".to_string()]),
+                (Some(3), vec!["slots".to_string()]),
+            ]
+        );
+    }
+}
@@ -0,0 +1,855 @@
+use base64::{engine::general_purpose::STANDARD, Engine};
+use std::{collections::HashMap, fmt};
+
+use crate::proto::geyser::{
+    subscribe_request_filter_accounts_filter::Filter as AccountsFilter,
+    subscribe_request_filter_accounts_filter_lamports::Cmp as LamportsCmp,
+    subscribe_request_filter_accounts_filter_memcmp::Data as MemcmpData, CommitmentLevel,
+    SubscribeRequest, SubscribeRequestAccountsDataSlice, SubscribeRequestFilterAccounts,
+    SubscribeRequestFilterAccountsFilter, SubscribeRequestFilterAccountsFilterLamports,
+    SubscribeRequestFilterAccountsFilterMemcmp, SubscribeRequestFilterBlocks,
+    SubscribeRequestFilterBlocksMeta, SubscribeRequestFilterEntry, SubscribeRequestFilterSlots,
+    SubscribeRequestFilterTransactions,
+};
+
+pub(crate) const PUBKEY_LEN: usize = 32;
+const SIGNATURE_LEN: usize = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum RequestValidationError {
+    #[error("request has no filters")]
+    NoFilters,
+    #[error("{kind} filter has an empty name")]
+    EmptyFilterName { kind: &'static str },
+    #[error("filter `{filter}`: invalid pubkey `{value}`")]
+    InvalidPubkey { filter: String, value: String },
+    #[error("filter `{filter}`: invalid signature `{value}`")]
+    InvalidSignature { filter: String, value: String },
+    #[error("filter `{filter}`: {reason}")]
+    InvalidFilter { filter: String, reason: String },
+    #[error("filter `{filter}`: {reason}")]
+    Conflict { filter: String, reason: String },
+    #[error("accounts data slices overlap at offset {offset}")]
+    OverlappingDataSlices { offset: u64 },
+    #[error("accounts data slice of {length} bytes at offset {offset} overflows")]
+    DataSliceOverflow { offset: u64, length: u64 },
+}
+
+/// Fluent builder for `SubscribeRequest`s that validates pubkeys, signatures
+/// and filter combinations before anything is sent to the server.
+///
+/// Filters are addressed by name, calling e.g. [`SubscribeRequestBuilder::account`]
+/// twice with the same name extends the same filter.
+#[derive(Debug, Clone, Default)]
+pub struct SubscribeRequestBuilder {
+    request: SubscribeRequest,
+}
+
+impl SubscribeRequestBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn account(&mut self, name: impl Into<String>) -> AccountsFilterBuilder<'_> {
+        AccountsFilterBuilder {
+            filter: self.request.accounts.entry(name.into()).or_default(),
+        }
+    }
+
+    pub fn slots(&mut self, name: impl Into<String>) -> SlotsFilterBuilder<'_> {
+        SlotsFilterBuilder {
+            filter: self.request.slots.entry(name.into()).or_default(),
+        }
+    }
+
+    pub fn transactions(&mut self, name: impl Into<String>) -> TransactionsFilterBuilder<'_> {
+        TransactionsFilterBuilder {
+            filter: self.request.transactions.entry(name.into()).or_default(),
+        }
+    }
+
+    pub fn transactions_status(
+        &mut self,
+        name: impl Into<String>,
+    ) -> TransactionsFilterBuilder<'_> {
+        TransactionsFilterBuilder {
+            filter: self
+                .request
+                .transactions_status
+                .entry(name.into())
+                .or_default(),
+        }
+    }
+
+    pub fn blocks(&mut self, name: impl Into<String>) -> BlocksFilterBuilder<'_> {
+        BlocksFilterBuilder {
+            filter: self.request.blocks.entry(name.into()).or_default(),
+        }
+    }
+
+    pub fn blocks_meta(&mut self) -> &mut Self {
+        self.request.blocks_meta.insert(
+            "blocks_meta".to_string(),
+            SubscribeRequestFilterBlocksMeta {},
+        );
+        self
+    }
+
+    pub fn entries(&mut self) -> &mut Self {
+        self.request
+            .entry
+            .insert("entry".to_string(), SubscribeRequestFilterEntry {});
+        self
+    }
+
+    pub fn commitment(&mut self, commitment: CommitmentLevel) -> &mut Self {
+        self.request.commitment = Some(commitment as i32);
+        self
+    }
+
+    pub fn from_slot(&mut self, slot: u64) -> &mut Self {
+        self.request.from_slot = Some(slot);
+        self
+    }
+
+    /// Only return `length` bytes of account data starting at `offset`.
+    pub fn accounts_data_slice(&mut self, offset: u64, length: u64) -> &mut Self {
+        self.request
+            .accounts_data_slice
+            .push(SubscribeRequestAccountsDataSlice { offset, length });
+        self
+    }
+
+    /// Validates the request and returns it.
+    pub fn build(&self) -> Result<SubscribeRequest, RequestValidationError> {
+        validate(&self.request)?;
+
+        Ok(self.request.clone())
+    }
+}
+
+pub struct AccountsFilterBuilder<'a> {
+    filter: &'a mut SubscribeRequestFilterAccounts,
+}
+
+impl AccountsFilterBuilder<'_> {
+    /// Matches the account with this base58 pubkey.
+    pub fn pubkey(self, pubkey: impl Into<String>) -> Self {
+        self.filter.account.push(pubkey.into());
+        self
+    }
+
+    pub fn pubkeys(self, pubkeys: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.filter
+            .account
+            .extend(pubkeys.into_iter().map(Into::into));
+        self
+    }
+
+    /// Matches accounts owned by this base58 program id.
+    pub fn owner(self, owner: impl Into<String>) -> Self {
+        self.filter.owner.push(owner.into());
+        self
+    }
+
+    pub fn owners(self, owners: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.filter.owner.extend(owners.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn memcmp(self, offset: u64, bytes: impl Into<Vec<u8>>) -> Self {
+        self.push(AccountsFilter::Memcmp(
+            SubscribeRequestFilterAccountsFilterMemcmp {
+                offset,
+                data: Some(MemcmpData::Bytes(bytes.into())),
+            },
+        ))
+    }
+
+    pub fn memcmp_base58(self, offset: u64, data: impl Into<String>) -> Self {
+        self.push(AccountsFilter::Memcmp(
+            SubscribeRequestFilterAccountsFilterMemcmp {
+                offset,
+                data: Some(MemcmpData::Base58(data.into())),
+            },
+        ))
+    }
+
+    pub fn datasize(self, size: u64) -> Self {
+        self.push(AccountsFilter::Datasize(size))
+    }
+
+    /// Only matches initialized SPL token accounts.
+    pub fn token_account_state(self) -> Self {
+        self.push(AccountsFilter::TokenAccountState(true))
+    }
+
+    pub fn lamports(self, cmp: LamportsCmp) -> Self {
+        self.push(AccountsFilter::Lamports(
+            SubscribeRequestFilterAccountsFilterLamports { cmp: Some(cmp) },
+        ))
+    }
+
+    /// Only send updates caused by a transaction, skipping startup snapshots.
+    pub fn nonempty_txn_signature(self, nonempty: bool) -> Self {
+        self.filter.nonempty_txn_signature = Some(nonempty);
+        self
+    }
+
+    fn push(self, filter: AccountsFilter) -> Self {
+        self.filter
+            .filters
+            .push(SubscribeRequestFilterAccountsFilter {
+                filter: Some(filter),
+            });
+        self
+    }
+}
+
+pub struct SlotsFilterBuilder<'a> {
+    filter: &'a mut SubscribeRequestFilterSlots,
+}
+
+impl SlotsFilterBuilder<'_> {
+    /// Only send slot updates matching the request commitment.
+    pub fn filter_by_commitment(self, enabled: bool) -> Self {
+        self.filter.filter_by_commitment = Some(enabled);
+        self
+    }
+
+    pub fn interslot_updates(self, enabled: bool) -> Self {
+        self.filter.interslot_updates = Some(enabled);
+        self
+    }
+}
+
+pub struct TransactionsFilterBuilder<'a> {
+    filter: &'a mut SubscribeRequestFilterTransactions,
+}
+
+impl TransactionsFilterBuilder<'_> {
+    /// `false` skips vote transactions, `true` only sends them.
+    pub fn vote(self, vote: bool) -> Self {
+        self.filter.vote = Some(vote);
+        self
+    }
+
+    /// `false` skips failed transactions, `true` only sends them.
+    pub fn failed(self, failed: bool) -> Self {
+        self.filter.failed = Some(failed);
+        self
+    }
+
+    /// Only matches the transaction with this base58 signature.
+    pub fn signature(self, signature: impl Into<String>) -> Self {
+        self.filter.signature = Some(signature.into());
+        self
+    }
+
+    /// Matches transactions using any of these accounts.
+    pub fn account_include(self, pubkeys: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.filter
+            .account_include
+            .extend(pubkeys.into_iter().map(Into::into));
+        self
+    }
+
+    /// Skips transactions using any of these accounts.
+    pub fn account_exclude(self, pubkeys: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.filter
+            .account_exclude
+            .extend(pubkeys.into_iter().map(Into::into));
+        self
+    }
+
+    /// Matches transactions using all of these accounts.
+    pub fn account_required(self, pubkeys: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.filter
+            .account_required
+            .extend(pubkeys.into_iter().map(Into::into));
+        self
+    }
+}
+
+pub struct BlocksFilterBuilder<'a> {
+    filter: &'a mut SubscribeRequestFilterBlocks,
+}
+
+impl BlocksFilterBuilder<'_> {
+    /// Only include transactions and accounts touching any of these accounts.
+    pub fn account_include(self, pubkeys: impl IntoIterator<Item = impl Into<String>>) -> Self {
+        self.filter
+            .account_include
+            .extend(pubkeys.into_iter().map(Into::into));
+        self
+    }
+
+    pub fn include_transactions(self, include: bool) -> Self {
+        self.filter.include_transactions = Some(include);
+        self
+    }
+
+    pub fn include_accounts(self, include: bool) -> Self {
+        self.filter.include_accounts = Some(include);
+        self
+    }
+
+    pub fn include_entries(self, include: bool) -> Self {
+        self.filter.include_entries = Some(include);
+        self
+    }
+}
+
+/// Checks a `SubscribeRequest` the way the builder does, also for requests
+/// built by hand.
+///
+/// Accounts and transactions filters without conditions are accepted, the
+/// server and [`crate::filter::FilterMatcher`] treat them as matching everything.
+pub fn validate(request: &SubscribeRequest) -> Result<(), RequestValidationError> {
+    let has_filters = !request.accounts.is_empty()
+        || !request.slots.is_empty()
+        || !request.transactions.is_empty()
+        || !request.transactions_status.is_empty()
+        || !request.blocks.is_empty()
+        || !request.blocks_meta.is_empty()
+        || !request.entry.is_empty();
+    if !has_filters {
+        return Err(RequestValidationError::NoFilters);
+    }
+
+    check_names("accounts", &request.accounts)?;
+    check_names("slots", &request.slots)?;
+    check_names("transactions", &request.transactions)?;
+    check_names("transactions_status", &request.transactions_status)?;
+    check_names("blocks", &request.blocks)?;
+    check_names("blocks_meta", &request.blocks_meta)?;
+    check_names("entry", &request.entry)?;
+
+    for (name, filter) in &request.accounts {
+        validate_accounts(name, filter)?;
+    }
+    for (name, filter) in request
+        .transactions
+        .iter()
+        .chain(&request.transactions_status)
+    {
+        validate_transactions(name, filter)?;
+    }
+    for (name, filter) in &request.blocks {
+        check_pubkeys(name, &filter.account_include)?;
+    }
+
+    let mut slices = request
+        .accounts_data_slice
+        .iter()
+        .map(|slice| match slice.offset.checked_add(slice.length) {
+            Some(end) => Ok((slice.offset, end)),
+            None => Err(RequestValidationError::DataSliceOverflow {
+                offset: slice.offset,
+                length: slice.length,
+            }),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    slices.sort_unstable();
+    for pair in slices.windows(2) {
+        if pair[0].1 > pair[1].0 {
+            return Err(RequestValidationError::OverlappingDataSlices { offset: pair[1].0 });
+        }
+    }
+
+    Ok(())
+}
+
+fn check_names<T>(
+    kind: &'static str,
+    filters: &HashMap<String, T>,
+) -> Result<(), RequestValidationError> {
+    if filters.keys().any(|name| name.is_empty()) {
+        return Err(RequestValidationError::EmptyFilterName { kind });
+    }
+    Ok(())
+}
+
+fn validate_accounts(
+    name: &str,
+    filter: &SubscribeRequestFilterAccounts,
+) -> Result<(), RequestValidationError> {
+    check_pubkeys(name, &filter.account)?;
+    check_pubkeys(name, &filter.owner)?;
+
+    let invalid = |reason: &str| RequestValidationError::InvalidFilter {
+        filter: name.to_string(),
+        reason: reason.to_string(),
+    };
+    let mut datasize = None;
+    for filter in &filter.filters {
+        match filter.filter.as_ref() {
+            None => return Err(invalid("accounts filter without a condition")),
+            Some(AccountsFilter::Memcmp(memcmp)) => {
+                let len = match memcmp.data.as_ref() {
+                    Some(MemcmpData::Bytes(bytes)) => bytes.len(),
+                    Some(MemcmpData::Base58(data)) => bs58::decode(data)
+                        .into_vec()
+                        .map_err(|_| invalid("memcmp data is not valid base58"))?
+                        .len(),
+                    Some(MemcmpData::Base64(data)) => STANDARD
+                        .decode(data)
+                        .map_err(|_| invalid("memcmp data is not valid base64"))?
+                        .len(),
+                    None => 0,
+                };
+                if len == 0 {
+                    return Err(invalid("memcmp without data"));
+                }
+            }
+            Some(AccountsFilter::Datasize(size)) => {
+                if let Some(previous) = datasize.filter(|previous| previous != size) {
+                    return Err(RequestValidationError::Conflict {
+                        filter: name.to_string(),
+                        reason: format!("datasize {previous} and {size} can never both match"),
+                    });
+                }
+                datasize = Some(*size);
+            }
+            Some(AccountsFilter::Lamports(lamports)) if lamports.cmp.is_none() => {
+                return Err(invalid("lamports filter without a comparison"));
+            }
+            Some(AccountsFilter::TokenAccountState(_) | AccountsFilter::Lamports(_)) => {}
+        }
+    }
+
+    Ok(())
+}
+
+fn validate_transactions(
+    name: &str,
+    filter: &SubscribeRequestFilterTransactions,
+) -> Result<(), RequestValidationError> {
+    if let Some(signature) = filter.signature.as_ref() {
+        if !is_base58_of_len(signature, SIGNATURE_LEN) {
+            return Err(RequestValidationError::InvalidSignature {
+                filter: name.to_string(),
+                value: signature.clone(),
+            });
+        }
+    }
+    check_pubkeys(name, &filter.account_include)?;
+    check_pubkeys(name, &filter.account_exclude)?;
+    check_pubkeys(name, &filter.account_required)?;
+
+    let conflicting = filter.account_exclude.iter().find(|pubkey| {
+        filter.account_include.contains(pubkey) || filter.account_required.contains(pubkey)
+    });
+    if let Some(pubkey) = conflicting {
+        return Err(RequestValidationError::Conflict {
+            filter: name.to_string(),
+            reason: format!("{pubkey} is both included and excluded"),
+        });
+    }
+
+    Ok(())
+}
+
+fn check_pubkeys(name: &str, pubkeys: &[String]) -> Result<(), RequestValidationError> {
+    match pubkeys
+        .iter()
+        .find(|pubkey| !is_base58_of_len(pubkey, PUBKEY_LEN))
+    {
+        Some(pubkey) => Err(RequestValidationError::InvalidPubkey {
+            filter: name.to_string(),
+            value: pubkey.clone(),
+        }),
+        None => Ok(()),
+    }
+}
+
+fn is_base58_of_len(value: &str, len: usize) -> bool {
+    bs58::decode(value)
+        .into_vec()
+        .is_ok_and(|bytes| bytes.len() == len)
+}
+
+impl SubscribeRequest {
+    /// A readable, multi-line description of the request, one filter per line.
+    pub fn summary(&self) -> RequestSummary<'_> {
+        RequestSummary(self)
+    }
+
+    /// Names of all filters of the request, sorted and deduplicated.
+    pub fn filter_names(&self) -> Vec<&str> {
+        let mut names = self
+            .accounts
+            .keys()
+            .chain(self.slots.keys())
+            .chain(self.transactions.keys())
+            .chain(self.transactions_status.keys())
+            .chain(self.blocks.keys())
+            .chain(self.blocks_meta.keys())
+            .chain(self.entry.keys())
+            .map(String::as_str)
+            .collect::<Vec<_>>();
+        names.sort_unstable();
+        names.dedup();
+        names
+    }
+}
+
+pub struct RequestSummary<'a>(&'a SubscribeRequest);
+
+impl fmt::Display for RequestSummary<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let request = self.0;
+
+        let commitment = request
+            .commitment
+            .and_then(|commitment| CommitmentLevel::try_from(commitment).ok())
+            .map_or("default", |commitment| commitment.as_str_name());
+        write!(f, "commitment: {commitment}")?;
+        if let Some(from_slot) = request.from_slot {
+            write!(f, ", from slot {from_slot}")?;
+        }
+        for slice in &request.accounts_data_slice {
+            write!(
+                f,
+                ", data slice {}..{}",
+                slice.offset,
+                slice.offset.saturating_add(slice.length)
+            )?;
+        }
+        writeln!(f)?;
+
+        for (name, filter) in sorted(&request.accounts) {
+            write!(f, "accounts `{name}`:")?;
+            write_list(f, "pubkey", &filter.account)?;
+            write_list(f, "owner", &filter.owner)?;
+            for filter in filter
+                .filters
+                .iter()
+                .filter_map(|filter| filter.filter.as_ref())
+            {
+                match filter {
+                    AccountsFilter::Memcmp(memcmp) => match memcmp.data.as_ref() {
+                        Some(MemcmpData::Bytes(bytes)) => {
+                            write!(f, " memcmp@{}={} bytes", memcmp.offset, bytes.len())?
+                        }
+                        Some(MemcmpData::Base58(data)) => {
+                            write!(f, " memcmp@{}={data}", memcmp.offset)?
+                        }
+                        Some(MemcmpData::Base64(data)) => {
+                            write!(f, " memcmp@{}=base64:{data}", memcmp.offset)?
+                        }
+                        None => write!(f, " memcmp@{}", memcmp.offset)?,
+                    },
+                    AccountsFilter::Datasize(size) => write!(f, " datasize={size}")?,
+                    AccountsFilter::TokenAccountState(_) => write!(f, " token-account")?,
+                    AccountsFilter::Lamports(lamports) => match lamports.cmp {
+                        Some(LamportsCmp::Eq(value)) => write!(f, " lamports=={value}")?,
+                        Some(LamportsCmp::Ne(value)) => write!(f, " lamports!={value}")?,
+                        Some(LamportsCmp::Lt(value)) => write!(f, " lamports<{value}")?,
+                        Some(LamportsCmp::Gt(value)) => write!(f, " lamports>{value}")?,
+                        None => write!(f, " lamports")?,
+                    },
+                }
+            }
+            if let Some(nonempty) = filter.nonempty_txn_signature {
+                write!(f, " nonempty_txn_signature={nonempty}")?;
+            }
+            writeln!(f)?;
+        }
+
+        for (name, filter) in sorted(&request.slots) {
+            write!(f, "slots `{name}`:")?;
+            write_flag(f, "filter_by_commitment", filter.filter_by_commitment)?;
+            write_flag(f, "interslot_updates", filter.interslot_updates)?;
+            writeln!(f)?;
+        }
+
+        for (kind, filters) in [
+            ("transactions", &request.transactions),
+            ("transactions_status", &request.transactions_status),
+        ] {
+            for (name, filter) in sorted(filters) {
+                write!(f, "{kind} `{name}`:")?;
+                write_flag(f, "vote", filter.vote)?;
+                write_flag(f, "failed", filter.failed)?;
+                if let Some(signature) = filter.signature.as_ref() {
+                    write!(f, " signature={signature}")?;
+                }
+                write_list(f, "include", &filter.account_include)?;
+                write_list(f, "exclude", &filter.account_exclude)?;
+                write_list(f, "required", &filter.account_required)?;
+                writeln!(f)?;
+            }
+        }
+
+        for (name, filter) in sorted(&request.blocks) {
+            write!(f, "blocks `{name}`:")?;
+            write_list(f, "include", &filter.account_include)?;
+            write_flag(f, "transactions", filter.include_transactions)?;
+            write_flag(f, "accounts", filter.include_accounts)?;
+            write_flag(f, "entries", filter.include_entries)?;
+            writeln!(f)?;
+        }
+
+        for (name, _) in sorted(&request.blocks_meta) {
+            writeln!(f, "blocks_meta `{name}`")?;
+        }
+        for (name, _) in sorted(&request.entry) {
+            writeln!(f, "entry `{name}`")?;
+        }
+
+        Ok(())
+    }
+}
+
+fn sorted<T>(filters: &HashMap<String, T>) -> Vec<(&String, &T)> {
+    let mut filters = filters.iter().collect::<Vec<_>>();
+    filters.sort_by_key(|(name, _)| *name);
+    filters
+}
+
+fn write_list(f: &mut fmt::Formatter<'_>, label: &str, values: &[String]) -> fmt::Result {
+    if values.is_empty() {
+        return Ok(());
+    }
+    write!(f, " {label}=[{}]", values.join(", "))
+}
+
+fn write_flag(f: &mut fmt::Formatter<'_>, label: &str, value: Option<bool>) -> fmt::Result {
+    match value {
+        Some(value) => write!(f, " {label}={value}"),
+        None => Ok(()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spl::{ACCOUNT_LEN, MINT_LEN};
+
+    fn pubkey(byte: u8) -> String {
+        bs58::encode([byte; PUBKEY_LEN]).into_string()
+    }
+
+    fn signature(byte: u8) -> String {
+        bs58::encode([byte; SIGNATURE_LEN]).into_string()
+    }
+
+    fn invalid_filter(reason: &str) -> RequestValidationError {
+        RequestValidationError::InvalidFilter {
+            filter: "accounts".to_string(),
+            reason: reason.to_string(),
+        }
+    }
+
+    #[test]
+    fn builds_valid_request() {
+        let mut builder = SubscribeRequestBuilder::new();
+        builder
+            .account("accounts")
+            .pubkey(pubkey(1))
+            .owner(pubkey(2))
+            .memcmp(0, [1, 2, 3])
+            .memcmp_base58(8, pubkey(3))
+            .datasize(ACCOUNT_LEN as u64)
+            .datasize(ACCOUNT_LEN as u64)
+            .token_account_state()
+            .lamports(LamportsCmp::Gt(1));
+        builder
+            .transactions("transactions")
+            .signature(signature(1))
+            .account_include([pubkey(1)])
+            .account_exclude([pubkey(2)])
+            .account_required([pubkey(3)]);
+        builder.blocks("blocks").account_include([pubkey(1)]);
+        builder
+            .commitment(CommitmentLevel::Confirmed)
+            .accounts_data_slice(0, 32)
+            .accounts_data_slice(32, 8);
+
+        let request = builder.build().unwrap();
+        assert_eq!(
+            request.filter_names(),
+            ["accounts", "blocks", "transactions"]
+        );
+    }
+
+    #[test]
+    fn accepts_filters_without_conditions() {
+        let mut builder = SubscribeRequestBuilder::new();
+        builder.account("accounts");
+        builder.transactions("transactions");
+        builder.transactions_status("transactions_status");
+
+        assert!(builder.build().is_ok());
+    }
+
+    #[test]
+    fn rejects_request_without_filters() {
+        let request = SubscribeRequest::default();
+
+        assert_eq!(validate(&request), Err(RequestValidationError::NoFilters));
+    }
+
+    #[test]
+    fn rejects_empty_filter_name() {
+        let mut builder = SubscribeRequestBuilder::new();
+        builder.slots("");
+
+        assert_eq!(
+            builder.build(),
+            Err(RequestValidationError::EmptyFilterName { kind: "slots" })
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_pubkeys() {
+        for value in ["not base58!", "1111", &signature(1)] {
+            let mut builder = SubscribeRequestBuilder::new();
+            builder.account("accounts").owner(value);
+
+            assert_eq!(
+                builder.build(),
+                Err(RequestValidationError::InvalidPubkey {
+                    filter: "accounts".to_string(),
+                    value: value.to_string(),
+                })
+            );
+        }
+
+        let mut builder = SubscribeRequestBuilder::new();
+        builder.blocks("blocks").account_include(["nope"]);
+        assert_eq!(
+            builder.build(),
+            Err(RequestValidationError::InvalidPubkey {
+                filter: "blocks".to_string(),
+                value: "nope".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_signature() {
+        let mut builder = SubscribeRequestBuilder::new();
+        builder.transactions_status("status").signature(pubkey(1));
+
+        assert_eq!(
+            builder.build(),
+            Err(RequestValidationError::InvalidSignature {
+                filter: "status".to_string(),
+                value: pubkey(1),
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_accounts_filters() {
+        let mut builder = SubscribeRequestBuilder::new();
+        builder.account("accounts").memcmp(0, []);
+        assert_eq!(builder.build(), Err(invalid_filter("memcmp without data")));
+
+        let mut builder = SubscribeRequestBuilder::new();
+        builder.account("accounts").memcmp_base58(0, "0OIl");
+        assert_eq!(
+            builder.build(),
+            Err(invalid_filter("memcmp data is not valid base58"))
+        );
+
+        let mut request = SubscribeRequest::default();
+        request.accounts.insert(
+            "accounts".to_string(),
+            SubscribeRequestFilterAccounts {
+                filters: vec![SubscribeRequestFilterAccountsFilter {
+                    filter: Some(AccountsFilter::Memcmp(
+                        SubscribeRequestFilterAccountsFilterMemcmp {
+                            offset: 0,
+                            data: Some(MemcmpData::Base64("%%%".to_string())),
+                        },
+                    )),
+                }],
+                ..Default::default()
+            },
+        );
+        assert_eq!(
+            validate(&request),
+            Err(invalid_filter("memcmp data is not valid base64"))
+        );
+
+        request.accounts.get_mut("accounts").unwrap().filters =
+            vec![SubscribeRequestFilterAccountsFilter { filter: None }];
+        assert_eq!(
+            validate(&request),
+            Err(invalid_filter("accounts filter without a condition"))
+        );
+
+        request.accounts.get_mut("accounts").unwrap().filters =
+            vec![SubscribeRequestFilterAccountsFilter {
+                filter: Some(AccountsFilter::Lamports(
+                    SubscribeRequestFilterAccountsFilterLamports { cmp: None },
+                )),
+            }];
+        assert_eq!(
+            validate(&request),
+            Err(invalid_filter("lamports filter without a comparison"))
+        );
+    }
+
+    #[test]
+    fn rejects_conflicting_filters() {
+        let mut builder = SubscribeRequestBuilder::new();
+        builder
+            .account("accounts")
+            .datasize(MINT_LEN as u64)
+            .datasize(ACCOUNT_LEN as u64);
+        assert_eq!(
+            builder.build(),
+            Err(RequestValidationError::Conflict {
+                filter: "accounts".to_string(),
+                reason: format!("datasize {MINT_LEN} and {ACCOUNT_LEN} can never both match"),
+            })
+        );
+
+        let mut builder = SubscribeRequestBuilder::new();
+        builder
+            .transactions("transactions")
+            .account_required([pubkey(1)])
+            .account_exclude([pubkey(1)]);
+        assert_eq!(
+            builder.build(),
+            Err(RequestValidationError::Conflict {
+                filter: "transactions".to_string(),
+                reason: format!("{} is both included and excluded", pubkey(1)),
+            })
+        );
+    }
+
+    #[test]
+    fn rejects_overlapping_data_slices() {
+        let mut builder = SubscribeRequestBuilder::new();
+        builder.entries();
+        builder
+            .accounts_data_slice(32, 8)
+            .accounts_data_slice(0, 33);
+
+        assert_eq!(
+            builder.build(),
+            Err(RequestValidationError::OverlappingDataSlices { offset: 32 })
+        );
+    }
+
+    #[test]
+    fn rejects_overflowing_data_slice() {
+        let mut builder = SubscribeRequestBuilder::new();
+        builder.entries();
+        builder.accounts_data_slice(u64::MAX, 1);
+
+        assert_eq!(
+            builder.build(),
+            Err(RequestValidationError::DataSliceOverflow {
+                offset: u64::MAX,
+                length: 1,
+            })
+        );
+        assert!(builder.request.summary().to_string().contains("data slice"));
+    }
+}
@@ -0,0 +1,497 @@
+use std::collections::{BTreeMap, HashSet};
+
+use crate::proto::geyser::{
+    subscribe_update::UpdateOneof, SlotStatus, SubscribeUpdate, SubscribeUpdateSlot,
+};
+
+/// Where a slot stands relative to the finalized chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ForkState {
+    /// Not decided yet, the slot descends from the latest root or is unknown relative to it.
+    Pending,
+    /// Finalized, part of the canonical chain.
+    Rooted,
+    Dead,
+    /// On a fork that can no longer become part of the finalized chain.
+    Abandoned,
+}
+
+#[derive(Debug, Clone)]
+pub struct SlotInfo {
+    pub slot: u64,
+    pub parent: Option<u64>,
+    /// Highest of processed, confirmed and finalized seen for the slot.
+    pub commitment: Option<SlotStatus>,
+    /// The slot received all its shreds.
+    pub completed: bool,
+    pub fork: ForkState,
+    pub dead_error: Option<String>,
+}
+
+impl SlotInfo {
+    fn new(slot: u64) -> Self {
+        Self {
+            slot,
+            parent: None,
+            commitment: None,
+            completed: false,
+            fork: ForkState::Pending,
+            dead_error: None,
+        }
+    }
+
+    fn raise_commitment(&mut self, status: SlotStatus) {
+        if self.commitment.is_none_or(|commitment| commitment < status) {
+            self.commitment = Some(status);
+        }
+    }
+
+    fn is_live(&self) -> bool {
+        matches!(self.fork, ForkState::Pending | ForkState::Rooted)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SlotEvent {
+    /// The slot was finalized.
+    Rooted {
+        slot: u64,
+    },
+    /// The finalized chain passed over the slot, no block for it will ever be finalized.
+    ///
+    /// A tracked slot that was skipped is abandoned as well, but only reported
+    /// as skipped.
+    Skipped {
+        slot: u64,
+    },
+    Dead {
+        slot: u64,
+        error: Option<String>,
+    },
+    /// The slot is on a fork that was left behind by a new root or that descends from a dead slot.
+    Abandoned {
+        slot: u64,
+    },
+}
+
+/// Tracks the fork tree built from `SubscribeUpdateSlot` updates.
+///
+/// Slots are linked through their `parent`. When a slot is finalized its
+/// ancestors are rooted, slots jumped over by the rooted chain are reported
+/// as skipped and every fork not descending from the new root is abandoned.
+/// Slots older than `retention_slots` behind the newest slot are forgotten.
+#[derive(Debug, Clone)]
+pub struct SlotTracker {
+    slots: BTreeMap<u64, SlotInfo>,
+    retention_slots: u64,
+    finalized_tip: Option<u64>,
+    /// Oldest slot from which the rooted chain is known without gaps.
+    root_floor: Option<u64>,
+    pruned_below: u64,
+}
+
+impl Default for SlotTracker {
+    fn default() -> Self {
+        Self::new(1024)
+    }
+}
+
+impl SlotTracker {
+    pub fn new(retention_slots: u64) -> Self {
+        Self {
+            slots: BTreeMap::new(),
+            retention_slots,
+            finalized_tip: None,
+            root_floor: None,
+            pruned_below: 0,
+        }
+    }
+
+    /// Slots kept behind the newest tracked slot.
+    pub fn retention_slots(&self) -> u64 {
+        self.retention_slots
+    }
+
+    /// Feeds any update, only slot updates change the tracker.
+    pub fn handle(&mut self, update: &SubscribeUpdate) -> Vec<SlotEvent> {
+        match update.update_oneof.as_ref() {
+            Some(UpdateOneof::Slot(slot)) => self.update(slot),
+            _ => Vec::new(),
+        }
+    }
+
+    pub fn update(&mut self, update: &SubscribeUpdateSlot) -> Vec<SlotEvent> {
+        let mut events = Vec::new();
+        if update.slot < self.pruned_below {
+            return events;
+        }
+
+        let info = self
+            .slots
+            .entry(update.slot)
+            .or_insert_with(|| SlotInfo::new(update.slot));
+        if info.parent.is_none() {
+            info.parent = update.parent;
+        }
+
+        match update.status() {
+            SlotStatus::SlotProcessed => info.raise_commitment(SlotStatus::SlotProcessed),
+            SlotStatus::SlotConfirmed => self.confirm(update.slot),
+            SlotStatus::SlotFinalized => self.root(update.slot, &mut events),
+            SlotStatus::SlotCompleted => info.completed = true,
+            SlotStatus::SlotDead => self.kill(update.slot, update.dead_error.clone(), &mut events),
+            SlotStatus::SlotFirstShredReceived | SlotStatus::SlotCreatedBank => {}
+        }
+
+        self.prune();
+        events
+    }
+
+    pub fn get(&self, slot: u64) -> Option<&SlotInfo> {
+        self.slots.get(&slot)
+    }
+
+    /// Oldest slot still tracked, older ones were pruned or never seen.
+    pub fn oldest_slot(&self) -> Option<u64> {
+        self.slots.keys().next().copied()
+    }
+
+    /// Ancestors of `slot` known to the tracker, nearest first.
+    pub fn ancestors(&self, slot: u64) -> impl Iterator<Item = &SlotInfo> {
+        let mut next = self.slots.get(&slot).and_then(|info| info.parent);
+        std::iter::from_fn(move || {
+            let info = self.slots.get(&next?)?;
+            next = info.parent;
+            Some(info)
+        })
+    }
+
+    /// Highest processed slot that is not dead or abandoned.
+    pub fn processed_tip(&self) -> Option<u64> {
+        self.tip(SlotStatus::SlotProcessed)
+    }
+
+    /// Highest confirmed slot that is not dead or abandoned.
+    pub fn confirmed_tip(&self) -> Option<u64> {
+        self.tip(SlotStatus::SlotConfirmed)
+    }
+
+    pub fn finalized_tip(&self) -> Option<u64> {
+        self.finalized_tip
+    }
+
+    /// Whether `slot` is part of the finalized chain.
+    ///
+    /// Returns `None` while that is not decided yet, that is for slots after
+    /// the latest root, and for slots older than what the tracker knows.
+    pub fn is_on_finalized_chain(&self, slot: u64) -> Option<bool> {
+        let root_floor = self.root_floor?;
+        if slot < root_floor.max(self.pruned_below) || slot > self.finalized_tip? {
+            return None;
+        }
+
+        Some(
+            self.slots
+                .get(&slot)
+                .is_some_and(|info| info.fork == ForkState::Rooted),
+        )
+    }
+
+    fn tip(&self, status: SlotStatus) -> Option<u64> {
+        self.slots
+            .values()
+            .rev()
+            .find(|info| info.is_live() && info.commitment.is_some_and(|x| x >= status))
+            .map(|info| info.slot)
+    }
+
+    /// A confirmed slot implies its ancestors are confirmed too.
+    fn confirm(&mut self, slot: u64) {
+        let mut next = Some(slot);
+        while let Some(info) = next.and_then(|slot| self.slots.get_mut(&slot)) {
+            if info.commitment >= Some(SlotStatus::SlotConfirmed) && info.slot != slot {
+                break;
+            }
+            info.raise_commitment(SlotStatus::SlotConfirmed);
+            next = info.parent;
+        }
+    }
+
+    fn root(&mut self, slot: u64, events: &mut Vec<SlotEvent>) {
+        if self.finalized_tip.is_some_and(|tip| slot <= tip) {
+            return;
+        }
+
+        // Walk down to the previous root, collecting the new part of the chain.
+        let mut chain = vec![slot];
+        let mut connected = false;
+        let mut next = self.slots.get(&slot).and_then(|info| info.parent);
+        while let Some(info) = next.and_then(|parent| self.slots.get(&parent)) {
+            if info.fork == ForkState::Rooted {
+                connected = true;
+                break;
+            }
+            chain.push(info.slot);
+            next = info.parent;
+        }
+
+        for &rooted in chain.iter().rev() {
+            let info = self
+                .slots
+                .get_mut(&rooted)
+                .expect("chain slots are tracked");
+            info.fork = ForkState::Rooted;
+            info.commitment = Some(SlotStatus::SlotFinalized);
+            // A malformed parent of `u64::MAX` has no slots after it to skip.
+            let first_skipped = info.parent.and_then(|parent| parent.checked_add(1));
+
+            if let Some(first_skipped) = first_skipped {
+                for skipped in first_skipped.max(self.pruned_below)..rooted {
+                    if let Some(info) = self.slots.get_mut(&skipped) {
+                        if info.fork == ForkState::Pending {
+                            info.fork = ForkState::Abandoned;
+                        }
+                    }
+                    events.push(SlotEvent::Skipped { slot: skipped });
+                }
+            }
+            events.push(SlotEvent::Rooted { slot: rooted });
+        }
+
+        if !connected || self.root_floor.is_none() {
+            self.root_floor = chain.last().copied();
+        }
+        self.finalized_tip = Some(slot);
+
+        let abandoned = self
+            .slots
+            .values()
+            .filter(|info| info.fork == ForkState::Pending && !self.descends_from_root(info))
+            .map(|info| info.slot)
+            .collect::<Vec<_>>();
+        for slot in abandoned {
+            self.abandon(slot, events);
+        }
+    }
+
+    fn descends_from_root(&self, info: &SlotInfo) -> bool {
+        let finalized_tip = self.finalized_tip.unwrap_or_default();
+        let mut info = info;
+        loop {
+            match info.fork {
+                ForkState::Rooted => return true,
+                ForkState::Dead | ForkState::Abandoned => return false,
+                ForkState::Pending if info.slot <= finalized_tip => return false,
+                ForkState::Pending => {}
+            }
+            match info.parent.and_then(|parent| self.slots.get(&parent)) {
+                Some(parent) => info = parent,
+                // Nothing contradicts it yet.
+                None => return true,
+            }
+        }
+    }
+
+    fn kill(&mut self, slot: u64, error: Option<String>, events: &mut Vec<SlotEvent>) {
+        let Some(info) = self.slots.get_mut(&slot) else {
+            return;
+        };
+        if info.fork != ForkState::Pending {
+            return;
+        }
+        info.fork = ForkState::Dead;
+        info.dead_error = error.clone();
+        events.push(SlotEvent::Dead { slot, error });
+
+        // Parents come before their children, so one pass in slot order finds
+        // every descendant.
+        let mut descendants = HashSet::from([slot]);
+        for (descendant, info) in self.slots.range(slot + 1..) {
+            if info
+                .parent
+                .is_some_and(|parent| descendants.contains(&parent))
+            {
+                descendants.insert(*descendant);
+            }
+        }
+        descendants.remove(&slot);
+        let mut descendants = descendants
+            .into_iter()
+            .filter(|descendant| self.slots[descendant].fork == ForkState::Pending)
+            .collect::<Vec<_>>();
+        descendants.sort_unstable();
+        for descendant in descendants {
+            self.abandon(descendant, events);
+        }
+    }
+
+    fn abandon(&mut self, slot: u64, events: &mut Vec<SlotEvent>) {
+        if let Some(info) = self.slots.get_mut(&slot) {
+            info.fork = ForkState::Abandoned;
+            events.push(SlotEvent::Abandoned { slot });
+        }
+    }
+
+    fn prune(&mut self) {
+        let Some(newest) = self.slots.keys().next_back().copied() else {
+            return;
+        };
+        let pruned_below = newest.saturating_sub(self.retention_slots);
+        if pruned_below <= self.pruned_below {
+            return;
+        }
+
+        self.pruned_below = pruned_below;
+        self.slots = self.slots.split_off(&pruned_below);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn update(slot: u64, parent: Option<u64>, status: SlotStatus) -> SubscribeUpdateSlot {
+        SubscribeUpdateSlot {
+            slot,
+            parent,
+            status: status as i32,
+            ..Default::default()
+        }
+    }
+
+    fn processed(tracker: &mut SlotTracker, slot: u64, parent: u64) -> Vec<SlotEvent> {
+        tracker.update(&update(slot, Some(parent), SlotStatus::SlotProcessed))
+    }
+
+    fn finalized(tracker: &mut SlotTracker, slot: u64) -> Vec<SlotEvent> {
+        tracker.update(&update(slot, None, SlotStatus::SlotFinalized))
+    }
+
+    #[test]
+    fn roots_chain() {
+        let mut tracker = SlotTracker::default();
+        for slot in 1..=3 {
+            processed(&mut tracker, slot, slot - 1);
+        }
+        tracker.update(&update(3, None, SlotStatus::SlotConfirmed));
+
+        assert_eq!(tracker.processed_tip(), Some(3));
+        assert_eq!(tracker.confirmed_tip(), Some(3));
+        assert_eq!(
+            tracker.get(1).unwrap().commitment,
+            Some(SlotStatus::SlotConfirmed)
+        );
+        assert_eq!(
+            finalized(&mut tracker, 2),
+            [SlotEvent::Rooted { slot: 1 }, SlotEvent::Rooted { slot: 2 }]
+        );
+        assert_eq!(tracker.finalized_tip(), Some(2));
+        assert_eq!(tracker.is_on_finalized_chain(1), Some(true));
+        assert_eq!(tracker.is_on_finalized_chain(3), None);
+        // Older roots are ignored.
+        assert!(finalized(&mut tracker, 1).is_empty());
+    }
+
+    #[test]
+    fn abandons_forks_left_behind_by_root() {
+        let mut tracker = SlotTracker::default();
+        processed(&mut tracker, 1, 0);
+        processed(&mut tracker, 2, 1);
+        // A fork off slot 1 that loses.
+        processed(&mut tracker, 3, 1);
+        processed(&mut tracker, 5, 3);
+        processed(&mut tracker, 4, 2);
+
+        assert_eq!(
+            finalized(&mut tracker, 4),
+            [
+                SlotEvent::Rooted { slot: 1 },
+                SlotEvent::Rooted { slot: 2 },
+                SlotEvent::Skipped { slot: 3 },
+                SlotEvent::Rooted { slot: 4 },
+                SlotEvent::Abandoned { slot: 5 },
+            ]
+        );
+        assert_eq!(tracker.get(3).unwrap().fork, ForkState::Abandoned);
+        assert_eq!(tracker.get(5).unwrap().fork, ForkState::Abandoned);
+        assert_eq!(tracker.processed_tip(), Some(4));
+        assert_eq!(tracker.is_on_finalized_chain(3), Some(false));
+    }
+
+    #[test]
+    fn reports_untracked_skipped_slots() {
+        let mut tracker = SlotTracker::default();
+        processed(&mut tracker, 1, 0);
+        processed(&mut tracker, 4, 1);
+
+        assert_eq!(
+            finalized(&mut tracker, 4),
+            [
+                SlotEvent::Rooted { slot: 1 },
+                SlotEvent::Skipped { slot: 2 },
+                SlotEvent::Skipped { slot: 3 },
+                SlotEvent::Rooted { slot: 4 },
+            ]
+        );
+        assert_eq!(tracker.is_on_finalized_chain(2), Some(false));
+    }
+
+    #[test]
+    fn roots_slot_with_max_parent() {
+        let mut tracker = SlotTracker::default();
+        processed(&mut tracker, 5, u64::MAX);
+
+        assert_eq!(finalized(&mut tracker, 5), [SlotEvent::Rooted { slot: 5 }]);
+    }
+
+    #[test]
+    fn abandons_descendants_of_dead_slot() {
+        let mut tracker = SlotTracker::default();
+        processed(&mut tracker, 1, 0);
+        processed(&mut tracker, 2, 1);
+        processed(&mut tracker, 3, 2);
+        processed(&mut tracker, 5, 3);
+        processed(&mut tracker, 4, 1);
+
+        let mut dead = update(2, None, SlotStatus::SlotDead);
+        dead.dead_error = Some("invalid block".to_string());
+        assert_eq!(
+            tracker.update(&dead),
+            [
+                SlotEvent::Dead {
+                    slot: 2,
+                    error: Some("invalid block".to_string()),
+                },
+                SlotEvent::Abandoned { slot: 3 },
+                SlotEvent::Abandoned { slot: 5 },
+            ]
+        );
+        assert_eq!(
+            tracker.get(2).unwrap().dead_error.as_deref(),
+            Some("invalid block")
+        );
+        assert_eq!(tracker.get(4).unwrap().fork, ForkState::Pending);
+        assert_eq!(tracker.processed_tip(), Some(4));
+        // A slot dies only once.
+        assert!(tracker.update(&dead).is_empty());
+    }
+
+    #[test]
+    fn prunes_old_slots() {
+        let mut tracker = SlotTracker::new(2);
+        for slot in 1..=5 {
+            processed(&mut tracker, slot, slot - 1);
+        }
+
+        assert_eq!(tracker.oldest_slot(), Some(3));
+        assert!(processed(&mut tracker, 1, 0).is_empty());
+        assert!(tracker.get(1).is_none());
+        assert_eq!(
+            tracker
+                .ancestors(5)
+                .map(|info| info.slot)
+                .collect::<Vec<_>>(),
+            [4, 3]
+        );
+    }
+}
@@ -0,0 +1,13 @@
+//! Byte layout of SPL Token and Token-2022 accounts.
+
+pub const ACCOUNT_LEN: usize = 165;
+pub const MINT_LEN: usize = 82;
+pub const MULTISIG_LEN: usize = 355;
+
+/// Offset of the `AccountState` byte in a token account, zero while uninitialized.
+pub const ACCOUNT_STATE_OFFSET: usize = 108;
+
+/// Token-2022 stores the account type right after the base account.
+pub const ACCOUNT_TYPE_OFFSET: usize = ACCOUNT_LEN;
+pub const ACCOUNT_TYPE_MINT: u8 = 1;
+pub const ACCOUNT_TYPE_ACCOUNT: u8 = 2;
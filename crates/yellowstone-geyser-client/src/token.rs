@@ -0,0 +1,836 @@
+//! Decoders for SPL Token and Token-2022 account data.
+
+use crate::{
+    proto::geyser::SubscribeUpdateAccountInfo,
+    spl::{
+        ACCOUNT_LEN, ACCOUNT_TYPE_ACCOUNT, ACCOUNT_TYPE_MINT, ACCOUNT_TYPE_OFFSET, MINT_LEN,
+        MULTISIG_LEN,
+    },
+};
+
+pub const TOKEN_PROGRAM_ID: [u8; 32] =
+    bs58::decode("TokenkegQfeZyiNwAJbNbGKPFXCWuBvf9Ss623VQ5DA".as_bytes())
+        .into_array_const_unwrap();
+pub const TOKEN_2022_PROGRAM_ID: [u8; 32] =
+    bs58::decode("TokenzQdBNbLqP5VEhdkAS6EPFLC1PHnBqCXEpPxuEb".as_bytes())
+        .into_array_const_unwrap();
+
+#[derive(Debug, Clone, PartialEq, Eq, thiserror::Error)]
+pub enum TokenDecodeError {
+    #[error("unexpected data length {0}")]
+    InvalidLength(usize),
+    #[error("unknown account type {0}")]
+    InvalidAccountType(u8),
+    #[error("unknown account state {0}")]
+    InvalidState(u8),
+    #[error("invalid option tag {0}")]
+    InvalidOption(u32),
+    #[error("extension {extension_type} is malformed")]
+    InvalidExtension { extension_type: u16 },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenProgram {
+    Token,
+    Token2022,
+}
+
+impl TokenProgram {
+    pub fn from_program_id(program_id: &[u8]) -> Option<Self> {
+        if program_id == TOKEN_PROGRAM_ID {
+            Some(Self::Token)
+        } else if program_id == TOKEN_2022_PROGRAM_ID {
+            Some(Self::Token2022)
+        } else {
+            None
+        }
+    }
+
+    pub fn program_id(&self) -> [u8; 32] {
+        match self {
+            Self::Token => TOKEN_PROGRAM_ID,
+            Self::Token2022 => TOKEN_2022_PROGRAM_ID,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenAccountState {
+    Uninitialized,
+    Initialized,
+    Frozen,
+}
+
+impl TryFrom<u8> for TokenAccountState {
+    type Error = TokenDecodeError;
+
+    fn try_from(state: u8) -> Result<Self, Self::Error> {
+        match state {
+            0 => Ok(Self::Uninitialized),
+            1 => Ok(Self::Initialized),
+            2 => Ok(Self::Frozen),
+            state => Err(TokenDecodeError::InvalidState(state)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenAccount {
+    pub program: TokenProgram,
+    pub mint: [u8; 32],
+    pub owner: [u8; 32],
+    pub amount: u64,
+    pub delegate: Option<[u8; 32]>,
+    pub state: TokenAccountState,
+    /// Rent-exempt reserve of wrapped SOL accounts.
+    pub is_native: Option<u64>,
+    pub delegated_amount: u64,
+    pub close_authority: Option<[u8; 32]>,
+    pub extensions: Vec<TokenExtension>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct TokenMint {
+    pub program: TokenProgram,
+    pub mint_authority: Option<[u8; 32]>,
+    pub supply: u64,
+    pub decimals: u8,
+    pub is_initialized: bool,
+    pub freeze_authority: Option<[u8; 32]>,
+    pub extensions: Vec<TokenExtension>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TokenMultisig {
+    pub program: TokenProgram,
+    /// Number of signers required.
+    pub m: u8,
+    /// Number of valid signers.
+    pub n: u8,
+    pub is_initialized: bool,
+    pub signers: Vec<[u8; 32]>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenAccountData {
+    Account(TokenAccount),
+    Mint(TokenMint),
+    Multisig(TokenMultisig),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TransferFee {
+    pub epoch: u64,
+    pub maximum_fee: u64,
+    pub transfer_fee_basis_points: u16,
+}
+
+/// A Token-2022 extension. Authorities set to the default pubkey are `None`.
+#[derive(Debug, Clone, PartialEq)]
+pub enum TokenExtension {
+    TransferFeeConfig {
+        transfer_fee_config_authority: Option<[u8; 32]>,
+        withdraw_withheld_authority: Option<[u8; 32]>,
+        withheld_amount: u64,
+        older_transfer_fee: TransferFee,
+        newer_transfer_fee: TransferFee,
+    },
+    TransferFeeAmount {
+        withheld_amount: u64,
+    },
+    MintCloseAuthority {
+        close_authority: Option<[u8; 32]>,
+    },
+    DefaultAccountState {
+        state: TokenAccountState,
+    },
+    ImmutableOwner,
+    MemoTransfer {
+        require_incoming_transfer_memos: bool,
+    },
+    NonTransferable,
+    InterestBearingConfig {
+        rate_authority: Option<[u8; 32]>,
+        initialization_timestamp: i64,
+        pre_update_average_rate: i16,
+        last_update_timestamp: i64,
+        current_rate: i16,
+    },
+    CpiGuard {
+        lock_cpi: bool,
+    },
+    PermanentDelegate {
+        delegate: Option<[u8; 32]>,
+    },
+    NonTransferableAccount,
+    TransferHook {
+        authority: Option<[u8; 32]>,
+        program_id: Option<[u8; 32]>,
+    },
+    TransferHookAccount {
+        transferring: bool,
+    },
+    MetadataPointer {
+        authority: Option<[u8; 32]>,
+        metadata_address: Option<[u8; 32]>,
+    },
+    TokenMetadata {
+        update_authority: Option<[u8; 32]>,
+        mint: [u8; 32],
+        name: String,
+        symbol: String,
+        uri: String,
+        additional_metadata: Vec<(String, String)>,
+    },
+    GroupPointer {
+        authority: Option<[u8; 32]>,
+        group_address: Option<[u8; 32]>,
+    },
+    TokenGroup {
+        update_authority: Option<[u8; 32]>,
+        mint: [u8; 32],
+        size: u64,
+        max_size: u64,
+    },
+    GroupMemberPointer {
+        authority: Option<[u8; 32]>,
+        member_address: Option<[u8; 32]>,
+    },
+    TokenGroupMember {
+        mint: [u8; 32],
+        group: [u8; 32],
+        member_number: u64,
+    },
+    ScaledUiAmount {
+        authority: Option<[u8; 32]>,
+        multiplier: f64,
+        new_multiplier_effective_timestamp: i64,
+        new_multiplier: f64,
+    },
+    Pausable {
+        authority: Option<[u8; 32]>,
+        paused: bool,
+    },
+    PausableAccount,
+    /// Confidential transfer extensions and anything newer, left undecoded.
+    Other {
+        extension_type: u16,
+        data: Vec<u8>,
+    },
+}
+
+impl TokenExtension {
+    fn decode(extension_type: u16, data: &[u8]) -> Result<Self, TokenDecodeError> {
+        let mut reader = AccountReader::new(data);
+        let invalid = || TokenDecodeError::InvalidExtension { extension_type };
+
+        let extension = match extension_type {
+            1 => Self::TransferFeeConfig {
+                transfer_fee_config_authority: reader.nonzero_pubkey().ok_or_else(invalid)?,
+                withdraw_withheld_authority: reader.nonzero_pubkey().ok_or_else(invalid)?,
+                withheld_amount: reader.u64().ok_or_else(invalid)?,
+                older_transfer_fee: reader.transfer_fee().ok_or_else(invalid)?,
+                newer_transfer_fee: reader.transfer_fee().ok_or_else(invalid)?,
+            },
+            2 => Self::TransferFeeAmount {
+                withheld_amount: reader.u64().ok_or_else(invalid)?,
+            },
+            3 => Self::MintCloseAuthority {
+                close_authority: reader.nonzero_pubkey().ok_or_else(invalid)?,
+            },
+            6 => Self::DefaultAccountState {
+                state: reader.u8().ok_or_else(invalid)?.try_into()?,
+            },
+            7 => Self::ImmutableOwner,
+            8 => Self::MemoTransfer {
+                require_incoming_transfer_memos: reader.bool().ok_or_else(invalid)?,
+            },
+            9 => Self::NonTransferable,
+            10 => Self::InterestBearingConfig {
+                rate_authority: reader.nonzero_pubkey().ok_or_else(invalid)?,
+                initialization_timestamp: reader.i64().ok_or_else(invalid)?,
+                pre_update_average_rate: reader.i16().ok_or_else(invalid)?,
+                last_update_timestamp: reader.i64().ok_or_else(invalid)?,
+                current_rate: reader.i16().ok_or_else(invalid)?,
+            },
+            11 => Self::CpiGuard {
+                lock_cpi: reader.bool().ok_or_else(invalid)?,
+            },
+            12 => Self::PermanentDelegate {
+                delegate: reader.nonzero_pubkey().ok_or_else(invalid)?,
+            },
+            13 => Self::NonTransferableAccount,
+            14 => Self::TransferHook {
+                authority: reader.nonzero_pubkey().ok_or_else(invalid)?,
+                program_id: reader.nonzero_pubkey().ok_or_else(invalid)?,
+            },
+            15 => Self::TransferHookAccount {
+                transferring: reader.bool().ok_or_else(invalid)?,
+            },
+            18 => Self::MetadataPointer {
+                authority: reader.nonzero_pubkey().ok_or_else(invalid)?,
+                metadata_address: reader.nonzero_pubkey().ok_or_else(invalid)?,
+            },
+            19 => {
+                let update_authority = reader.nonzero_pubkey().ok_or_else(invalid)?;
+                let mint = reader.pubkey().ok_or_else(invalid)?;
+                let name = reader.string().ok_or_else(invalid)?;
+                let symbol = reader.string().ok_or_else(invalid)?;
+                let uri = reader.string().ok_or_else(invalid)?;
+                let count = reader.u32().ok_or_else(invalid)?;
+                let additional_metadata = (0..count)
+                    .map(|_| Some((reader.string()?, reader.string()?)))
+                    .collect::<Option<_>>()
+                    .ok_or_else(invalid)?;
+                Self::TokenMetadata {
+                    update_authority,
+                    mint,
+                    name,
+                    symbol,
+                    uri,
+                    additional_metadata,
+                }
+            }
+            20 => Self::GroupPointer {
+                authority: reader.nonzero_pubkey().ok_or_else(invalid)?,
+                group_address: reader.nonzero_pubkey().ok_or_else(invalid)?,
+            },
+            21 => Self::TokenGroup {
+                update_authority: reader.nonzero_pubkey().ok_or_else(invalid)?,
+                mint: reader.pubkey().ok_or_else(invalid)?,
+                size: reader.u64().ok_or_else(invalid)?,
+                max_size: reader.u64().ok_or_else(invalid)?,
+            },
+            22 => Self::GroupMemberPointer {
+                authority: reader.nonzero_pubkey().ok_or_else(invalid)?,
+                member_address: reader.nonzero_pubkey().ok_or_else(invalid)?,
+            },
+            23 => Self::TokenGroupMember {
+                mint: reader.pubkey().ok_or_else(invalid)?,
+                group: reader.pubkey().ok_or_else(invalid)?,
+                member_number: reader.u64().ok_or_else(invalid)?,
+            },
+            25 => Self::ScaledUiAmount {
+                authority: reader.nonzero_pubkey().ok_or_else(invalid)?,
+                multiplier: reader.f64().ok_or_else(invalid)?,
+                new_multiplier_effective_timestamp: reader.i64().ok_or_else(invalid)?,
+                new_multiplier: reader.f64().ok_or_else(invalid)?,
+            },
+            26 => Self::Pausable {
+                authority: reader.nonzero_pubkey().ok_or_else(invalid)?,
+                paused: reader.bool().ok_or_else(invalid)?,
+            },
+            27 => Self::PausableAccount,
+            extension_type => Self::Other {
+                extension_type,
+                data: data.to_vec(),
+            },
+        };
+
+        Ok(extension)
+    }
+}
+
+/// Decodes `data` of an account owned by `program_id`.
+///
+/// Returns `None` when the owner is not a token program. Data cut by
+/// `accounts_data_slice` fails with [`TokenDecodeError::InvalidLength`].
+pub fn decode(
+    program_id: &[u8],
+    data: &[u8],
+) -> Option<Result<TokenAccountData, TokenDecodeError>> {
+    let program = TokenProgram::from_program_id(program_id)?;
+    Some(decode_with(program, data))
+}
+
+fn decode_with(program: TokenProgram, data: &[u8]) -> Result<TokenAccountData, TokenDecodeError> {
+    match data.len() {
+        ACCOUNT_LEN => decode_account(program, data).map(TokenAccountData::Account),
+        MINT_LEN => decode_mint(program, data).map(TokenAccountData::Mint),
+        MULTISIG_LEN => decode_multisig(program, data).map(TokenAccountData::Multisig),
+        len if program == TokenProgram::Token2022 && len > ACCOUNT_TYPE_OFFSET => {
+            match data[ACCOUNT_TYPE_OFFSET] {
+                ACCOUNT_TYPE_ACCOUNT => {
+                    decode_account(program, data).map(TokenAccountData::Account)
+                }
+                ACCOUNT_TYPE_MINT => decode_mint(program, data).map(TokenAccountData::Mint),
+                account_type => Err(TokenDecodeError::InvalidAccountType(account_type)),
+            }
+        }
+        len => Err(TokenDecodeError::InvalidLength(len)),
+    }
+}
+
+fn decode_account(program: TokenProgram, data: &[u8]) -> Result<TokenAccount, TokenDecodeError> {
+    let mut reader = AccountReader::new(&data[..ACCOUNT_LEN]);
+    let invalid = || TokenDecodeError::InvalidLength(data.len());
+
+    Ok(TokenAccount {
+        program,
+        mint: reader.pubkey().ok_or_else(invalid)?,
+        owner: reader.pubkey().ok_or_else(invalid)?,
+        amount: reader.u64().ok_or_else(invalid)?,
+        delegate: reader.coption_pubkey()?,
+        state: reader.u8().ok_or_else(invalid)?.try_into()?,
+        is_native: reader.coption_u64()?,
+        delegated_amount: reader.u64().ok_or_else(invalid)?,
+        close_authority: reader.coption_pubkey()?,
+        extensions: decode_extensions(data)?,
+    })
+}
+
+fn decode_mint(program: TokenProgram, data: &[u8]) -> Result<TokenMint, TokenDecodeError> {
+    let mut reader = AccountReader::new(&data[..MINT_LEN]);
+    let invalid = || TokenDecodeError::InvalidLength(data.len());
+
+    Ok(TokenMint {
+        program,
+        mint_authority: reader.coption_pubkey()?,
+        supply: reader.u64().ok_or_else(invalid)?,
+        decimals: reader.u8().ok_or_else(invalid)?,
+        is_initialized: reader.bool().ok_or_else(invalid)?,
+        freeze_authority: reader.coption_pubkey()?,
+        extensions: decode_extensions(data)?,
+    })
+}
+
+fn decode_multisig(program: TokenProgram, data: &[u8]) -> Result<TokenMultisig, TokenDecodeError> {
+    let mut reader = AccountReader::new(data);
+    let invalid = || TokenDecodeError::InvalidLength(data.len());
+
+    let m = reader.u8().ok_or_else(invalid)?;
+    let n = reader.u8().ok_or_else(invalid)?;
+    let is_initialized = reader.bool().ok_or_else(invalid)?;
+    let signers = (0..n.min(11))
+        .map(|_| reader.pubkey())
+        .collect::<Option<_>>()
+        .ok_or_else(invalid)?;
+
+    Ok(TokenMultisig {
+        program,
+        m,
+        n,
+        is_initialized,
+        signers,
+    })
+}
+
+/// Reads the type-length-value extensions following the account type.
+fn decode_extensions(data: &[u8]) -> Result<Vec<TokenExtension>, TokenDecodeError> {
+    let mut extensions = Vec::new();
+    let Some(tlv) = data.get(ACCOUNT_TYPE_OFFSET + 1..) else {
+        return Ok(extensions);
+    };
+
+    let mut reader = AccountReader::new(tlv);
+    while let (Some(extension_type), Some(len)) = (reader.u16(), reader.u16()) {
+        // The rest of the account is zeroed space for future extensions.
+        if extension_type == 0 {
+            break;
+        }
+        let value = reader
+            .take(len.into())
+            .ok_or(TokenDecodeError::InvalidExtension { extension_type })?;
+        extensions.push(TokenExtension::decode(extension_type, value)?);
+    }
+
+    Ok(extensions)
+}
+
+/// Little-endian reader over token account data with its `COption` and
+/// borsh layouts, `None` once the data runs out.
+struct AccountReader<'a> {
+    data: &'a [u8],
+    /// Length of the whole data, reported by [`TokenDecodeError::InvalidLength`].
+    len: usize,
+}
+
+impl<'a> AccountReader<'a> {
+    fn new(data: &'a [u8]) -> Self {
+        Self {
+            data,
+            len: data.len(),
+        }
+    }
+
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        if self.data.len() < len {
+            return None;
+        }
+        let (taken, rest) = self.data.split_at(len);
+        self.data = rest;
+        Some(taken)
+    }
+
+    fn array<const N: usize>(&mut self) -> Option<[u8; N]> {
+        self.take(N)?.try_into().ok()
+    }
+
+    fn u8(&mut self) -> Option<u8> {
+        self.array::<1>().map(|[byte]| byte)
+    }
+
+    fn bool(&mut self) -> Option<bool> {
+        self.u8().map(|byte| byte != 0)
+    }
+
+    fn u16(&mut self) -> Option<u16> {
+        self.array().map(u16::from_le_bytes)
+    }
+
+    fn i16(&mut self) -> Option<i16> {
+        self.array().map(i16::from_le_bytes)
+    }
+
+    fn u32(&mut self) -> Option<u32> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    fn u64(&mut self) -> Option<u64> {
+        self.array().map(u64::from_le_bytes)
+    }
+
+    fn i64(&mut self) -> Option<i64> {
+        self.array().map(i64::from_le_bytes)
+    }
+
+    fn f64(&mut self) -> Option<f64> {
+        self.array().map(f64::from_le_bytes)
+    }
+
+    fn pubkey(&mut self) -> Option<[u8; 32]> {
+        self.array()
+    }
+
+    /// A pubkey where all zeroes stands for `None`.
+    fn nonzero_pubkey(&mut self) -> Option<Option<[u8; 32]>> {
+        self.pubkey()
+            .map(|pubkey| (pubkey != [0; 32]).then_some(pubkey))
+    }
+
+    /// A borsh string with its u32 length prefix.
+    fn string(&mut self) -> Option<String> {
+        let len = self.u32()?;
+        let bytes = self.take(len.try_into().ok()?)?;
+        String::from_utf8(bytes.to_vec()).ok()
+    }
+
+    /// `COption` tag, the value is present either way.
+    fn coption_tag(&mut self) -> Result<bool, TokenDecodeError> {
+        match self.u32() {
+            Some(0) => Ok(false),
+            Some(1) => Ok(true),
+            Some(tag) => Err(TokenDecodeError::InvalidOption(tag)),
+            None => Err(TokenDecodeError::InvalidLength(self.len)),
+        }
+    }
+
+    fn coption_pubkey(&mut self) -> Result<Option<[u8; 32]>, TokenDecodeError> {
+        let some = self.coption_tag()?;
+        let pubkey = self
+            .pubkey()
+            .ok_or(TokenDecodeError::InvalidLength(self.len))?;
+        Ok(some.then_some(pubkey))
+    }
+
+    fn coption_u64(&mut self) -> Result<Option<u64>, TokenDecodeError> {
+        let some = self.coption_tag()?;
+        let value = self
+            .u64()
+            .ok_or(TokenDecodeError::InvalidLength(self.len))?;
+        Ok(some.then_some(value))
+    }
+
+    fn transfer_fee(&mut self) -> Option<TransferFee> {
+        Some(TransferFee {
+            epoch: self.u64()?,
+            maximum_fee: self.u64()?,
+            transfer_fee_basis_points: self.u16()?,
+        })
+    }
+}
+
+impl SubscribeUpdateAccountInfo {
+    /// Decodes `data` if the account is owned by SPL Token or Token-2022.
+    pub fn decode_token(&self) -> Option<Result<TokenAccountData, TokenDecodeError>> {
+        decode(&self.owner, &self.data)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A token account with a delegate, a native reserve and a close authority.
+    fn token_account() -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend([1; 32]);
+        data.extend([2; 32]);
+        data.extend(500u64.to_le_bytes());
+        data.extend(1u32.to_le_bytes());
+        data.extend([3; 32]);
+        data.push(2);
+        data.extend(1u32.to_le_bytes());
+        data.extend(2_039_280u64.to_le_bytes());
+        data.extend(100u64.to_le_bytes());
+        data.extend(1u32.to_le_bytes());
+        data.extend([4; 32]);
+        data
+    }
+
+    /// A mint with a mint authority and without a freeze authority.
+    fn mint() -> Vec<u8> {
+        let mut data = Vec::new();
+        data.extend(1u32.to_le_bytes());
+        data.extend([5; 32]);
+        data.extend(1_000_000u64.to_le_bytes());
+        data.push(6);
+        data.push(1);
+        data.extend(0u32.to_le_bytes());
+        data.extend([0; 32]);
+        data
+    }
+
+    /// Pads `data` to the base account length and appends the account type
+    /// and the extensions, followed by zeroed space.
+    fn with_extensions(
+        mut data: Vec<u8>,
+        account_type: u8,
+        extensions: &[(u16, Vec<u8>)],
+    ) -> Vec<u8> {
+        data.resize(ACCOUNT_TYPE_OFFSET, 0);
+        data.push(account_type);
+        for (extension_type, value) in extensions {
+            data.extend(extension_type.to_le_bytes());
+            data.extend((value.len() as u16).to_le_bytes());
+            data.extend(value);
+        }
+        data.extend([0; 4]);
+        data
+    }
+
+    fn borsh_string(data: &mut Vec<u8>, value: &str) {
+        data.extend((value.len() as u32).to_le_bytes());
+        data.extend(value.as_bytes());
+    }
+
+    fn transfer_fee(data: &mut Vec<u8>, epoch: u64, maximum_fee: u64, basis_points: u16) {
+        data.extend(epoch.to_le_bytes());
+        data.extend(maximum_fee.to_le_bytes());
+        data.extend(basis_points.to_le_bytes());
+    }
+
+    #[test]
+    fn decodes_account() {
+        let data = token_account();
+        assert_eq!(data.len(), ACCOUNT_LEN);
+
+        assert_eq!(
+            decode(&TOKEN_PROGRAM_ID, &data),
+            Some(Ok(TokenAccountData::Account(TokenAccount {
+                program: TokenProgram::Token,
+                mint: [1; 32],
+                owner: [2; 32],
+                amount: 500,
+                delegate: Some([3; 32]),
+                state: TokenAccountState::Frozen,
+                is_native: Some(2_039_280),
+                delegated_amount: 100,
+                close_authority: Some([4; 32]),
+                extensions: Vec::new(),
+            })))
+        );
+    }
+
+    #[test]
+    fn decodes_mint() {
+        let data = mint();
+        assert_eq!(data.len(), MINT_LEN);
+
+        assert_eq!(
+            decode(&TOKEN_PROGRAM_ID, &data),
+            Some(Ok(TokenAccountData::Mint(TokenMint {
+                program: TokenProgram::Token,
+                mint_authority: Some([5; 32]),
+                supply: 1_000_000,
+                decimals: 6,
+                is_initialized: true,
+                freeze_authority: None,
+                extensions: Vec::new(),
+            })))
+        );
+    }
+
+    #[test]
+    fn decodes_token_2022_account_extensions() {
+        let data = with_extensions(
+            token_account(),
+            ACCOUNT_TYPE_ACCOUNT,
+            &[
+                (2, 7u64.to_le_bytes().to_vec()),
+                (7, Vec::new()),
+                (200, vec![1, 2, 3]),
+            ],
+        );
+
+        let Some(Ok(TokenAccountData::Account(account))) = decode(&TOKEN_2022_PROGRAM_ID, &data)
+        else {
+            panic!("expected a token account");
+        };
+        assert_eq!(account.program, TokenProgram::Token2022);
+        assert_eq!(account.amount, 500);
+        assert_eq!(
+            account.extensions,
+            [
+                TokenExtension::TransferFeeAmount { withheld_amount: 7 },
+                TokenExtension::ImmutableOwner,
+                // Unknown extensions are kept as is.
+                TokenExtension::Other {
+                    extension_type: 200,
+                    data: vec![1, 2, 3],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn decodes_token_2022_mint_extensions() {
+        let mut fee_config = Vec::new();
+        fee_config.extend([7; 32]);
+        fee_config.extend([0; 32]);
+        fee_config.extend(9u64.to_le_bytes());
+        transfer_fee(&mut fee_config, 1, 10, 50);
+        transfer_fee(&mut fee_config, 2, 20, 100);
+
+        let mut metadata = Vec::new();
+        metadata.extend([0; 32]);
+        metadata.extend([8; 32]);
+        borsh_string(&mut metadata, "Token");
+        borsh_string(&mut metadata, "TKN");
+        borsh_string(&mut metadata, "https://example.com/token.json");
+        metadata.extend(1u32.to_le_bytes());
+        borsh_string(&mut metadata, "key");
+        borsh_string(&mut metadata, "value");
+
+        let data = with_extensions(
+            mint(),
+            ACCOUNT_TYPE_MINT,
+            &[(1, fee_config), (19, metadata)],
+        );
+
+        let Some(Ok(TokenAccountData::Mint(mint))) = decode(&TOKEN_2022_PROGRAM_ID, &data) else {
+            panic!("expected a mint");
+        };
+        assert_eq!(mint.program, TokenProgram::Token2022);
+        assert_eq!(mint.decimals, 6);
+        assert_eq!(
+            mint.extensions,
+            [
+                TokenExtension::TransferFeeConfig {
+                    transfer_fee_config_authority: Some([7; 32]),
+                    withdraw_withheld_authority: None,
+                    withheld_amount: 9,
+                    older_transfer_fee: TransferFee {
+                        epoch: 1,
+                        maximum_fee: 10,
+                        transfer_fee_basis_points: 50,
+                    },
+                    newer_transfer_fee: TransferFee {
+                        epoch: 2,
+                        maximum_fee: 20,
+                        transfer_fee_basis_points: 100,
+                    },
+                },
+                TokenExtension::TokenMetadata {
+                    update_authority: None,
+                    mint: [8; 32],
+                    name: "Token".to_string(),
+                    symbol: "TKN".to_string(),
+                    uri: "https://example.com/token.json".to_string(),
+                    additional_metadata: vec![("key".to_string(), "value".to_string())],
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn decodes_multisig_before_token_2022_account_type() {
+        let mut data = vec![2, 3, 1];
+        for signer in 1..=3 {
+            data.extend([signer; 32]);
+        }
+        data.resize(MULTISIG_LEN, 0);
+        // Token-2022 pads extended accounts that would be exactly as long as
+        // a multisig, so this is not an account type.
+        data[ACCOUNT_TYPE_OFFSET] = ACCOUNT_TYPE_ACCOUNT;
+
+        assert_eq!(
+            decode(&TOKEN_2022_PROGRAM_ID, &data),
+            Some(Ok(TokenAccountData::Multisig(TokenMultisig {
+                program: TokenProgram::Token2022,
+                m: 2,
+                n: 3,
+                is_initialized: true,
+                signers: vec![[1; 32], [2; 32], [3; 32]],
+            })))
+        );
+    }
+
+    #[test]
+    fn rejects_truncated_data() {
+        let data = token_account();
+
+        assert_eq!(
+            decode(&TOKEN_PROGRAM_ID, &data[..100]),
+            Some(Err(TokenDecodeError::InvalidLength(100)))
+        );
+        // Only Token-2022 accounts are longer than the base layout.
+        let mut extended = data.clone();
+        extended.push(ACCOUNT_TYPE_ACCOUNT);
+        assert_eq!(
+            decode(&TOKEN_PROGRAM_ID, &extended),
+            Some(Err(TokenDecodeError::InvalidLength(ACCOUNT_LEN + 1)))
+        );
+
+        // An extension running past the end of the data.
+        let mut data = with_extensions(data, ACCOUNT_TYPE_ACCOUNT, &[]);
+        data.truncate(ACCOUNT_TYPE_OFFSET + 1);
+        data.extend(2u16.to_le_bytes());
+        data.extend(8u16.to_le_bytes());
+        data.extend([0; 4]);
+        assert_eq!(
+            decode(&TOKEN_2022_PROGRAM_ID, &data),
+            Some(Err(TokenDecodeError::InvalidExtension {
+                extension_type: 2
+            }))
+        );
+    }
+
+    #[test]
+    fn rejects_invalid_fields() {
+        let mut data = token_account();
+        data[108] = 3;
+        assert_eq!(
+            decode(&TOKEN_PROGRAM_ID, &data),
+            Some(Err(TokenDecodeError::InvalidState(3)))
+        );
+
+        let mut data = token_account();
+        data[72] = 2;
+        assert_eq!(
+            decode(&TOKEN_PROGRAM_ID, &data),
+            Some(Err(TokenDecodeError::InvalidOption(2)))
+        );
+
+        let data = with_extensions(token_account(), 3, &[]);
+        assert_eq!(
+            decode(&TOKEN_2022_PROGRAM_ID, &data),
+            Some(Err(TokenDecodeError::InvalidAccountType(3)))
+        );
+    }
+
+    #[test]
+    fn ignores_other_owners() {
+        assert_eq!(decode(&[0; 32], &token_account()), None);
+    }
+}
@@ -0,0 +1,455 @@
+#![cfg(feature = "mock")]
+
+use std::time::{Duration, Instant};
+use tonic::{Code, Status};
+use yellowstone_geyser_client::{
+    error::GeyserClientError,
+    failover::{FailoverConfig, FailoverSubscription, SwitchReason, Switchover},
+    keepalive::KeepaliveConfig,
+    mock::{MockEvent, MockGeyser, MockRpc, MockServer},
+    proto::geyser::{
+        subscribe_update::UpdateOneof, SubscribeRequest, SubscribeUpdate, SubscribeUpdateSlot,
+    },
+    racing::{RacingConfig, RacingSubscription},
+    reconnect::{ReconnectConfig, ResilientSubscription},
+    request_builder::SubscribeRequestBuilder,
+    GeyserClient, GeyserClientConfig,
+};
+
+fn slot(slot: u64) -> MockEvent {
+    MockEvent::Update(SubscribeUpdate {
+        update_oneof: Some(UpdateOneof::Slot(SubscribeUpdateSlot {
+            slot,
+            ..Default::default()
+        })),
+        ..Default::default()
+    })
+}
+
+/// Requests are read in the background, wait for `count` of them to arrive.
+async fn requests(mock: &MockGeyser, count: usize) -> Vec<SubscribeRequest> {
+    for _ in 0..100 {
+        let requests = mock.requests();
+        if requests.len() >= count {
+            return requests;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("expected {count} requests, got {:?}", mock.requests());
+}
+
+fn request() -> SubscribeRequest {
+    let mut builder = SubscribeRequestBuilder::new();
+    builder.slots("slots");
+    builder.build().unwrap()
+}
+
+/// Fails over without reconnecting, probing only once unless a test asks for more.
+fn failover_config() -> FailoverConfig {
+    FailoverConfig {
+        reconnect: ReconnectConfig::disabled(),
+        health_check_interval: Duration::from_secs(60),
+        probe_timeout: Duration::from_secs(1),
+        stall_timeout: None,
+        ..Default::default()
+    }
+}
+
+fn failover(servers: [&MockServer; 2], config: FailoverConfig) -> FailoverSubscription {
+    let clients = servers
+        .iter()
+        .map(|server| GeyserClient::new(server.endpoint(), None).unwrap())
+        .collect();
+    FailoverSubscription::new(clients, request(), config).unwrap()
+}
+
+/// A standby that can replay from any slot.
+fn standby() -> MockGeyser {
+    let mock = MockGeyser::new();
+    mock.set_first_available(Some(0));
+    mock
+}
+
+async fn failover_slots(failover: &mut FailoverSubscription, count: usize) -> Vec<u64> {
+    let mut slots = Vec::new();
+    for _ in 0..count {
+        let update = failover.message().await.unwrap().unwrap();
+        slots.push(update.slot().unwrap());
+    }
+    slots
+}
+
+#[tokio::test]
+async fn delivers_updates() {
+    let mock = MockGeyser::new();
+    mock.push_subscription([slot(1), slot(2), MockEvent::End]);
+    let server = mock.clone().serve().await.unwrap();
+
+    let mut client = GeyserClient::new(server.endpoint(), None).unwrap();
+    let mut stream = client.subscribe(request()).await.unwrap();
+
+    let mut slots = Vec::new();
+    while let Some(update) = stream.message().await.unwrap() {
+        slots.push(update.slot());
+    }
+    assert_eq!(slots, [Some(1), Some(2)]);
+    assert_eq!(requests(&mock, 1).await, [request()]);
+}
+
+#[tokio::test]
+async fn reports_status() {
+    let mock = MockGeyser::new();
+    mock.push_subscription([
+        slot(1),
+        MockEvent::Status(Code::Unavailable, "going away".to_string()),
+    ]);
+    let server = mock.serve().await.unwrap();
+
+    let mut client = GeyserClient::new(server.endpoint(), None).unwrap();
+    let mut stream = client.subscribe_with_keepalive(request()).await.unwrap();
+
+    assert_eq!(stream.message().await.unwrap().unwrap().slot(), Some(1));
+    let error = stream.message().await.unwrap_err();
+    assert_eq!(error.code(), Some(Code::Unavailable));
+    assert_eq!(error.status().unwrap().message(), "going away");
+}
+
+#[tokio::test]
+async fn reports_reset() {
+    let mock = MockGeyser::new();
+    mock.push_subscription([
+        slot(1),
+        MockEvent::Delay(Duration::from_millis(50)),
+        MockEvent::Reset,
+    ]);
+    let server = mock.serve().await.unwrap();
+
+    let mut client = GeyserClient::new(server.endpoint(), None).unwrap();
+    let mut stream = client.subscribe_with_keepalive(request()).await.unwrap();
+
+    assert_eq!(stream.message().await.unwrap().unwrap().slot(), Some(1));
+    // A reset stream fails without a status from the server, it does not end cleanly.
+    let error = stream.message().await.unwrap_err();
+    assert!(matches!(error, GeyserClientError::Status(_)), "{error:?}");
+}
+
+#[tokio::test]
+async fn reports_malformed_update() {
+    let mock = MockGeyser::new();
+    mock.push_subscription([slot(1), MockEvent::Malformed(vec![0x0a, 0xff])]);
+    let server = mock.serve().await.unwrap();
+
+    let mut client = GeyserClient::new(server.endpoint(), None).unwrap();
+    let mut stream = client.subscribe_with_keepalive(request()).await.unwrap();
+
+    assert_eq!(stream.message().await.unwrap().unwrap().slot(), Some(1));
+    let error = stream.message().await.unwrap_err();
+    assert!(matches!(error, GeyserClientError::Decode(_)), "{error:?}");
+}
+
+#[tokio::test]
+async fn reconnects_from_last_slot() {
+    let mock = MockGeyser::new();
+    mock.set_first_available(Some(5));
+    mock.push_subscription([
+        slot(10),
+        slot(11),
+        MockEvent::Status(Code::Unavailable, "going away".to_string()),
+    ]);
+    mock.push_subscription([slot(11), slot(12)]);
+    let server = mock.clone().serve().await.unwrap();
+
+    let client = GeyserClient::new(server.endpoint(), None).unwrap();
+    let config = ReconnectConfig {
+        initial_backoff: Duration::from_millis(10),
+        ..Default::default()
+    };
+    let mut subscription = ResilientSubscription::new(client, request(), config);
+
+    let mut slots = Vec::new();
+    for _ in 0..4 {
+        let update = subscription.message().await.unwrap().unwrap();
+        slots.push(update.slot().unwrap());
+    }
+    assert_eq!(slots, [10, 11, 11, 12]);
+    assert_eq!(subscription.reconnects(), 1);
+    assert_eq!(subscription.unrecoverable_slots(), 0);
+    assert_eq!(mock.subscribes(), 2);
+
+    let from_slots = requests(&mock, 2)
+        .await
+        .iter()
+        .map(|request| request.from_slot)
+        .collect::<Vec<_>>();
+    assert_eq!(from_slots, [None, Some(11)]);
+}
+
+#[tokio::test]
+async fn counts_slots_lost_before_replay_history() {
+    let mock = MockGeyser::new();
+    mock.set_first_available(Some(20));
+    mock.push_subscription([
+        slot(10),
+        MockEvent::Status(Code::Unavailable, "going away".to_string()),
+    ]);
+    mock.push_subscription([slot(20)]);
+    let server = mock.clone().serve().await.unwrap();
+
+    let client = GeyserClient::new(server.endpoint(), None).unwrap();
+    let config = ReconnectConfig {
+        initial_backoff: Duration::from_millis(10),
+        ..Default::default()
+    };
+    let mut subscription = ResilientSubscription::new(client, request(), config);
+
+    assert_eq!(
+        subscription.message().await.unwrap().unwrap().slot(),
+        Some(10)
+    );
+    assert_eq!(
+        subscription.message().await.unwrap().unwrap().slot(),
+        Some(20)
+    );
+    assert_eq!(subscription.unrecoverable_slots(), 10);
+    assert_eq!(requests(&mock, 2).await[1].from_slot, Some(20));
+}
+
+#[tokio::test]
+async fn races_endpoints_serving_the_same_updates() {
+    let fast = MockGeyser::new();
+    fast.push_subscription([slot(1), slot(2), slot(3)]);
+    let slow = MockGeyser::new();
+    slow.push_subscription([
+        MockEvent::Delay(Duration::from_millis(100)),
+        slot(1),
+        slot(2),
+        slot(3),
+    ]);
+    let fast_server = fast.serve().await.unwrap();
+    let slow_server = slow.serve().await.unwrap();
+
+    let clients = vec![
+        GeyserClient::new(fast_server.endpoint(), None).unwrap(),
+        GeyserClient::new(slow_server.endpoint(), None).unwrap(),
+    ];
+    let mut racing = RacingSubscription::new(clients, request(), RacingConfig::default());
+
+    let mut updates = Vec::new();
+    for _ in 0..3 {
+        let raced = racing.message().await.unwrap().unwrap();
+        updates.push((raced.endpoint, raced.update.slot()));
+    }
+    assert_eq!(updates, [(0, Some(1)), (0, Some(2)), (0, Some(3))]);
+
+    // The slow copies arrive later and are dropped, nothing else comes out.
+    let next = tokio::time::timeout(Duration::from_millis(300), racing.message()).await;
+    assert!(next.is_err(), "{next:?}");
+    let stats = racing.stats();
+    assert_eq!([stats[0].wins, stats[1].wins], [3, 0]);
+    assert_eq!([stats[0].duplicates, stats[1].duplicates], [0, 3]);
+    assert!(stats[1].average_lag().unwrap() >= Duration::from_millis(50));
+}
+
+#[tokio::test]
+async fn fails_over_from_last_slot() {
+    let active = MockGeyser::new();
+    // Fails after the first probes, which would otherwise restart the switch.
+    active.push_subscription([
+        slot(1),
+        slot(2),
+        MockEvent::Delay(Duration::from_millis(100)),
+        MockEvent::Status(Code::Unavailable, "going away".to_string()),
+    ]);
+    let standby = standby();
+    standby.push_subscription([slot(2), slot(3)]);
+    let active_server = active.clone().serve().await.unwrap();
+    let standby_server = standby.clone().serve().await.unwrap();
+
+    let mut failover = failover([&active_server, &standby_server], failover_config());
+
+    assert_eq!(failover_slots(&mut failover, 4).await, [1, 2, 2, 3]);
+    assert_eq!(failover.active(), 1);
+    assert_eq!(failover.switchovers(), 1);
+    assert_eq!(
+        failover.last_switch(),
+        Some(Switchover {
+            from: 0,
+            to: 1,
+            reason: SwitchReason::Failed,
+        })
+    );
+    assert_eq!(failover.last_slot(), Some(3));
+    assert_eq!(requests(&active, 1).await[0].from_slot, None);
+    assert_eq!(requests(&standby, 1).await[0].from_slot, Some(2));
+}
+
+#[tokio::test]
+async fn switches_away_from_stalled_stream() {
+    let active = MockGeyser::new();
+    active.push_subscription([slot(1)]);
+    let standby = standby();
+    standby.push_subscription([slot(2)]);
+    let active_server = active.serve().await.unwrap();
+    let standby_server = standby.clone().serve().await.unwrap();
+
+    let config = FailoverConfig {
+        stall_timeout: Some(Duration::from_millis(100)),
+        ..failover_config()
+    };
+    let mut failover = failover([&active_server, &standby_server], config);
+
+    assert_eq!(failover_slots(&mut failover, 2).await, [1, 2]);
+    assert_eq!(
+        failover.last_switch().map(|switch| switch.reason),
+        Some(SwitchReason::Stalled)
+    );
+    assert_eq!(requests(&standby, 1).await[0].from_slot, Some(1));
+}
+
+#[tokio::test]
+async fn stays_without_healthier_standby() {
+    let active = MockGeyser::new();
+    active.push_subscription([slot(1)]);
+    // The failed probe ranks the standby below the stalled active endpoint.
+    let standby = standby();
+    standby.fail_next(MockRpc::Ping, Status::unavailable("down"));
+    let active_server = active.serve().await.unwrap();
+    let standby_server = standby.clone().serve().await.unwrap();
+
+    let config = FailoverConfig {
+        stall_timeout: Some(Duration::from_millis(200)),
+        ..failover_config()
+    };
+    let mut failover = failover([&active_server, &standby_server], config);
+
+    assert_eq!(failover_slots(&mut failover, 1).await, [1]);
+    // Only the first stall passes before the timeout, the second one would
+    // rank the active endpoint below the standby.
+    let next = tokio::time::timeout(Duration::from_millis(300), failover.message()).await;
+    assert!(next.is_err(), "{next:?}");
+    assert_eq!(failover.active(), 0);
+    assert_eq!(failover.switchovers(), 0);
+    assert_eq!(standby.subscribes(), 0);
+}
+
+#[tokio::test]
+async fn switches_after_lagging_probes_in_a_row() {
+    let active = MockGeyser::new();
+    active.set_slot(100);
+    active.push_subscription([slot(100)]);
+    let standby = standby();
+    standby.set_slot(200);
+    standby.push_subscription([slot(200)]);
+    let active_server = active.serve().await.unwrap();
+    let standby_server = standby.clone().serve().await.unwrap();
+
+    let config = FailoverConfig {
+        health_check_interval: Duration::from_millis(50),
+        max_slot_lag: 10,
+        lag_probes: 3,
+        ..failover_config()
+    };
+    let started = Instant::now();
+    let mut failover = failover([&active_server, &standby_server], config);
+
+    assert_eq!(failover_slots(&mut failover, 2).await, [100, 200]);
+    // Probes run right away and then every 50ms, the third one switches.
+    assert!(started.elapsed() >= Duration::from_millis(100));
+    assert_eq!(
+        failover.last_switch().map(|switch| switch.reason),
+        Some(SwitchReason::Lagging)
+    );
+    assert_eq!(failover.health()[0].slot, Some(100));
+    assert_eq!(failover.health()[1].slot, Some(200));
+    assert_eq!(requests(&standby, 1).await[0].from_slot, Some(100));
+}
+
+#[tokio::test]
+async fn switches_after_failed_probes_in_a_row() {
+    let active = MockGeyser::new();
+    for _ in 0..3 {
+        active.fail_next(MockRpc::Ping, Status::unavailable("down"));
+    }
+    active.push_subscription([slot(1)]);
+    let standby = standby();
+    standby.push_subscription([slot(2)]);
+    let active_server = active.serve().await.unwrap();
+    let standby_server = standby.clone().serve().await.unwrap();
+
+    let config = FailoverConfig {
+        health_check_interval: Duration::from_millis(50),
+        lag_probes: 3,
+        ..failover_config()
+    };
+    let started = Instant::now();
+    let mut failover = failover([&active_server, &standby_server], config);
+
+    assert_eq!(failover_slots(&mut failover, 2).await, [1, 2]);
+    assert!(started.elapsed() >= Duration::from_millis(100));
+    assert_eq!(
+        failover.last_switch().map(|switch| switch.reason),
+        Some(SwitchReason::Unhealthy)
+    );
+    assert_eq!(requests(&standby, 1).await[0].from_slot, Some(1));
+}
+
+#[tokio::test]
+async fn keeps_endpoint_delivering_pongs() {
+    let active = MockGeyser::new();
+    for _ in 0..3 {
+        active.fail_next(MockRpc::Ping, Status::unavailable("down"));
+    }
+    active.push_subscription([slot(1)]);
+    let standby = standby();
+    let active_server = active.serve().await.unwrap();
+    let standby_server = standby.clone().serve().await.unwrap();
+
+    let client_config = GeyserClientConfig {
+        keepalive: KeepaliveConfig {
+            ping_interval: Some(Duration::from_millis(10)),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let clients = vec![
+        GeyserClient::new(active_server.endpoint(), Some(client_config)).unwrap(),
+        GeyserClient::new(standby_server.endpoint(), None).unwrap(),
+    ];
+    let config = FailoverConfig {
+        health_check_interval: Duration::from_millis(50),
+        lag_probes: 3,
+        ..failover_config()
+    };
+    let mut failover = FailoverSubscription::new(clients, request(), config).unwrap();
+
+    assert_eq!(failover_slots(&mut failover, 1).await, [1]);
+    let next = tokio::time::timeout(Duration::from_millis(300), failover.message()).await;
+    assert!(next.is_err(), "{next:?}");
+    assert_eq!(failover.switchovers(), 0);
+    assert_eq!(standby.subscribes(), 0);
+}
+
+#[tokio::test]
+async fn gives_up_once_every_endpoint_failed() {
+    let active = MockGeyser::new();
+    active.push_subscription([
+        slot(1),
+        MockEvent::Delay(Duration::from_millis(100)),
+        MockEvent::Status(Code::Unavailable, "going away".to_string()),
+    ]);
+    let standby = standby();
+    standby.push_subscription([MockEvent::Status(
+        Code::Unavailable,
+        "standby going away".to_string(),
+    )]);
+    let active_server = active.serve().await.unwrap();
+    let standby_server = standby.serve().await.unwrap();
+
+    let mut failover = failover([&active_server, &standby_server], failover_config());
+
+    assert_eq!(failover_slots(&mut failover, 1).await, [1]);
+    let error = failover.message().await.unwrap_err();
+    assert_eq!(error.status().unwrap().message(), "standby going away");
+    assert_eq!(failover.switchovers(), 1);
+    assert_eq!(failover.health()[1].failures, 1);
+}
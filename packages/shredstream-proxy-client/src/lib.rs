@@ -4,13 +4,18 @@
 extern crate napi_derive;
 
 pub mod decode;
+#[cfg(feature = "tracing")]
+pub mod logging {
+    shredstream_proxy_client::logging::napi_logger!();
+}
 pub mod types;
 
-use crate::types::{DecodedShredstreamEntry, ShredstreamClientConfig};
+use crate::types::{client_config, DecodedShredstreamEntry, ShredstreamClientConfig};
 use napi::{
     threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode},
     tokio::task::JoinHandle,
 };
+use shredstream_proxy_client::auth::RotatingMetadata;
 use solana_entry_decoder::decode_entries;
 use types::{ShredstreamEntriesRequest, ShredstreamEntry};
 
@@ -34,6 +39,7 @@ impl ShredstreamSubscription {
 #[napi]
 pub struct ShredstreamClient {
     client: shredstream_proxy_client::ShredstreamClient,
+    authorization: RotatingMetadata,
 }
 
 #[napi]
@@ -41,15 +47,53 @@ pub fn create_shredstream_client(
     endpoint: String,
     config: Option<ShredstreamClientConfig>,
 ) -> napi::Result<ShredstreamClient> {
-    let client =
-        shredstream_proxy_client::ShredstreamClient::new(endpoint, config.map(|x| x.into()))
-            .map_err(|e| napi::Error::from_reason(e.to_string()))?;
+    let authorization = RotatingMetadata::authorization();
+    let config = client_config(config, &authorization)?;
+    let client = shredstream_proxy_client::ShredstreamClient::new(endpoint, Some(config))
+        .map_err(|e| napi::Error::from_reason(e.to_string()))?;
+
+    Ok(ShredstreamClient {
+        client,
+        authorization,
+    })
+}
 
-    Ok(ShredstreamClient { client })
+/// Like `createShredstreamClient`, but connects right away and rejects if the
+/// endpoint is unreachable.
+#[napi]
+pub async fn connect_shredstream_client(
+    endpoint: String,
+    config: Option<ShredstreamClientConfig>,
+) -> napi::Result<ShredstreamClient> {
+    let authorization = RotatingMetadata::authorization();
+    let config = client_config(config, &authorization)?;
+    let client = shredstream_proxy_client::ShredstreamClient::connect(endpoint, Some(config))
+        .await
+        .map_err(|e| napi::Error::from_reason(e.to_string()))?;
+
+    Ok(ShredstreamClient {
+        client,
+        authorization,
+    })
 }
 
 #[napi]
 impl ShredstreamClient {
+    /// Replaces the bearer token sent on every following request, `null` stops sending it.
+    #[napi]
+    pub fn set_bearer_token(&self, token: Option<String>) -> napi::Result<()> {
+        match token {
+            Some(token) => self
+                .authorization
+                .set_bearer(&token)
+                .map_err(|e| napi::Error::from_reason(e.to_string())),
+            None => {
+                self.authorization.clear();
+                Ok(())
+            }
+        }
+    }
+
     #[napi]
     pub fn subscribe_entries(
         &self,
@@ -122,8 +166,16 @@ impl ShredstreamClient {
                     .await
                     .map_err(|e| napi::Error::from_reason(e.to_string()))?
                 {
-                    let decoded_entry = decode_entries(entry.entries)
-                        .map_err(|e| napi::Error::from_reason(e.to_string()))?;
+                    let decoded_entry = decode_entries(entry.entries).map_err(|e| {
+                        #[cfg(feature = "tracing")]
+                        shredstream_proxy_client::logging::tracing::warn!(
+                            endpoint = client.endpoint_url(),
+                            slot = entry.slot,
+                            error = %e,
+                            "failed to decode entries"
+                        );
+                        napi::Error::from_reason(e.to_string())
+                    })?;
 
                     on_entry.call(
                         Ok(DecodedShredstreamEntry {
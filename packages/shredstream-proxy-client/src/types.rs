@@ -1,21 +1,144 @@
 use napi::bindgen_prelude::BigInt;
-use std::collections::HashMap;
+use shredstream_proxy_client::auth::{RotatingMetadata, StaticMetadata};
+use std::{collections::HashMap, str::FromStr, sync::Arc, time::Duration};
+use tonic::metadata::{AsciiMetadataKey, AsciiMetadataValue};
 
 #[napi(object)]
 pub struct ShredstreamClientConfig {
     pub x_token: Option<String>,
     pub x_request_snapshot: Option<bool>,
+    /// Sent as `authorization: Bearer <token>`, can be replaced with `setBearerToken`.
+    pub bearer_token: Option<String>,
+    /// Extra metadata added to every request.
+    pub metadata: Option<HashMap<String, String>>,
     pub max_decoding_message_size: Option<u32>,
     pub max_encoding_message_size: Option<u32>,
+    pub transport: Option<TransportConfig>,
+    pub tls: Option<TlsConfig>,
 }
 
-impl From<ShredstreamClientConfig> for shredstream_proxy_client::ShredstreamClientConfig {
-    fn from(config: ShredstreamClientConfig) -> Self {
-        shredstream_proxy_client::ShredstreamClientConfig {
+impl TryFrom<ShredstreamClientConfig> for shredstream_proxy_client::ShredstreamClientConfig {
+    type Error = napi::Error;
+
+    fn try_from(config: ShredstreamClientConfig) -> napi::Result<Self> {
+        let mut metadata = StaticMetadata::new();
+        let entries = config
+            .x_token
+            .map(|x_token| ("x-token".to_string(), x_token))
+            .into_iter()
+            .chain(config.metadata.unwrap_or_default());
+        for (key, value) in entries {
+            let (key, value) = metadata_entry(&key, &value)?;
+            metadata = metadata.insert(key, value);
+        }
+
+        Ok(shredstream_proxy_client::ShredstreamClientConfig {
+            auth: vec![Arc::new(metadata)],
             send_compressed: None,
             accept_compressed: None,
             max_decoding_message_size: config.max_decoding_message_size.map(|x| x as usize),
             max_encoding_message_size: config.max_encoding_message_size.map(|x| x as usize),
+            transport: config.transport.map(|x| x.into()).unwrap_or_default(),
+            tls: config.tls.map(|x| x.into()).unwrap_or_default(),
+            metrics: None,
+        })
+    }
+}
+
+/// Converts the JS config and appends the client's `authorization` entry, which
+/// stays replaceable after the client is created.
+pub(crate) fn client_config(
+    config: Option<ShredstreamClientConfig>,
+    authorization: &RotatingMetadata,
+) -> napi::Result<shredstream_proxy_client::ShredstreamClientConfig> {
+    if let Some(token) = config.as_ref().and_then(|x| x.bearer_token.as_deref()) {
+        authorization
+            .set_bearer(token)
+            .map_err(|e| napi::Error::from_reason(e.to_string()))?;
+    }
+
+    let mut config: shredstream_proxy_client::ShredstreamClientConfig = config
+        .map(|x| x.try_into())
+        .transpose()?
+        .unwrap_or_default();
+    config.auth.push(Arc::new(authorization.clone()));
+
+    Ok(config)
+}
+
+fn metadata_entry(key: &str, value: &str) -> napi::Result<(AsciiMetadataKey, AsciiMetadataValue)> {
+    let key =
+        AsciiMetadataKey::from_str(key).map_err(|e| napi::Error::from_reason(e.to_string()))?;
+    let value =
+        AsciiMetadataValue::from_str(value).map_err(|e| napi::Error::from_reason(e.to_string()))?;
+
+    Ok((key, value))
+}
+
+#[napi(object)]
+pub struct TlsConfig {
+    /// Connect over plaintext HTTP/2, the endpoint must use `http://`.
+    pub plaintext: Option<bool>,
+    /// PEM encoded CA bundle. Replaces the public roots unless `publicRoots` is set.
+    pub ca_certificate: Option<String>,
+    pub public_roots: Option<bool>,
+    /// PEM encoded client certificate for mutual TLS.
+    pub client_certificate: Option<String>,
+    /// PEM encoded client key for mutual TLS.
+    pub client_key: Option<String>,
+    pub domain_name: Option<String>,
+}
+
+impl From<TlsConfig> for shredstream_proxy_client::TlsConfig {
+    fn from(config: TlsConfig) -> Self {
+        let mut tls = shredstream_proxy_client::TlsConfig::default();
+        if let Some(ca_certificate) = config.ca_certificate {
+            tls = tls.with_ca_certificate(ca_certificate);
+        }
+        if let (Some(certificate), Some(key)) = (config.client_certificate, config.client_key) {
+            tls = tls.with_identity(certificate, key);
+        }
+        if let Some(domain_name) = config.domain_name {
+            tls = tls.with_domain_name(domain_name);
+        }
+        if let Some(public_roots) = config.public_roots {
+            tls.public_roots = public_roots;
+        }
+        tls.plaintext = config.plaintext.unwrap_or(false);
+
+        tls
+    }
+}
+
+#[napi(object)]
+pub struct TransportConfig {
+    pub http2_keep_alive_interval_ms: Option<u32>,
+    pub keep_alive_timeout_ms: Option<u32>,
+    pub keep_alive_while_idle: Option<bool>,
+    pub tcp_keepalive_ms: Option<u32>,
+    pub tcp_nodelay: Option<bool>,
+    pub connect_timeout_ms: Option<u32>,
+    pub request_timeout_ms: Option<u32>,
+    pub initial_stream_window_size: Option<u32>,
+    pub initial_connection_window_size: Option<u32>,
+    pub http2_adaptive_window: Option<bool>,
+}
+
+impl From<TransportConfig> for shredstream_proxy_client::TransportConfig {
+    fn from(config: TransportConfig) -> Self {
+        let millis = |x: u32| Duration::from_millis(x.into());
+
+        shredstream_proxy_client::TransportConfig {
+            http2_keep_alive_interval: config.http2_keep_alive_interval_ms.map(millis),
+            keep_alive_timeout: config.keep_alive_timeout_ms.map(millis),
+            keep_alive_while_idle: config.keep_alive_while_idle,
+            tcp_keepalive: config.tcp_keepalive_ms.map(millis),
+            tcp_nodelay: config.tcp_nodelay,
+            connect_timeout: config.connect_timeout_ms.map(millis),
+            request_timeout: config.request_timeout_ms.map(millis),
+            initial_stream_window_size: config.initial_stream_window_size,
+            initial_connection_window_size: config.initial_connection_window_size,
+            http2_adaptive_window: config.http2_adaptive_window,
         }
     }
 }
@@ -133,7 +256,7 @@ impl From<ShredstreamEntriesRequest> for shredstream_proxy_client::proto::Subscr
 
             commitment: request
                 .commitment
-                .map(|c| ShredstreamCommitmentLevel::from(c) as i32),
+                .map(|c| shredstream_proxy_client::proto::CommitmentLevel::from(c) as i32),
         }
     }
 }
@@ -249,16 +372,16 @@ pub struct VersionedMessage {
 impl From<solana_message::VersionedMessage> for VersionedMessage {
     fn from(message: solana_message::VersionedMessage) -> Self {
         let mut new_message = VersionedMessage {
-            header: message.header().clone().into(),
+            header: (*message.header()).into(),
             account_keys: message
                 .static_account_keys()
-                .into_iter()
+                .iter()
                 .map(|key| key.to_string())
                 .collect(),
             recent_blockhash: message.recent_blockhash().to_string(),
             instructions: message
                 .instructions()
-                .into_iter()
+                .iter()
                 .map(|instruction| instruction.clone().into())
                 .collect(),
             address_table_lookups: None,
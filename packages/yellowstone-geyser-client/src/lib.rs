@@ -3,23 +3,73 @@
 #[macro_use]
 extern crate napi_derive;
 
+#[cfg(feature = "tracing")]
+pub mod logging {
+    yellowstone_geyser_client::logging::napi_logger!();
+}
+pub mod token;
 pub mod types;
 
-use crate::types::{GeyserClientConfig, SubscribeRequest, SubscribeUpdate};
+use crate::types::{
+    client_config, FromSlotReplay, GeyserClientConfig, ReconnectConfig,
+    SubscribeReplayInfoResponse, SubscribeRequest, SubscribeUpdate, UpdateLatency,
+};
+use napi::bindgen_prelude::BigInt;
 use napi::{
     threadsafe_function::{ThreadsafeFunction, ThreadsafeFunctionCallMode},
-    tokio::task::JoinHandle,
+    tokio::{sync::mpsc, task::JoinHandle},
     Error, Result,
 };
+use yellowstone_geyser_client::{
+    auth::RotatingMetadata, keepalive::KeepaliveStats, latency::LatencyStats,
+    reconnect::ResilientSubscription,
+};
 
 #[napi]
 pub struct GeyserSubscription {
     task_handle: JoinHandle<napi::Result<()>>,
     on_close: Option<ThreadsafeFunction<()>>,
+    request_sender:
+        mpsc::UnboundedSender<yellowstone_geyser_client::proto::geyser::SubscribeRequest>,
+    keepalive_stats: KeepaliveStats,
+    latency_stats: LatencyStats,
 }
 
 #[napi]
 impl GeyserSubscription {
+    #[napi]
+    pub fn update(&self, subscribe_request: SubscribeRequest) -> Result<()> {
+        self.request_sender
+            .send(subscribe_request.into())
+            .map_err(|_| Error::from_reason("subscription is closed"))
+    }
+
+    /// Round-trip time of the last answered keepalive ping in milliseconds.
+    #[napi]
+    pub fn ping_rtt_ms(&self) -> Option<f64> {
+        self.keepalive_stats
+            .last_rtt()
+            .map(|rtt| rtt.as_secs_f64() * 1000.0)
+    }
+
+    /// Latency of received updates against their `createdAt`, and slot lag
+    /// against the highest known slot, per update type.
+    #[napi]
+    pub fn latency(&self) -> Vec<UpdateLatency> {
+        UpdateLatency::from_stats(&self.latency_stats)
+    }
+
+    /// Raises the slot used as tip for slot lag, e.g. one seen by another provider.
+    #[napi]
+    pub fn set_tip(&self, slot: BigInt) {
+        self.latency_stats.set_tip(slot.get_u64().1);
+    }
+
+    #[napi]
+    pub fn reset_latency(&self) {
+        self.latency_stats.reset();
+    }
+
     #[napi]
     pub fn close(&mut self) {
         self.on_close
@@ -32,6 +82,8 @@ impl GeyserSubscription {
 #[napi]
 pub struct GeyserClient {
     client: yellowstone_geyser_client::GeyserClient,
+    authorization: RotatingMetadata,
+    decode_token_accounts: bool,
 }
 
 #[napi]
@@ -39,38 +91,106 @@ pub fn create_geyser_client(
     endpoint: String,
     config: Option<GeyserClientConfig>,
 ) -> Result<GeyserClient> {
-    let client = yellowstone_geyser_client::GeyserClient::new(endpoint, config.map(|x| x.into()))
+    let authorization = RotatingMetadata::authorization();
+    let decode_token_accounts = decode_token_accounts(&config);
+    let config = client_config(config, &authorization)?;
+    let client = yellowstone_geyser_client::GeyserClient::new(endpoint, Some(config))
         .map_err(|e| Error::from_reason(e.to_string()))?;
 
-    Ok(GeyserClient { client })
+    Ok(GeyserClient {
+        client,
+        authorization,
+        decode_token_accounts,
+    })
+}
+
+/// Like `createGeyserClient`, but connects right away and rejects if the
+/// endpoint is unreachable.
+#[napi]
+pub async fn connect_geyser_client(
+    endpoint: String,
+    config: Option<GeyserClientConfig>,
+) -> Result<GeyserClient> {
+    let authorization = RotatingMetadata::authorization();
+    let decode_token_accounts = decode_token_accounts(&config);
+    let config = client_config(config, &authorization)?;
+    let client = yellowstone_geyser_client::GeyserClient::connect(endpoint, Some(config))
+        .await
+        .map_err(|e| Error::from_reason(e.to_string()))?;
+
+    Ok(GeyserClient {
+        client,
+        authorization,
+        decode_token_accounts,
+    })
+}
+
+fn decode_token_accounts(config: &Option<GeyserClientConfig>) -> bool {
+    config
+        .as_ref()
+        .and_then(|x| x.decode_token_accounts)
+        .unwrap_or(false)
 }
 
 #[napi]
 impl GeyserClient {
+    /// Replaces the bearer token sent on every following request, `null` stops sending it.
+    #[napi]
+    pub fn set_bearer_token(&self, token: Option<String>) -> Result<()> {
+        match token {
+            Some(token) => self
+                .authorization
+                .set_bearer(&token)
+                .map_err(|e| Error::from_reason(e.to_string())),
+            None => {
+                self.authorization.clear();
+                Ok(())
+            }
+        }
+    }
+
     #[napi]
     pub fn subscribe(
         &self,
         subscribe_request: Option<SubscribeRequest>,
         on_update: ThreadsafeFunction<SubscribeUpdate>,
         on_close: Option<ThreadsafeFunction<()>>,
+        reconnect: Option<ReconnectConfig>,
     ) -> Result<GeyserSubscription> {
-        let mut client = self.client.clone();
+        let mut subscription = ResilientSubscription::new(
+            self.client.clone(),
+            subscribe_request.map(|x| x.into()).unwrap_or_default(),
+            reconnect
+                .map(|x| x.into())
+                .unwrap_or_else(yellowstone_geyser_client::reconnect::ReconnectConfig::disabled),
+        );
+        let keepalive_stats = subscription.keepalive_stats();
+        let latency_stats = subscription.latency_stats();
         let on_update = on_update.clone();
         let on_close_clone = on_close.clone();
+        let decode_token_accounts = self.decode_token_accounts;
+        let (request_sender, mut request_receiver) = mpsc::unbounded_channel();
 
         let task_handle = napi::tokio::spawn(async move {
             let result = async {
-                let mut stream = client
-                    .subscribe(subscribe_request.map(|x| x.into()).unwrap_or_default())
-                    .await
-                    .map_err(|e| Error::from_reason(e.to_string()))?;
-
-                while let Some(update) = stream
-                    .message()
-                    .await
-                    .map_err(|e| Error::from_reason(e.to_string()))?
-                {
-                    on_update.call(Ok(update.into()), ThreadsafeFunctionCallMode::NonBlocking);
+                loop {
+                    napi::tokio::select! {
+                        Some(request) = request_receiver.recv() => {
+                            subscription.update(request);
+                        }
+                        message = subscription.timestamped_message() => {
+                            match message.map_err(|e| Error::from_reason(e.to_string()))? {
+                                Some(update) => {
+                                    let mut update: SubscribeUpdate = update.into();
+                                    if decode_token_accounts {
+                                        update.decode_token();
+                                    }
+                                    on_update.call(Ok(update), ThreadsafeFunctionCallMode::NonBlocking);
+                                }
+                                None => break,
+                            }
+                        }
+                    }
                 }
 
                 Ok::<(), Error>(())
@@ -87,6 +207,33 @@ impl GeyserClient {
         Ok(GeyserSubscription {
             task_handle,
             on_close,
+            request_sender,
+            keepalive_stats,
+            latency_stats,
         })
     }
+
+    #[napi]
+    pub async fn subscribe_replay_info(&self) -> Result<SubscribeReplayInfoResponse> {
+        let mut client = self.client.clone();
+        let response = client
+            .subscribe_replay_info()
+            .await
+            .map_err(|e| Error::from_reason(e.to_string()))?;
+
+        Ok(response.into())
+    }
+
+    /// Clamps `fromSlot` to the oldest slot the server can replay, returns
+    /// `null` if the server keeps no replay history.
+    #[napi]
+    pub async fn clamp_from_slot(&self, from_slot: BigInt) -> Result<Option<FromSlotReplay>> {
+        let mut client = self.client.clone();
+        let replay = client
+            .clamp_from_slot(from_slot.get_u64().1)
+            .await
+            .map_err(|e| Error::from_reason(e.to_string()))?;
+
+        Ok(replay.map(|x| x.into()))
+    }
 }
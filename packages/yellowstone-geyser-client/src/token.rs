@@ -0,0 +1,497 @@
+use napi::{bindgen_prelude::BigInt, Error, Result};
+use yellowstone_geyser_client::token;
+
+/// A decoded SPL Token or Token-2022 account, exactly one field is set.
+#[napi(object)]
+pub struct TokenAccountData {
+    pub account: Option<TokenAccount>,
+    pub mint: Option<TokenMint>,
+    pub multisig: Option<TokenMultisig>,
+}
+
+impl From<token::TokenAccountData> for TokenAccountData {
+    fn from(data: token::TokenAccountData) -> Self {
+        let mut new_data = TokenAccountData {
+            account: None,
+            mint: None,
+            multisig: None,
+        };
+
+        match data {
+            token::TokenAccountData::Account(account) => new_data.account = Some(account.into()),
+            token::TokenAccountData::Mint(mint) => new_data.mint = Some(mint.into()),
+            token::TokenAccountData::Multisig(multisig) => {
+                new_data.multisig = Some(multisig.into())
+            }
+        }
+
+        new_data
+    }
+}
+
+#[napi]
+pub enum TokenAccountState {
+    Uninitialized = 0,
+    Initialized = 1,
+    Frozen = 2,
+}
+
+impl From<token::TokenAccountState> for TokenAccountState {
+    fn from(state: token::TokenAccountState) -> Self {
+        match state {
+            token::TokenAccountState::Uninitialized => Self::Uninitialized,
+            token::TokenAccountState::Initialized => Self::Initialized,
+            token::TokenAccountState::Frozen => Self::Frozen,
+        }
+    }
+}
+
+#[napi(object)]
+pub struct TokenAccount {
+    pub program_id: Vec<u8>,
+    pub mint: Vec<u8>,
+    pub owner: Vec<u8>,
+    pub amount: BigInt,
+    pub delegate: Option<Vec<u8>>,
+    pub state: TokenAccountState,
+    pub is_native: Option<BigInt>,
+    pub delegated_amount: BigInt,
+    pub close_authority: Option<Vec<u8>>,
+    pub extensions: TokenExtensions,
+}
+
+impl From<token::TokenAccount> for TokenAccount {
+    fn from(account: token::TokenAccount) -> Self {
+        TokenAccount {
+            program_id: account.program.program_id().to_vec(),
+            mint: account.mint.to_vec(),
+            owner: account.owner.to_vec(),
+            amount: account.amount.into(),
+            delegate: account.delegate.map(|x| x.to_vec()),
+            state: account.state.into(),
+            is_native: account.is_native.map(|x| x.into()),
+            delegated_amount: account.delegated_amount.into(),
+            close_authority: account.close_authority.map(|x| x.to_vec()),
+            extensions: account.extensions.into(),
+        }
+    }
+}
+
+#[napi(object)]
+pub struct TokenMint {
+    pub program_id: Vec<u8>,
+    pub mint_authority: Option<Vec<u8>>,
+    pub supply: BigInt,
+    pub decimals: u32,
+    pub is_initialized: bool,
+    pub freeze_authority: Option<Vec<u8>>,
+    pub extensions: TokenExtensions,
+}
+
+impl From<token::TokenMint> for TokenMint {
+    fn from(mint: token::TokenMint) -> Self {
+        TokenMint {
+            program_id: mint.program.program_id().to_vec(),
+            mint_authority: mint.mint_authority.map(|x| x.to_vec()),
+            supply: mint.supply.into(),
+            decimals: mint.decimals.into(),
+            is_initialized: mint.is_initialized,
+            freeze_authority: mint.freeze_authority.map(|x| x.to_vec()),
+            extensions: mint.extensions.into(),
+        }
+    }
+}
+
+#[napi(object)]
+pub struct TokenMultisig {
+    pub program_id: Vec<u8>,
+    pub m: u32,
+    pub n: u32,
+    pub is_initialized: bool,
+    pub signers: Vec<Vec<u8>>,
+}
+
+impl From<token::TokenMultisig> for TokenMultisig {
+    fn from(multisig: token::TokenMultisig) -> Self {
+        TokenMultisig {
+            program_id: multisig.program.program_id().to_vec(),
+            m: multisig.m.into(),
+            n: multisig.n.into(),
+            is_initialized: multisig.is_initialized,
+            signers: multisig.signers.iter().map(|x| x.to_vec()).collect(),
+        }
+    }
+}
+
+/// Token-2022 extensions of an account or mint, unset when not present.
+#[napi(object)]
+#[derive(Default)]
+pub struct TokenExtensions {
+    pub transfer_fee_config: Option<TransferFeeConfig>,
+    pub transfer_fee_amount: Option<TransferFeeAmount>,
+    pub mint_close_authority: Option<MintCloseAuthority>,
+    pub default_account_state: Option<DefaultAccountState>,
+    pub immutable_owner: bool,
+    pub memo_transfer: Option<MemoTransfer>,
+    pub non_transferable: bool,
+    pub interest_bearing_config: Option<InterestBearingConfig>,
+    pub cpi_guard: Option<CpiGuard>,
+    pub permanent_delegate: Option<PermanentDelegate>,
+    pub non_transferable_account: bool,
+    pub transfer_hook: Option<TransferHook>,
+    pub transfer_hook_account: Option<TransferHookAccount>,
+    pub metadata_pointer: Option<MetadataPointer>,
+    pub token_metadata: Option<TokenMetadata>,
+    pub group_pointer: Option<GroupPointer>,
+    pub token_group: Option<TokenGroup>,
+    pub group_member_pointer: Option<GroupMemberPointer>,
+    pub token_group_member: Option<TokenGroupMember>,
+    pub scaled_ui_amount: Option<ScaledUiAmount>,
+    pub pausable: Option<Pausable>,
+    pub pausable_account: bool,
+    /// Extensions that are not decoded, such as confidential transfers.
+    pub other: Vec<OtherExtension>,
+}
+
+impl From<Vec<token::TokenExtension>> for TokenExtensions {
+    fn from(extensions: Vec<token::TokenExtension>) -> Self {
+        use token::TokenExtension;
+
+        let pubkey = |pubkey: Option<[u8; 32]>| pubkey.map(|x| x.to_vec());
+        let mut new_extensions = TokenExtensions::default();
+
+        for extension in extensions {
+            match extension {
+                TokenExtension::TransferFeeConfig {
+                    transfer_fee_config_authority,
+                    withdraw_withheld_authority,
+                    withheld_amount,
+                    older_transfer_fee,
+                    newer_transfer_fee,
+                } => {
+                    new_extensions.transfer_fee_config = Some(TransferFeeConfig {
+                        transfer_fee_config_authority: pubkey(transfer_fee_config_authority),
+                        withdraw_withheld_authority: pubkey(withdraw_withheld_authority),
+                        withheld_amount: withheld_amount.into(),
+                        older_transfer_fee: older_transfer_fee.into(),
+                        newer_transfer_fee: newer_transfer_fee.into(),
+                    })
+                }
+                TokenExtension::TransferFeeAmount { withheld_amount } => {
+                    new_extensions.transfer_fee_amount = Some(TransferFeeAmount {
+                        withheld_amount: withheld_amount.into(),
+                    })
+                }
+                TokenExtension::MintCloseAuthority { close_authority } => {
+                    new_extensions.mint_close_authority = Some(MintCloseAuthority {
+                        close_authority: pubkey(close_authority),
+                    })
+                }
+                TokenExtension::DefaultAccountState { state } => {
+                    new_extensions.default_account_state = Some(DefaultAccountState {
+                        state: state.into(),
+                    })
+                }
+                TokenExtension::ImmutableOwner => new_extensions.immutable_owner = true,
+                TokenExtension::MemoTransfer {
+                    require_incoming_transfer_memos,
+                } => {
+                    new_extensions.memo_transfer = Some(MemoTransfer {
+                        require_incoming_transfer_memos,
+                    })
+                }
+                TokenExtension::NonTransferable => new_extensions.non_transferable = true,
+                TokenExtension::InterestBearingConfig {
+                    rate_authority,
+                    initialization_timestamp,
+                    pre_update_average_rate,
+                    last_update_timestamp,
+                    current_rate,
+                } => {
+                    new_extensions.interest_bearing_config = Some(InterestBearingConfig {
+                        rate_authority: pubkey(rate_authority),
+                        initialization_timestamp: initialization_timestamp.into(),
+                        pre_update_average_rate: pre_update_average_rate.into(),
+                        last_update_timestamp: last_update_timestamp.into(),
+                        current_rate: current_rate.into(),
+                    })
+                }
+                TokenExtension::CpiGuard { lock_cpi } => {
+                    new_extensions.cpi_guard = Some(CpiGuard { lock_cpi })
+                }
+                TokenExtension::PermanentDelegate { delegate } => {
+                    new_extensions.permanent_delegate = Some(PermanentDelegate {
+                        delegate: pubkey(delegate),
+                    })
+                }
+                TokenExtension::NonTransferableAccount => {
+                    new_extensions.non_transferable_account = true
+                }
+                TokenExtension::TransferHook {
+                    authority,
+                    program_id,
+                } => {
+                    new_extensions.transfer_hook = Some(TransferHook {
+                        authority: pubkey(authority),
+                        program_id: pubkey(program_id),
+                    })
+                }
+                TokenExtension::TransferHookAccount { transferring } => {
+                    new_extensions.transfer_hook_account =
+                        Some(TransferHookAccount { transferring })
+                }
+                TokenExtension::MetadataPointer {
+                    authority,
+                    metadata_address,
+                } => {
+                    new_extensions.metadata_pointer = Some(MetadataPointer {
+                        authority: pubkey(authority),
+                        metadata_address: pubkey(metadata_address),
+                    })
+                }
+                TokenExtension::TokenMetadata {
+                    update_authority,
+                    mint,
+                    name,
+                    symbol,
+                    uri,
+                    additional_metadata,
+                } => {
+                    new_extensions.token_metadata = Some(TokenMetadata {
+                        update_authority: pubkey(update_authority),
+                        mint: mint.to_vec(),
+                        name,
+                        symbol,
+                        uri,
+                        additional_metadata: additional_metadata
+                            .into_iter()
+                            .map(|(key, value)| vec![key, value])
+                            .collect(),
+                    })
+                }
+                TokenExtension::GroupPointer {
+                    authority,
+                    group_address,
+                } => {
+                    new_extensions.group_pointer = Some(GroupPointer {
+                        authority: pubkey(authority),
+                        group_address: pubkey(group_address),
+                    })
+                }
+                TokenExtension::TokenGroup {
+                    update_authority,
+                    mint,
+                    size,
+                    max_size,
+                } => {
+                    new_extensions.token_group = Some(TokenGroup {
+                        update_authority: pubkey(update_authority),
+                        mint: mint.to_vec(),
+                        size: size.into(),
+                        max_size: max_size.into(),
+                    })
+                }
+                TokenExtension::GroupMemberPointer {
+                    authority,
+                    member_address,
+                } => {
+                    new_extensions.group_member_pointer = Some(GroupMemberPointer {
+                        authority: pubkey(authority),
+                        member_address: pubkey(member_address),
+                    })
+                }
+                TokenExtension::TokenGroupMember {
+                    mint,
+                    group,
+                    member_number,
+                } => {
+                    new_extensions.token_group_member = Some(TokenGroupMember {
+                        mint: mint.to_vec(),
+                        group: group.to_vec(),
+                        member_number: member_number.into(),
+                    })
+                }
+                TokenExtension::ScaledUiAmount {
+                    authority,
+                    multiplier,
+                    new_multiplier_effective_timestamp,
+                    new_multiplier,
+                } => {
+                    new_extensions.scaled_ui_amount = Some(ScaledUiAmount {
+                        authority: pubkey(authority),
+                        multiplier,
+                        new_multiplier_effective_timestamp: new_multiplier_effective_timestamp
+                            .into(),
+                        new_multiplier,
+                    })
+                }
+                TokenExtension::Pausable { authority, paused } => {
+                    new_extensions.pausable = Some(Pausable {
+                        authority: pubkey(authority),
+                        paused,
+                    })
+                }
+                TokenExtension::PausableAccount => new_extensions.pausable_account = true,
+                TokenExtension::Other {
+                    extension_type,
+                    data,
+                } => new_extensions.other.push(OtherExtension {
+                    extension_type: extension_type.into(),
+                    data,
+                }),
+            }
+        }
+
+        new_extensions
+    }
+}
+
+#[napi(object)]
+pub struct TransferFee {
+    pub epoch: BigInt,
+    pub maximum_fee: BigInt,
+    pub transfer_fee_basis_points: u32,
+}
+
+impl From<token::TransferFee> for TransferFee {
+    fn from(fee: token::TransferFee) -> Self {
+        TransferFee {
+            epoch: fee.epoch.into(),
+            maximum_fee: fee.maximum_fee.into(),
+            transfer_fee_basis_points: fee.transfer_fee_basis_points.into(),
+        }
+    }
+}
+
+#[napi(object)]
+pub struct TransferFeeConfig {
+    pub transfer_fee_config_authority: Option<Vec<u8>>,
+    pub withdraw_withheld_authority: Option<Vec<u8>>,
+    pub withheld_amount: BigInt,
+    pub older_transfer_fee: TransferFee,
+    pub newer_transfer_fee: TransferFee,
+}
+
+#[napi(object)]
+pub struct TransferFeeAmount {
+    pub withheld_amount: BigInt,
+}
+
+#[napi(object)]
+pub struct MintCloseAuthority {
+    pub close_authority: Option<Vec<u8>>,
+}
+
+#[napi(object)]
+pub struct DefaultAccountState {
+    pub state: TokenAccountState,
+}
+
+#[napi(object)]
+pub struct MemoTransfer {
+    pub require_incoming_transfer_memos: bool,
+}
+
+#[napi(object)]
+pub struct InterestBearingConfig {
+    pub rate_authority: Option<Vec<u8>>,
+    pub initialization_timestamp: BigInt,
+    pub pre_update_average_rate: i32,
+    pub last_update_timestamp: BigInt,
+    pub current_rate: i32,
+}
+
+#[napi(object)]
+pub struct CpiGuard {
+    pub lock_cpi: bool,
+}
+
+#[napi(object)]
+pub struct PermanentDelegate {
+    pub delegate: Option<Vec<u8>>,
+}
+
+#[napi(object)]
+pub struct TransferHook {
+    pub authority: Option<Vec<u8>>,
+    pub program_id: Option<Vec<u8>>,
+}
+
+#[napi(object)]
+pub struct TransferHookAccount {
+    pub transferring: bool,
+}
+
+#[napi(object)]
+pub struct MetadataPointer {
+    pub authority: Option<Vec<u8>>,
+    pub metadata_address: Option<Vec<u8>>,
+}
+
+#[napi(object)]
+pub struct TokenMetadata {
+    pub update_authority: Option<Vec<u8>>,
+    pub mint: Vec<u8>,
+    pub name: String,
+    pub symbol: String,
+    pub uri: String,
+    /// `[key, value]` pairs.
+    pub additional_metadata: Vec<Vec<String>>,
+}
+
+#[napi(object)]
+pub struct GroupPointer {
+    pub authority: Option<Vec<u8>>,
+    pub group_address: Option<Vec<u8>>,
+}
+
+#[napi(object)]
+pub struct TokenGroup {
+    pub update_authority: Option<Vec<u8>>,
+    pub mint: Vec<u8>,
+    pub size: BigInt,
+    pub max_size: BigInt,
+}
+
+#[napi(object)]
+pub struct GroupMemberPointer {
+    pub authority: Option<Vec<u8>>,
+    pub member_address: Option<Vec<u8>>,
+}
+
+#[napi(object)]
+pub struct TokenGroupMember {
+    pub mint: Vec<u8>,
+    pub group: Vec<u8>,
+    pub member_number: BigInt,
+}
+
+#[napi(object)]
+pub struct ScaledUiAmount {
+    pub authority: Option<Vec<u8>>,
+    pub multiplier: f64,
+    pub new_multiplier_effective_timestamp: BigInt,
+    pub new_multiplier: f64,
+}
+
+#[napi(object)]
+pub struct Pausable {
+    pub authority: Option<Vec<u8>>,
+    pub paused: bool,
+}
+
+#[napi(object)]
+pub struct OtherExtension {
+    pub extension_type: u32,
+    pub data: Vec<u8>,
+}
+
+/// Decodes the data of an account owned by SPL Token or Token-2022, returns
+/// `null` for accounts of other programs.
+#[napi]
+pub fn decode_token_account(owner: Vec<u8>, data: Vec<u8>) -> Result<Option<TokenAccountData>> {
+    token::decode(&owner, &data)
+        .transpose()
+        .map(|x| x.map(|x| x.into()))
+        .map_err(|e| Error::from_reason(e.to_string()))
+}
@@ -1,26 +1,204 @@
-use std::{collections::HashMap, str::FromStr};
-
+use std::{
+    collections::HashMap,
+    str::FromStr,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+use crate::token::TokenAccountData;
 use napi::bindgen_prelude::BigInt;
-use tonic::metadata::MetadataValue;
-use yellowstone_geyser_client::proto::geyser::subscribe_update::UpdateOneof;
+use tonic::metadata::{AsciiMetadataKey, AsciiMetadataValue, MetadataValue};
+use yellowstone_geyser_client::{
+    auth::{RotatingMetadata, StaticMetadata},
+    latency::{LatencyStats, TimestampedUpdate, UpdateKind},
+    proto::geyser::subscribe_update::UpdateOneof,
+};
 
 #[napi(object)]
+#[derive(Default)]
 pub struct GeyserClientConfig {
     pub x_token: Option<String>,
     pub x_request_snapshot: Option<bool>,
+    /// Sent as `authorization: Bearer <token>`, can be replaced with `setBearerToken`.
+    pub bearer_token: Option<String>,
+    /// Extra metadata added to every request.
+    pub metadata: Option<HashMap<String, String>>,
     pub max_decoding_message_size: Option<u32>,
     pub max_encoding_message_size: Option<u32>,
-}
+    /// Interval of client pings on subscriptions, unset or `0` disables them.
+    pub ping_interval_ms: Option<u32>,
+    /// Answer server pings on subscriptions, off by default.
+    pub respond_to_pings: Option<bool>,
+    /// Pass ping and pong updates through to `onUpdate`, on by default.
+    pub forward_pings: Option<bool>,
+    /// Decode SPL Token and Token-2022 account updates into `token`, off by default.
+    pub decode_token_accounts: Option<bool>,
+    pub transport: Option<TransportConfig>,
+    pub tls: Option<TlsConfig>,
+}
+
+impl TryFrom<GeyserClientConfig> for yellowstone_geyser_client::GeyserClientConfig {
+    type Error = napi::Error;
+
+    fn try_from(config: GeyserClientConfig) -> napi::Result<Self> {
+        // Subscriptions used to hand every update to JS untouched, keep that unless asked.
+        let keepalive = yellowstone_geyser_client::keepalive::KeepaliveConfig {
+            respond_to_pings: config.respond_to_pings.unwrap_or(false),
+            ping_interval: config
+                .ping_interval_ms
+                .map(|ping_interval_ms| Duration::from_millis(ping_interval_ms.into()))
+                .filter(|x| !x.is_zero()),
+            forward_pings: config.forward_pings.unwrap_or(true),
+        };
+
+        let mut metadata = StaticMetadata::new();
+        for (key, value) in config.metadata.unwrap_or_default() {
+            let (key, value) = metadata_entry(&key, &value)?;
+            metadata = metadata.insert(key, value);
+        }
 
-impl From<GeyserClientConfig> for yellowstone_geyser_client::GeyserClientConfig {
-    fn from(config: GeyserClientConfig) -> Self {
-        yellowstone_geyser_client::GeyserClientConfig {
-            x_token: config.x_token.map(|x| MetadataValue::from_str(&x).unwrap()),
+        Ok(yellowstone_geyser_client::GeyserClientConfig {
+            x_token: config
+                .x_token
+                .map(|x| MetadataValue::from_str(&x))
+                .transpose()
+                .map_err(|e| napi::Error::from_reason(e.to_string()))?,
             x_request_snapshot: config.x_request_snapshot.unwrap_or(false),
+            auth: vec![Arc::new(metadata)],
             send_compressed: None,
             accept_compressed: None,
             max_decoding_message_size: config.max_decoding_message_size.map(|x| x as usize),
             max_encoding_message_size: config.max_encoding_message_size.map(|x| x as usize),
+            keepalive,
+            transport: config.transport.map(|x| x.into()).unwrap_or_default(),
+            tls: config.tls.map(|x| x.into()).unwrap_or_default(),
+            metrics: None,
+        })
+    }
+}
+
+/// Converts the JS config and appends the client's `authorization` entry, which
+/// stays replaceable after the client is created.
+pub(crate) fn client_config(
+    config: Option<GeyserClientConfig>,
+    authorization: &RotatingMetadata,
+) -> napi::Result<yellowstone_geyser_client::GeyserClientConfig> {
+    if let Some(token) = config.as_ref().and_then(|x| x.bearer_token.as_deref()) {
+        authorization
+            .set_bearer(token)
+            .map_err(|e| napi::Error::from_reason(e.to_string()))?;
+    }
+
+    let mut config: yellowstone_geyser_client::GeyserClientConfig =
+        config.unwrap_or_default().try_into()?;
+    config.auth.push(Arc::new(authorization.clone()));
+
+    Ok(config)
+}
+
+fn metadata_entry(key: &str, value: &str) -> napi::Result<(AsciiMetadataKey, AsciiMetadataValue)> {
+    let key =
+        AsciiMetadataKey::from_str(key).map_err(|e| napi::Error::from_reason(e.to_string()))?;
+    let value =
+        AsciiMetadataValue::from_str(value).map_err(|e| napi::Error::from_reason(e.to_string()))?;
+
+    Ok((key, value))
+}
+
+#[napi(object)]
+pub struct TlsConfig {
+    /// Connect over plaintext HTTP/2, the endpoint must use `http://`.
+    pub plaintext: Option<bool>,
+    /// PEM encoded CA bundle. Replaces the public roots unless `publicRoots` is set.
+    pub ca_certificate: Option<String>,
+    pub public_roots: Option<bool>,
+    /// PEM encoded client certificate for mutual TLS.
+    pub client_certificate: Option<String>,
+    /// PEM encoded client key for mutual TLS.
+    pub client_key: Option<String>,
+    pub domain_name: Option<String>,
+}
+
+impl From<TlsConfig> for yellowstone_geyser_client::TlsConfig {
+    fn from(config: TlsConfig) -> Self {
+        let mut tls = yellowstone_geyser_client::TlsConfig::default();
+        if let Some(ca_certificate) = config.ca_certificate {
+            tls = tls.with_ca_certificate(ca_certificate);
+        }
+        if let (Some(certificate), Some(key)) = (config.client_certificate, config.client_key) {
+            tls = tls.with_identity(certificate, key);
+        }
+        if let Some(domain_name) = config.domain_name {
+            tls = tls.with_domain_name(domain_name);
+        }
+        if let Some(public_roots) = config.public_roots {
+            tls.public_roots = public_roots;
+        }
+        tls.plaintext = config.plaintext.unwrap_or(false);
+
+        tls
+    }
+}
+
+#[napi(object)]
+pub struct TransportConfig {
+    pub http2_keep_alive_interval_ms: Option<u32>,
+    pub keep_alive_timeout_ms: Option<u32>,
+    pub keep_alive_while_idle: Option<bool>,
+    pub tcp_keepalive_ms: Option<u32>,
+    pub tcp_nodelay: Option<bool>,
+    pub connect_timeout_ms: Option<u32>,
+    pub request_timeout_ms: Option<u32>,
+    pub initial_stream_window_size: Option<u32>,
+    pub initial_connection_window_size: Option<u32>,
+    pub http2_adaptive_window: Option<bool>,
+}
+
+impl From<TransportConfig> for yellowstone_geyser_client::TransportConfig {
+    fn from(config: TransportConfig) -> Self {
+        let millis = |x: u32| Duration::from_millis(x.into());
+
+        yellowstone_geyser_client::TransportConfig {
+            http2_keep_alive_interval: config.http2_keep_alive_interval_ms.map(millis),
+            keep_alive_timeout: config.keep_alive_timeout_ms.map(millis),
+            keep_alive_while_idle: config.keep_alive_while_idle,
+            tcp_keepalive: config.tcp_keepalive_ms.map(millis),
+            tcp_nodelay: config.tcp_nodelay,
+            connect_timeout: config.connect_timeout_ms.map(millis),
+            request_timeout: config.request_timeout_ms.map(millis),
+            initial_stream_window_size: config.initial_stream_window_size,
+            initial_connection_window_size: config.initial_connection_window_size,
+            http2_adaptive_window: config.http2_adaptive_window,
+        }
+    }
+}
+
+#[napi(object)]
+pub struct ReconnectConfig {
+    pub initial_backoff_ms: Option<u32>,
+    pub max_backoff_ms: Option<u32>,
+    pub backoff_multiplier: Option<f64>,
+    /// Consecutive failed attempts before giving up, unlimited when unset.
+    pub max_attempts: Option<u32>,
+}
+
+impl From<ReconnectConfig> for yellowstone_geyser_client::reconnect::ReconnectConfig {
+    fn from(config: ReconnectConfig) -> Self {
+        let default = yellowstone_geyser_client::reconnect::ReconnectConfig::default();
+
+        yellowstone_geyser_client::reconnect::ReconnectConfig {
+            initial_backoff: config
+                .initial_backoff_ms
+                .map(|x| Duration::from_millis(x.into()))
+                .unwrap_or(default.initial_backoff),
+            max_backoff: config
+                .max_backoff_ms
+                .map(|x| Duration::from_millis(x.into()))
+                .unwrap_or(default.max_backoff),
+            backoff_multiplier: config
+                .backoff_multiplier
+                .unwrap_or(default.backoff_multiplier),
+            max_attempts: config.max_attempts,
         }
     }
 }
@@ -783,7 +961,12 @@ impl From<SubscribeRequestPing> for yellowstone_geyser_client::proto::geyser::Su
 #[napi(object)]
 pub struct SubscribeUpdate {
     pub filters: Vec<String>,
+    /// Whole seconds of `createdAtMs`.
     pub created_at: Option<u32>,
+    /// Server creation time in milliseconds since the epoch.
+    pub created_at_ms: Option<f64>,
+    /// Local receive time in milliseconds since the epoch.
+    pub received_at_ms: Option<f64>,
     pub account: Option<SubscribeUpdateAccount>,
     pub slot: Option<SubscribeUpdateSlot>,
     pub transaction: Option<SubscribeUpdateTransaction>,
@@ -800,6 +983,10 @@ impl From<yellowstone_geyser_client::proto::geyser::SubscribeUpdate> for Subscri
         let mut new_update = SubscribeUpdate {
             filters: update.filters,
             created_at: update.created_at.map(|x| x.seconds as u32),
+            created_at_ms: update
+                .created_at
+                .map(|x| x.seconds as f64 * 1000.0 + f64::from(x.nanos) / 1_000_000.0),
+            received_at_ms: None,
             account: None,
             slot: None,
             transaction: None,
@@ -846,6 +1033,82 @@ impl From<yellowstone_geyser_client::proto::geyser::SubscribeUpdate> for Subscri
     }
 }
 
+impl SubscribeUpdate {
+    /// Decodes the account of an account update, see [`SubscribeUpdateAccountInfo::decode_token`].
+    pub(crate) fn decode_token(&mut self) {
+        if let Some(info) = self
+            .account
+            .as_mut()
+            .and_then(|account| account.account.as_mut())
+        {
+            info.decode_token();
+        }
+    }
+}
+
+impl From<TimestampedUpdate> for SubscribeUpdate {
+    fn from(update: TimestampedUpdate) -> Self {
+        let received_at_ms = update
+            .received_at
+            .duration_since(SystemTime::UNIX_EPOCH)
+            .map(|x| x.as_secs_f64() * 1000.0)
+            .ok();
+
+        SubscribeUpdate {
+            received_at_ms,
+            ..update.update.into()
+        }
+    }
+}
+
+#[napi(object)]
+pub struct UpdateLatency {
+    /// Update type, e.g. `account` or `transaction`.
+    pub kind: String,
+    pub count: BigInt,
+    pub mean_ms: Option<f64>,
+    pub p50_ms: Option<f64>,
+    pub p90_ms: Option<f64>,
+    pub p99_ms: Option<f64>,
+    pub max_ms: Option<f64>,
+    /// Slots behind the known tip at receive time.
+    pub slot_lag_p50: Option<u32>,
+    pub slot_lag_p99: Option<u32>,
+    pub slot_lag_max: Option<u32>,
+}
+
+impl UpdateLatency {
+    /// One entry per update type received so far.
+    pub fn from_stats(stats: &LatencyStats) -> Vec<Self> {
+        let ms = |micros: u64| micros as f64 / 1000.0;
+        let slots = |slots: u64| u32::try_from(slots).unwrap_or(u32::MAX);
+
+        UpdateKind::ALL
+            .iter()
+            .filter_map(|kind| {
+                let latency = stats.latency(*kind);
+                let slot_lag = stats.slot_lag(*kind);
+                if latency.count() == 0 && slot_lag.count() == 0 {
+                    return None;
+                }
+
+                Some(UpdateLatency {
+                    kind: kind.as_str().to_owned(),
+                    count: latency.count().max(slot_lag.count()).into(),
+                    mean_ms: latency.mean().map(|x| x / 1000.0),
+                    p50_ms: latency.percentile(0.5).map(ms),
+                    p90_ms: latency.percentile(0.9).map(ms),
+                    p99_ms: latency.percentile(0.99).map(ms),
+                    max_ms: latency.max().map(ms),
+                    slot_lag_p50: slot_lag.percentile(0.5).map(slots),
+                    slot_lag_p99: slot_lag.percentile(0.99).map(slots),
+                    slot_lag_max: slot_lag.max().map(slots),
+                })
+            })
+            .collect()
+    }
+}
+
 #[napi(object)]
 pub struct SubscribeUpdateAccount {
     pub account: Option<SubscribeUpdateAccountInfo>,
@@ -875,6 +1138,22 @@ pub struct SubscribeUpdateAccountInfo {
     pub data: Vec<u8>,
     pub write_version: BigInt,
     pub txn_signature: Option<Vec<u8>>,
+    /// Decoded `data` of SPL Token and Token-2022 accounts, set when
+    /// `decodeTokenAccounts` is enabled.
+    pub token: Option<TokenAccountData>,
+    /// Why a token account could not be decoded, `token` is unset then.
+    pub token_error: Option<String>,
+}
+
+impl SubscribeUpdateAccountInfo {
+    /// Fills `token` or `tokenError` if the account is owned by a token program.
+    pub(crate) fn decode_token(&mut self) {
+        match yellowstone_geyser_client::token::decode(&self.owner, &self.data) {
+            Some(Ok(token)) => self.token = Some(token.into()),
+            Some(Err(error)) => self.token_error = Some(error.to_string()),
+            None => {}
+        }
+    }
 }
 
 impl From<yellowstone_geyser_client::proto::geyser::SubscribeUpdateAccountInfo>
@@ -890,6 +1169,8 @@ impl From<yellowstone_geyser_client::proto::geyser::SubscribeUpdateAccountInfo>
             data: info.data,
             write_version: info.write_version.into(),
             txn_signature: info.txn_signature,
+            token: None,
+            token_error: None,
         }
     }
 }
@@ -1093,3 +1374,35 @@ impl From<yellowstone_geyser_client::proto::geyser::SubscribeUpdatePong> for Sub
         SubscribeUpdatePong { id: pong.id }
     }
 }
+
+#[napi(object)]
+pub struct SubscribeReplayInfoResponse {
+    pub first_available: Option<BigInt>,
+}
+
+impl From<yellowstone_geyser_client::proto::geyser::SubscribeReplayInfoResponse>
+    for SubscribeReplayInfoResponse
+{
+    fn from(
+        response: yellowstone_geyser_client::proto::geyser::SubscribeReplayInfoResponse,
+    ) -> Self {
+        SubscribeReplayInfoResponse {
+            first_available: response.first_available.map(|x| x.into()),
+        }
+    }
+}
+
+#[napi(object)]
+pub struct FromSlotReplay {
+    pub from_slot: BigInt,
+    pub unrecoverable_slots: BigInt,
+}
+
+impl From<yellowstone_geyser_client::FromSlotReplay> for FromSlotReplay {
+    fn from(replay: yellowstone_geyser_client::FromSlotReplay) -> Self {
+        FromSlotReplay {
+            from_slot: replay.from_slot.into(),
+            unrecoverable_slots: replay.unrecoverable_slots.into(),
+        }
+    }
+}